@@ -0,0 +1,105 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::css;
+use crate::dom::{self, Node, NodeType};
+use crate::layout::{self, Dimensions, Rect};
+use crate::paint::{self, Canvas};
+use crate::style;
+
+// Metadata elements are never rendered, whatever the page's own stylesheet says.
+const USER_AGENT_CSS: &str = "head, link, meta, script, style, title { display: none; }";
+
+pub struct Browser {
+    base_dir: PathBuf,
+    viewport: Dimensions,
+}
+
+impl Browser {
+    pub fn new(base_dir: PathBuf) -> Browser {
+        Browser {
+            base_dir,
+            viewport: Dimensions {
+                content: Rect {
+                    x: 0.0,
+                    y: 0.0,
+                    width: 800.0,
+                    height: 600.0,
+                },
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Load `{base_dir}/{path}` and run it through the whole rendering pipeline.
+    pub fn navigate(&self, path: &str) -> Canvas {
+        let html_path = self.base_dir.join(path);
+        let html = fs::read_to_string(&html_path)
+            .unwrap_or_else(|e| panic!("failed to read {}: {}", html_path.display(), e));
+        let dom_tree = dom::parse(html);
+
+        let mut css_source = USER_AGENT_CSS.to_string();
+        let html_dir = html_path.parent().unwrap_or(&self.base_dir);
+        collect_stylesheets(&dom_tree, html_dir, &mut css_source);
+        let style_sheet = css::parse(css_source);
+
+        let style_tree = style::style_tree(&dom_tree, &style_sheet);
+        let layout_tree = layout::layout_tree(&style_tree, self.viewport);
+        paint::paint(&layout_tree, self.viewport.content)
+    }
+}
+
+// Append the contents of `<style>` elements and `<link rel="stylesheet">` files in document order.
+fn collect_stylesheets(node: &Node, dir: &Path, css_source: &mut String) {
+    if let NodeType::Element(ref elem) = node.node_type {
+        match elem.tag_name.as_str() {
+            "style" => {
+                for child in &node.children {
+                    if let NodeType::Text(ref text) = child.node_type {
+                        css_source.push('\n');
+                        css_source.push_str(text);
+                    }
+                }
+            }
+            "link" if elem.attributes.get("rel").map(String::as_str) == Some("stylesheet") => {
+                if let Some(href) = elem.attributes.get("href") {
+                    let css_path = dir.join(href);
+                    let css = fs::read_to_string(&css_path)
+                        .unwrap_or_else(|e| panic!("failed to read {}: {}", css_path.display(), e));
+                    css_source.push('\n');
+                    css_source.push_str(&css);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for child in &node.children {
+        collect_stylesheets(child, dir, css_source);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::Color;
+
+    #[test]
+    fn navigate_between_pages() {
+        let browser = Browser::new(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures"));
+        let black = Color {
+            r: 0,
+            g: 0,
+            b: 0,
+            a: 255,
+        };
+
+        let index = browser.navigate("index.html");
+        assert_eq!((index.width, index.height), (800, 600));
+        assert!(index.pixels.iter().any(|&pixel| pixel != black));
+
+        let about = browser.navigate("about.html");
+        assert_eq!((about.width, about.height), (800, 600));
+        assert_ne!(index.pixels, about.pixels);
+    }
+}
@@ -12,6 +12,10 @@ pub struct Rule {
 #[derive(Debug)]
 pub enum Selector {
     Simple(SimpleSelector),
+    // `A B`: B inside A at any depth.
+    Descendant(Box<Selector>, Box<Selector>),
+    // `A > B`: B directly inside A.
+    Child(Box<Selector>, Box<Selector>),
 }
 
 #[derive(Debug)]
@@ -31,7 +35,8 @@ pub struct Declaration {
 pub enum Value {
     Keyword(String),
     Length(f32, Unit),
-    ColorValue(Color),
+    Number(f32),
+    Color(Color),
 }
 
 impl Value {
@@ -43,6 +48,15 @@ impl Value {
     }
 }
 
+// The width of `medium`, used when the `border` shorthand leaves out the width.
+const BORDER_WIDTH_MEDIUM: f32 = 3.0;
+
+// Values of `border-style`. Only `none`, `hidden` and `solid` are distinguished when
+// painting; the rest are drawn as `solid`.
+const BORDER_STYLES: [&str; 10] = [
+    "none", "hidden", "solid", "dashed", "dotted", "double", "groove", "ridge", "inset", "outset",
+];
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Unit {
     Px,
@@ -56,16 +70,89 @@ pub struct Color {
     pub a: u8,
 }
 
+impl Color {
+    // https://www.w3.org/TR/css-color-3/#hsl-color
+    fn from_hsl(hue: f32, saturation: f32, lightness: f32, a: u8) -> Color {
+        let h = hue.rem_euclid(360.0) / 360.0;
+        let m2 = if lightness <= 0.5 {
+            lightness * (saturation + 1.0)
+        } else {
+            lightness + saturation - lightness * saturation
+        };
+        let m1 = lightness * 2.0 - m2;
+        let hue_to_rgb = |mut h: f32| {
+            if h < 0.0 {
+                h += 1.0;
+            }
+            if h > 1.0 {
+                h -= 1.0;
+            }
+            let value = if h * 6.0 < 1.0 {
+                m1 + (m2 - m1) * h * 6.0
+            } else if h * 2.0 < 1.0 {
+                m2
+            } else if h * 3.0 < 2.0 {
+                m1 + (m2 - m1) * (2.0 / 3.0 - h) * 6.0
+            } else {
+                m1
+            };
+            (value * 255.0).round() as u8
+        };
+        Color {
+            r: hue_to_rgb(h + 1.0 / 3.0),
+            g: hue_to_rgb(h),
+            b: hue_to_rgb(h - 1.0 / 3.0),
+            a,
+        }
+    }
+}
+
+// An argument of a color function like `rgb()`.
+#[derive(Debug, Clone, Copy)]
+enum Component {
+    Number(f32),
+    Percentage(f32),
+}
+
+impl Component {
+    // A red, green or blue channel: 0-255 or 0%-100%.
+    fn to_channel(self) -> u8 {
+        match self {
+            Component::Number(n) => n.round().clamp(0.0, 255.0) as u8,
+            Component::Percentage(p) => (p * 2.55).round().clamp(0.0, 255.0) as u8,
+        }
+    }
+
+    // An alpha channel: 0-1 or 0%-100%.
+    fn to_alpha(self) -> u8 {
+        let alpha = match self {
+            Component::Number(n) => n,
+            Component::Percentage(p) => p / 100.0,
+        };
+        (alpha.clamp(0.0, 1.0) * 255.0).round() as u8
+    }
+}
+
 pub type Specificity = (usize, usize, usize);
 
 impl Selector {
     pub fn specificity(&self) -> Specificity {
         // https://www.w3.org/TR/selectors/#specificity
-        let Selector::Simple(ref simple) = *self;
-        let a = simple.id.iter().count();
-        let b = simple.class.len();
-        let c = simple.tag_name.iter().count();
-        (a, b, c)
+        match *self {
+            Selector::Simple(ref simple) => {
+                let a = simple.id.iter().count();
+                let b = simple.class.len();
+                let c = simple.tag_name.iter().count();
+                (a, b, c)
+            }
+            // The specificity of a complex selector is the sum over its compound selectors.
+            Selector::Descendant(ref ancestor, ref selector)
+            | Selector::Child(ref ancestor, ref selector) => {
+                let (a1, b1, c1) = ancestor.specificity();
+                let (a2, b2, c2) = selector.specificity();
+                (a1 + a2, b1 + b2, c1 + c2)
+            }
+        }
     }
 }
 
@@ -108,10 +195,7 @@ impl Parser {
 
     // This is a really simple identifier parser, but it's good enough for our purposes.
     fn parse_identifier(&mut self) -> String {
-        self.consume_while(|c| match c {
-            'a'..='z' | '-' => true,
-            _ => false,
-        })
+        self.consume_while(|c| matches!(c, 'a'..='z' | '-'))
     }
 
     fn parse_simple_selector(&mut self) -> SimpleSelector {
@@ -136,7 +220,7 @@ impl Parser {
                     // Universal selector
                     self.consume_char();
                 }
-                c if matches!(c, 'a'..='z') => {
+                c if c.is_ascii_lowercase() => {
                     // Type selector
                     simple_selector.tag_name = Some(self.parse_identifier());
                 }
@@ -147,11 +231,42 @@ impl Parser {
         simple_selector
     }
 
+    // Parse simple selectors joined by descendant (whitespace) and child (`>`) combinators.
+    fn parse_selector(&mut self) -> Selector {
+        let mut selector = Selector::Simple(self.parse_simple_selector());
+        loop {
+            self.consume_whitespace();
+            let combinator = self.next_char();
+            match combinator {
+                ',' | '{' => return selector,
+                '>' => {
+                    self.consume_char();
+                    self.consume_whitespace();
+                }
+                _ => {}
+            }
+
+            let start = self.pos;
+            let simple_selector = self.parse_simple_selector();
+            if self.pos == start {
+                panic!("Unexpected character {} in selector", self.next_char());
+            }
+            let (ancestor, simple_selector) = (
+                Box::new(selector),
+                Box::new(Selector::Simple(simple_selector)),
+            );
+            selector = if combinator == '>' {
+                Selector::Child(ancestor, simple_selector)
+            } else {
+                Selector::Descendant(ancestor, simple_selector)
+            };
+        }
+    }
+
     fn parse_selectors(&mut self) -> Vec<Selector> {
         let mut selectors = Vec::new();
         loop {
-            selectors.push(Selector::Simple(self.parse_simple_selector()));
-            self.consume_whitespace();
+            selectors.push(self.parse_selector());
             match self.next_char() {
                 ',' => {
                     self.consume_char();
@@ -161,54 +276,174 @@ impl Parser {
                 c => panic!("Unexpected character {} in selector list", c),
             }
         }
-        selectors.sort_by(|a, b| b.specificity().cmp(&a.specificity()));
+        selectors.sort_by_key(|s| std::cmp::Reverse(s.specificity()));
         selectors
     }
 
     fn parse_length(&mut self) -> Value {
         let length_str = self.consume_while(|c| matches!(c, '0'..='9' | '.'));
         let length = length_str.parse::<f32>().unwrap();
-        let unit_str = self.consume_while(|c| matches!(c, 'a'..='z'));
+        let unit_str = self.consume_while(|c: char| c.is_ascii_lowercase());
         let unit = match &*unit_str {
+            "" => return Value::Number(length),
             "px" => Unit::Px,
             _ => panic!("unexpected unit: {}", &unit_str),
         };
         Value::Length(length, unit)
     }
 
-    fn parse_hex_pair(&mut self) -> u8 {
-        let s = &self.input[self.pos..self.pos + 2];
-        self.pos += 2;
-        u8::from_str_radix(&s.to_lowercase(), 16).unwrap()
-    }
-
+    // Parse `#RRGGBB` or its short form `#RGB`, where each digit is repeated.
     fn parse_color(&mut self) -> Value {
         assert!(self.consume_char() == '#');
-        Value::ColorValue(Color {
-            r: self.parse_hex_pair(),
-            g: self.parse_hex_pair(),
-            b: self.parse_hex_pair(),
+        let digits = self.consume_while(|c| c.is_ascii_hexdigit());
+        let channel = |hex: &str| u8::from_str_radix(hex, 16).unwrap();
+        let (r, g, b) = match digits.len() {
+            3 => (
+                channel(&digits[0..1].repeat(2)),
+                channel(&digits[1..2].repeat(2)),
+                channel(&digits[2..3].repeat(2)),
+            ),
+            6 => (
+                channel(&digits[0..2]),
+                channel(&digits[2..4]),
+                channel(&digits[4..6]),
+            ),
+            _ => panic!("invalid hex color #{}", digits),
+        };
+        Value::Color(Color {
+            r,
+            g,
+            b,
             a: 255, // 1.0 opaque
         })
     }
 
+    // Parse `rgb(R, G, B)`, `rgba(R, G, B, A)`, `hsl(H, S%, L%)` or `hsla(H, S%, L%, A)`.
+    fn parse_color_function(&mut self, name: &str) -> Value {
+        assert!(self.consume_char() == '(');
+        let mut args = Vec::new();
+        loop {
+            self.consume_whitespace();
+            args.push(self.parse_component(name));
+            self.consume_whitespace();
+            if self.eof() {
+                panic!("unterminated {}() color", name);
+            }
+            match self.consume_char() {
+                ',' => {}
+                ')' => break,
+                c => panic!("unexpected character {} in {}() color", c, name),
+            }
+        }
+
+        use Component::{Number, Percentage};
+        let color = match (name, args.as_slice()) {
+            ("rgb", &[r, g, b]) | ("rgba", &[r, g, b, _]) => Color {
+                r: r.to_channel(),
+                g: g.to_channel(),
+                b: b.to_channel(),
+                a: args.get(3).map_or(255, |a| a.to_alpha()),
+            },
+            ("hsl", &[Number(h), Percentage(s), Percentage(l)])
+            | ("hsla", &[Number(h), Percentage(s), Percentage(l), _]) => Color::from_hsl(
+                h,
+                s.clamp(0.0, 100.0) / 100.0,
+                l.clamp(0.0, 100.0) / 100.0,
+                args.get(3).map_or(255, |a| a.to_alpha()),
+            ),
+            _ => panic!("invalid arguments to {}() color: {:?}", name, args),
+        };
+        Value::Color(color)
+    }
+
+    fn parse_component(&mut self, name: &str) -> Component {
+        let number_str = self.consume_while(|c| matches!(c, '0'..='9' | '.' | '-'));
+        let Ok(number) = number_str.parse::<f32>() else {
+            panic!("expected a number in {}() color", name);
+        };
+        if !self.eof() && self.next_char() == '%' {
+            self.consume_char();
+            Component::Percentage(number)
+        } else {
+            Component::Number(number)
+        }
+    }
+
     fn parse_value(&mut self) -> Value {
         match self.next_char() {
             '0'..='9' => self.parse_length(),
             '#' => self.parse_color(),
-            _ => Value::Keyword(self.parse_identifier()),
+            _ => {
+                let identifier = self.parse_identifier();
+                match identifier.as_str() {
+                    "rgb" | "rgba" | "hsl" | "hsla" if self.next_char() == '(' => {
+                        self.parse_color_function(&identifier)
+                    }
+                    _ => Value::Keyword(identifier),
+                }
+            }
         }
     }
 
-    fn parse_declaration(&mut self) -> Declaration {
+    // Shorthand properties are expanded into the longhand declarations they stand for.
+    fn parse_declaration(&mut self) -> Vec<Declaration> {
         let name = self.parse_identifier();
         self.consume_whitespace();
         assert!(self.consume_char() == ':');
         self.consume_whitespace();
-        let value = self.parse_value();
-        self.consume_whitespace();
+        let declarations = match name.as_str() {
+            "border" => self.parse_border(&["top", "right", "bottom", "left"]),
+            "border-top" | "border-right" | "border-bottom" | "border-left" => {
+                self.parse_border(&[&name["border-".len()..]])
+            }
+            _ => {
+                let value = self.parse_value();
+                self.consume_whitespace();
+                vec![Declaration { name, value }]
+            }
+        };
         assert!(self.consume_char() == ';');
-        Declaration { name, value }
+        declarations
+    }
+
+    // Parse `<width> <style> <color>` in any order, each part being optional.
+    // An omitted width is `medium`, an omitted style is `none` and an omitted color
+    // is left undeclared so that the border is painted with the foreground color.
+    fn parse_border(&mut self, sides: &[&str]) -> Vec<Declaration> {
+        let mut width = Value::Length(BORDER_WIDTH_MEDIUM, Unit::Px);
+        let mut style = Value::Keyword("none".to_string());
+        let mut color = None;
+        while self.next_char() != ';' {
+            match self.parse_value() {
+                Value::Length(length, unit) => width = Value::Length(length, unit),
+                Value::Number(length) => width = Value::Length(length, Unit::Px),
+                Value::Keyword(keyword) if BORDER_STYLES.contains(&keyword.as_str()) => {
+                    style = Value::Keyword(keyword)
+                }
+                Value::Color(c) => color = Some(Value::Color(c)),
+                value => panic!("invalid value in border shorthand: {:?}", value),
+            }
+            self.consume_whitespace();
+        }
+
+        let mut declarations = Vec::new();
+        for side in sides {
+            declarations.push(Declaration {
+                name: format!("border-{}-width", side),
+                value: width.clone(),
+            });
+            declarations.push(Declaration {
+                name: format!("border-{}-style", side),
+                value: style.clone(),
+            });
+            if let Some(color) = &color {
+                declarations.push(Declaration {
+                    name: format!("border-{}-color", side),
+                    value: color.clone(),
+                });
+            }
+        }
+        declarations
     }
 
     fn parse_declarations(&mut self) -> Vec<Declaration> {
@@ -220,7 +455,7 @@ impl Parser {
                 self.consume_char();
                 break;
             }
-            declarations.push(self.parse_declaration());
+            declarations.extend(self.parse_declaration());
         }
         declarations
     }
@@ -254,3 +489,101 @@ pub fn parse(source: String) -> StyleSheet {
     let rules = parser.parse_rules();
     StyleSheet { rules }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_color(source: &str) -> Color {
+        let style_sheet = parse(format!("p {{ color: {}; }}", source));
+        match style_sheet.rules[0].declarations[0].value {
+            Value::Color(color) => color,
+            ref value => panic!("not a color: {:?}", value),
+        }
+    }
+
+    fn rgba(r: u8, g: u8, b: u8, a: u8) -> Color {
+        Color { r, g, b, a }
+    }
+
+    #[test]
+    fn parse_color_functions() {
+        assert_eq!(parse_color("#ff8000"), rgba(255, 128, 0, 255));
+        assert_eq!(parse_color("#FF8000"), rgba(255, 128, 0, 255));
+        assert_eq!(parse_color("#f80"), rgba(255, 136, 0, 255));
+        assert_eq!(parse_color("rgb(255, 128, 0)"), rgba(255, 128, 0, 255));
+        assert_eq!(parse_color("rgb( 100% ,50%,0% )"), rgba(255, 128, 0, 255));
+        assert_eq!(parse_color("rgb(300, -1, 0)"), rgba(255, 0, 0, 255));
+        assert_eq!(parse_color("rgba(0, 0, 255, 0.5)"), rgba(0, 0, 255, 128));
+        assert_eq!(parse_color("rgba(0, 0, 255, 25%)"), rgba(0, 0, 255, 64));
+        assert_eq!(parse_color("hsl(0, 100%, 50%)"), rgba(255, 0, 0, 255));
+        assert_eq!(parse_color("hsl(120, 100%, 25%)"), rgba(0, 128, 0, 255));
+        assert_eq!(parse_color("hsl(-120, 100%, 50%)"), rgba(0, 0, 255, 255));
+        assert_eq!(parse_color("hsl(39, 100%, 50%)"), rgba(255, 166, 0, 255));
+        assert_eq!(parse_color("hsl(0, 0%, 100%)"), rgba(255, 255, 255, 255));
+        assert_eq!(parse_color("hsla(240, 100%, 50%, 0)"), rgba(0, 0, 255, 0));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid hex color #ff80")]
+    fn reject_wrong_number_of_hex_digits() {
+        parse_color("#ff80");
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid arguments to rgb() color")]
+    fn reject_wrong_number_of_arguments() {
+        parse_color("rgb(255, 0)");
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid arguments to hsl() color")]
+    fn reject_hsl_without_percentages() {
+        parse_color("hsl(120, 100, 50)");
+    }
+
+    #[test]
+    #[should_panic(expected = "expected a number in rgba() color")]
+    fn reject_missing_argument() {
+        parse_color("rgba(255, , 0, 1)");
+    }
+
+    fn declarations(source: &str) -> Vec<(String, Value)> {
+        let style_sheet = parse(format!("p {{ {} }}", source));
+        style_sheet.rules[0]
+            .declarations
+            .iter()
+            .map(|declaration| (declaration.name.clone(), declaration.value.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn expand_border_shorthand() {
+        let px = |length| Value::Length(length, Unit::Px);
+        let keyword = |keyword: &str| Value::Keyword(keyword.to_string());
+        let black = Value::Color(rgba(0, 0, 0, 255));
+
+        let expanded = declarations("border: 1px solid #000;");
+        assert_eq!(expanded.len(), 12);
+        for side in ["top", "right", "bottom", "left"] {
+            assert!(expanded.contains(&(format!("border-{}-width", side), px(1.0))));
+            assert!(expanded.contains(&(format!("border-{}-style", side), keyword("solid"))));
+            assert!(expanded.contains(&(format!("border-{}-color", side), black.clone())));
+        }
+
+        assert_eq!(
+            declarations("border-left: solid; color: #000000;"),
+            vec![
+                ("border-left-width".to_string(), px(3.0)),
+                ("border-left-style".to_string(), keyword("solid")),
+                ("color".to_string(), black),
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid value in border shorthand")]
+    fn reject_unknown_border_keyword() {
+        declarations("border: 1px wavy;");
+    }
+}
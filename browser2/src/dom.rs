@@ -28,7 +28,7 @@ impl ElementData {
 
     pub fn classes(&self) -> HashSet<&str> {
         match self.attributes.get("class") {
-            Some(classlist) => classlist.split(' ').collect(),
+            Some(classlist) => classlist.split_whitespace().collect(),
             None => HashSet::new(),
         }
     }
@@ -36,6 +36,9 @@ impl ElementData {
 
 pub type AttrMap = HashMap<String, String>;
 
+// Elements that never have children or a closing tag.
+const VOID_ELEMENTS: [&str; 6] = ["br", "hr", "img", "input", "link", "meta"];
+
 fn text(data: String) -> Node {
     Node {
         children: Vec::new(),
@@ -95,22 +98,32 @@ impl Parser {
     }
 
     fn parse_tag_name(&mut self) -> String {
-        self.consume_while(|c| match c {
-            'a'..='z' | 'A'..='Z' | '0'..='9' => true,
-            _ => false,
-        })
+        self.consume_while(|c| matches!(c, 'a'..='z' | 'A'..='Z' | '0'..='9'))
     }
 
+    // Attribute values may be quoted with `"` or `'`, or left unquoted. An attribute
+    // without a value, such as `<input disabled>`, gets the empty string.
     fn parse_attribute(&mut self) -> (String, String) {
-        let key = self.consume_while(|c| match c {
-            'a'..='z' | 'A'..='Z' | '0'..='9' => true,
-            _ => false,
-        });
-        assert!(self.consume_char() == '=');
-        let open_quote = self.consume_char();
-        assert!(open_quote == '"' || open_quote == '\'');
-        let value = self.consume_while(|c| c != open_quote);
-        assert!(self.consume_char() == open_quote);
+        let key =
+            self.consume_while(|c| matches!(c, 'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_'));
+        assert!(!key.is_empty(), "expected an attribute name");
+        self.consume_whitespace();
+        if self.next_char() != '=' {
+            return (key, String::new());
+        }
+        self.consume_char();
+        self.consume_whitespace();
+        let value = match self.next_char() {
+            open_quote @ ('"' | '\'') => {
+                self.consume_char();
+                let value = self.consume_while(|c| c != open_quote);
+                assert!(self.consume_char() == open_quote);
+                value
+            }
+            _ => self.consume_while(|c| {
+                !c.is_whitespace() && !matches!(c, '"' | '\'' | '<' | '>' | '=' | '`')
+            }),
+        };
         (key, value)
     }
 
@@ -118,7 +131,7 @@ impl Parser {
         let mut attributes = HashMap::new();
         loop {
             self.consume_whitespace();
-            if self.next_char() == '>' {
+            if self.next_char() == '>' || self.starts_with("/>") {
                 break;
             }
             let (name, value) = self.parse_attribute();
@@ -132,7 +145,14 @@ impl Parser {
         assert!(self.consume_char() == '<');
         let tag_name = self.parse_tag_name();
         let attributes = self.parse_attributes();
+        if self.starts_with("/>") {
+            self.pos += 2;
+            return element(tag_name, attributes, Vec::new());
+        }
         assert!(self.consume_char() == '>');
+        if VOID_ELEMENTS.contains(&tag_name.as_str()) {
+            return element(tag_name, attributes, Vec::new());
+        }
 
         // Contents.
         let children = self.parse_nodes();
@@ -183,3 +203,42 @@ pub fn parse(source: String) -> Node {
         element("html".to_string(), HashMap::new(), nodes)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attributes(source: &str) -> AttrMap {
+        match parse(source.to_string()).node_type {
+            NodeType::Element(elem) => elem.attributes,
+            NodeType::Text(_) => panic!("not an element"),
+        }
+    }
+
+    #[test]
+    fn parse_quoted_and_unquoted_attributes() {
+        let attrs = attributes("<input id=name class='a  b' data-x = \"1 2\" size=10 disabled>");
+        assert_eq!(attrs["id"], "name");
+        assert_eq!(attrs["class"], "a  b");
+        assert_eq!(attrs["data-x"], "1 2");
+        assert_eq!(attrs["size"], "10");
+        assert_eq!(attrs["disabled"], "");
+    }
+
+    #[test]
+    fn split_classes_on_whitespace() {
+        let node = parse("<div class=\" a\tb  a \"></div>".to_string());
+        let NodeType::Element(elem) = node.node_type else {
+            panic!("not an element");
+        };
+        assert_eq!(elem.classes(), HashSet::from(["a", "b"]));
+    }
+
+    #[test]
+    fn unquoted_value_ends_at_tag_end() {
+        assert_eq!(attributes("<p class=x>text</p>")["class"], "x");
+        // As in HTML, a slash directly after an unquoted value belongs to the value.
+        assert_eq!(attributes("<br class=x/>")["class"], "x/");
+        assert_eq!(attributes("<br class=x />")["class"], "x");
+    }
+}
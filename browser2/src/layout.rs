@@ -1,7 +1,9 @@
 use crate::css::{
     Unit::Px,
-    Value::{Keyword, Length},
+    Value::{self, Keyword, Length},
 };
+use crate::dom::NodeType;
+use crate::font;
 use crate::style::{Display, StyleNode};
 
 #[derive(Default, Debug, Clone, Copy)]
@@ -58,11 +60,37 @@ pub struct LayoutBox<'a> {
     pub dimensions: Dimensions,
     pub box_type: BoxType<'a>,
     pub children: Vec<LayoutBox<'a>>,
+    // Lines of inline content, for boxes that establish an inline formatting context.
+    pub lines: Vec<LineBox<'a>>,
+}
+
+#[derive(Debug, Default)]
+pub struct LineBox<'a> {
+    pub items: Vec<InlineBox<'a>>,
+    pub height: f32,
+}
+
+/// The part of a text node that ended up on a single line.
+#[derive(Debug)]
+pub struct InlineBox<'a> {
+    pub rect: Rect,
+    pub text: String,
+    // The inline elements containing the text, outermost first, followed by the text node itself.
+    pub nodes: Vec<&'a StyleNode<'a>>,
+}
+
+// A word of inline content, before it is placed on a line.
+struct Word<'a> {
+    text: String,
+    nodes: Vec<&'a StyleNode<'a>>,
+    space_before: bool,
 }
 
 #[derive(Debug)]
 pub enum BoxType<'a> {
     BlockNode(&'a StyleNode<'a>),
+    // A block-level box whose children are laid out by `FlexLayout`.
+    FlexNode(&'a StyleNode<'a>),
     InlineNode(&'a StyleNode<'a>),
     AnonymousBlock,
 }
@@ -73,13 +101,14 @@ impl<'a> LayoutBox<'a> {
             box_type,
             dimensions: Default::default(),
             children: Vec::new(),
+            lines: Vec::new(),
         }
     }
 
     fn get_inline_container(&mut self) -> &mut LayoutBox<'a> {
         match self.box_type {
             BoxType::InlineNode(_) | BoxType::AnonymousBlock => self,
-            BoxType::BlockNode(_) => {
+            BoxType::BlockNode(_) | BoxType::FlexNode(_) => {
                 match self.children.last() {
                     Some(&LayoutBox {
                         box_type: BoxType::AnonymousBlock,
@@ -94,19 +123,148 @@ impl<'a> LayoutBox<'a> {
 
     fn layout(&mut self, containing_block: Dimensions) {
         match self.box_type {
-            BoxType::BlockNode(_) => self.layout_block(containing_block),
+            BoxType::BlockNode(_) | BoxType::FlexNode(_) => self.layout_block(containing_block),
             BoxType::InlineNode(_) => {}
-            BoxType::AnonymousBlock => {}
+            BoxType::AnonymousBlock => self.layout_anonymous_block(containing_block),
         }
     }
 
+    // An anonymous block only wraps inline content, so it takes the whole width of its container.
+    fn layout_anonymous_block(&mut self, containing_block: Dimensions) {
+        let d = &mut self.dimensions;
+        d.content.x = containing_block.content.x;
+        d.content.y = containing_block.content.y + containing_block.content.height;
+        d.content.width = containing_block.content.width;
+        self.layout_inline_children();
+    }
+
     fn layout_block(&mut self, containing_block: Dimensions) {
         self.calculate_block_width(containing_block);
+        self.layout_block_contents(containing_block);
+    }
+
+    // Everything in block layout after the width is known.
+    fn layout_block_contents(&mut self, containing_block: Dimensions) {
         self.calculate_block_position(containing_block);
-        self.layout_block_children();
+        if let BoxType::FlexNode(style_node) = self.box_type {
+            let height = self.specified_length("height");
+            FlexLayout::new(style_node).layout(&mut self.dimensions, &mut self.children, height);
+        } else {
+            self.layout_block_children();
+        }
         self.calculate_block_height();
     }
 
+    // Lay out a flex item whose content box is `content_width` wide, at the top left of the container.
+    fn layout_flex_item(&mut self, mut containing_block: Dimensions, content_width: f32) {
+        if let BoxType::AnonymousBlock = self.box_type {
+            containing_block.content.width = content_width;
+            self.layout_anonymous_block(containing_block);
+            return;
+        }
+
+        self.calculate_block_width(containing_block);
+        // The flex layout has already decided the width, and auto margins count as 0.
+        let style = self.get_style_node();
+        let zero = Length(0.0, Px);
+        let d = &mut self.dimensions;
+        d.margin.left = style.lookup("margin-left", "margin", &zero).to_px();
+        d.margin.right = style.lookup("margin-right", "margin", &zero).to_px();
+        d.content.width = content_width;
+        self.layout_block_contents(containing_block);
+    }
+
+    // The width of the box when none of its content wraps, including its margin, border and padding.
+    fn max_content_width(&self) -> f32 {
+        if let BoxType::InlineNode(style_node) = self.box_type {
+            let text_width = match style_node.node.node_type {
+                NodeType::Text(ref text) => {
+                    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                    text.chars().count() as f32 * font::char_width(style_node.font_size())
+                }
+                NodeType::Element(_) => 0.0,
+            };
+            return text_width
+                + self
+                    .children
+                    .iter()
+                    .map(LayoutBox::max_content_width)
+                    .sum::<f32>();
+        }
+
+        let edges = self.horizontal_edges();
+        if let Some(width) = self.specified_length("width") {
+            return width + edges;
+        }
+        let children = self.children.iter().map(LayoutBox::max_content_width);
+        let side_by_side = match self.box_type {
+            BoxType::FlexNode(style_node) => {
+                matches!(FlexLayout::new(style_node).direction, FlexDirection::Row)
+            }
+            BoxType::AnonymousBlock => true,
+            _ => self.has_only_inline_children(),
+        };
+        edges
+            + if side_by_side {
+                children.sum::<f32>()
+            } else {
+                children.fold(0.0, f32::max)
+            }
+    }
+
+    // Left and right margin, border and padding, with auto margins counted as 0.
+    fn horizontal_edges(&self) -> f32 {
+        if let BoxType::AnonymousBlock = self.box_type {
+            return 0.0;
+        }
+        let style = self.get_style_node();
+        let zero = Length(0.0, Px);
+        [
+            style.lookup("margin-left", "margin", &zero),
+            style.lookup("margin-right", "margin", &zero),
+            style.border_width("left"),
+            style.border_width("right"),
+            style.lookup("padding-left", "padding", &zero),
+            style.lookup("padding-right", "padding", &zero),
+        ]
+        .iter()
+        .map(Value::to_px)
+        .sum()
+    }
+
+    fn specified_length(&self, name: &str) -> Option<f32> {
+        if let BoxType::AnonymousBlock = self.box_type {
+            return None;
+        }
+        match self.get_style_node().value(name) {
+            Some(Length(length, _)) => Some(length),
+            _ => None,
+        }
+    }
+
+    fn flex_grow(&self) -> f32 {
+        if let BoxType::AnonymousBlock = self.box_type {
+            return 0.0;
+        }
+        match self.get_style_node().value("flex-grow") {
+            Some(Value::Number(grow)) => grow.max(0.0),
+            _ => 0.0,
+        }
+    }
+
+    // Move the box and everything in it.
+    fn translate(&mut self, dx: f32, dy: f32) {
+        self.dimensions.content.x += dx;
+        self.dimensions.content.y += dy;
+        for item in self.lines.iter_mut().flat_map(|line| &mut line.items) {
+            item.rect.x += dx;
+            item.rect.y += dy;
+        }
+        for child in &mut self.children {
+            child.translate(dx, dy);
+        }
+    }
+
     fn calculate_block_width(&mut self, containing_block: Dimensions) {
         let style = self.get_style_node();
 
@@ -116,8 +274,8 @@ impl<'a> LayoutBox<'a> {
         let zero = Length(0.0, Px);
         let mut margin_left = style.lookup("margin-left", "margin", &zero);
         let mut margin_right = style.lookup("margin-right", "margin", &zero);
-        let border_left = style.lookup("border-left-width", "border-width", &zero);
-        let border_right = style.lookup("border-right-width", "border-width", &zero);
+        let border_left = style.border_width("left");
+        let border_right = style.border_width("right");
         let padding_left = style.lookup("padding-left", "padding", &zero);
         let padding_right = style.lookup("padding-right", "padding", &zero);
 
@@ -202,12 +360,8 @@ impl<'a> LayoutBox<'a> {
         d.margin.top = style.lookup("margin-top", "margin", &zero).to_px();
         d.margin.bottom = style.lookup("margin-bottom", "margin", &zero).to_px();
 
-        d.border.top = style
-            .lookup("border-top-width", "border-width", &zero)
-            .to_px();
-        d.border.bottom = style
-            .lookup("border-bottom-width", "border-width", &zero)
-            .to_px();
+        d.border.top = style.border_width("top").to_px();
+        d.border.bottom = style.border_width("bottom").to_px();
 
         d.padding.top = style.lookup("padding-top", "padding", &zero).to_px();
         d.padding.bottom = style.lookup("padding-bottom", "padding", &zero).to_px();
@@ -221,10 +375,85 @@ impl<'a> LayoutBox<'a> {
     }
 
     fn layout_block_children(&mut self) {
+        if self.has_only_inline_children() {
+            self.layout_inline_children();
+            return;
+        }
         let d = &mut self.dimensions;
         for child in &mut self.children {
             child.layout(*d);
-            d.content.height = d.content.height + child.dimensions.margin_box().height;
+            d.content.height += child.dimensions.margin_box().height;
+        }
+    }
+
+    fn has_only_inline_children(&self) -> bool {
+        !self.children.is_empty()
+            && self
+                .children
+                .iter()
+                .all(|child| matches!(child.box_type, BoxType::InlineNode(_)))
+    }
+
+    // Place the words of the inline children on lines, wrapping when a line overflows the content width.
+    fn layout_inline_children(&mut self) {
+        let mut words = Vec::new();
+        let mut pending_space = false;
+        for child in &self.children {
+            collect_words(child, &mut Vec::new(), &mut pending_space, &mut words);
+        }
+
+        let d = &mut self.dimensions;
+        let mut line = LineBox::default();
+        let mut x = 0.0;
+        for word in words {
+            // Text is measured with a fixed-pitch font.
+            let font_size = word.nodes[word.nodes.len() - 1].font_size();
+            let char_width = font::char_width(font_size);
+            let line_height = font::line_height(font_size);
+            let width = word.text.chars().count() as f32 * char_width;
+            let mut space = if word.space_before && !line.items.is_empty() {
+                char_width
+            } else {
+                0.0
+            };
+            if !line.items.is_empty() && x + space + width > d.content.width {
+                finish_line(&mut self.lines, line, d);
+                line = LineBox::default();
+                x = 0.0;
+                space = 0.0;
+            }
+            x += space;
+
+            // Consecutive words of the same text node share an inline box.
+            match line.items.last_mut() {
+                Some(last)
+                    if std::ptr::eq(
+                        last.nodes[last.nodes.len() - 1],
+                        word.nodes[word.nodes.len() - 1],
+                    ) =>
+                {
+                    if space > 0.0 {
+                        last.text.push(' ');
+                    }
+                    last.text.push_str(&word.text);
+                    last.rect.width = d.content.x + x + width - last.rect.x;
+                }
+                _ => line.items.push(InlineBox {
+                    rect: Rect {
+                        x: d.content.x + x,
+                        y: 0.0,
+                        width,
+                        height: line_height,
+                    },
+                    text: word.text,
+                    nodes: word.nodes,
+                }),
+            }
+            line.height = line.height.max(line_height);
+            x += width;
+        }
+        if !line.items.is_empty() {
+            finish_line(&mut self.lines, line, d);
         }
     }
 
@@ -237,21 +466,251 @@ impl<'a> LayoutBox<'a> {
     fn get_style_node(&self) -> &'a StyleNode<'a> {
         match self.box_type {
             BoxType::BlockNode(node) => node,
+            BoxType::FlexNode(node) => node,
             BoxType::InlineNode(node) => node,
             BoxType::AnonymousBlock => panic!("Anonymous block box has no style node."),
         }
     }
 }
 
+// Move the finished line below the previous ones and grow the container to fit it.
+// Text of different sizes on the same line is aligned at the bottom.
+fn finish_line<'a>(lines: &mut Vec<LineBox<'a>>, mut line: LineBox<'a>, d: &mut Dimensions) {
+    for item in &mut line.items {
+        item.rect.y = d.content.y + d.content.height + line.height - item.rect.height;
+    }
+    d.content.height += line.height;
+    lines.push(line);
+}
+
+// Split the text in an inline box and its descendants into words.
+fn collect_words<'a>(
+    layout_box: &LayoutBox<'a>,
+    nodes: &mut Vec<&'a StyleNode<'a>>,
+    pending_space: &mut bool,
+    words: &mut Vec<Word<'a>>,
+) {
+    let BoxType::InlineNode(style_node) = layout_box.box_type else {
+        return;
+    };
+    nodes.push(style_node);
+    if let NodeType::Text(ref text) = style_node.node.node_type {
+        for (i, word) in text.split_whitespace().enumerate() {
+            words.push(Word {
+                text: word.to_string(),
+                nodes: nodes.clone(),
+                space_before: i > 0 || *pending_space || text.starts_with(char::is_whitespace),
+            });
+            *pending_space = false;
+        }
+        *pending_space |= text.ends_with(char::is_whitespace);
+    }
+    for child in &layout_box.children {
+        collect_words(child, nodes, pending_space, words);
+    }
+    nodes.pop();
+}
+
+#[derive(Debug, Clone, Copy)]
+enum FlexDirection {
+    Row,
+    Column,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum JustifyContent {
+    FlexStart,
+    Center,
+    SpaceBetween,
+    SpaceAround,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum AlignItems {
+    Stretch,
+    Center,
+    FlexStart,
+    FlexEnd,
+}
+
+/// Single-line flex layout.
+/// https://www.w3.org/TR/css-flexbox-1/#layout-algorithm
+struct FlexLayout {
+    direction: FlexDirection,
+    justify_content: JustifyContent,
+    align_items: AlignItems,
+}
+
+impl FlexLayout {
+    fn new(style: &StyleNode) -> FlexLayout {
+        let keyword = |name| match style.value(name) {
+            Some(Keyword(keyword)) => keyword,
+            _ => String::new(),
+        };
+        FlexLayout {
+            direction: match keyword("flex-direction").as_str() {
+                "column" => FlexDirection::Column,
+                _ => FlexDirection::Row,
+            },
+            justify_content: match keyword("justify-content").as_str() {
+                "center" => JustifyContent::Center,
+                "space-between" => JustifyContent::SpaceBetween,
+                "space-around" => JustifyContent::SpaceAround,
+                _ => JustifyContent::FlexStart,
+            },
+            align_items: match keyword("align-items").as_str() {
+                "center" => AlignItems::Center,
+                "flex-start" => AlignItems::FlexStart,
+                "flex-end" => AlignItems::FlexEnd,
+                _ => AlignItems::Stretch,
+            },
+        }
+    }
+
+    /// Size and position the items inside the container's content box, and set the container's
+    /// content height. `height` is the container's specified height, if any.
+    fn layout(&self, container: &mut Dimensions, items: &mut [LayoutBox], height: Option<f32>) {
+        // Items are laid out at the top left of the container first, then moved into place.
+        let containing_block = Dimensions {
+            content: Rect {
+                height: 0.0,
+                ..container.content
+            },
+            ..Default::default()
+        };
+
+        match self.direction {
+            FlexDirection::Row => {
+                let bases = items
+                    .iter()
+                    .map(LayoutBox::max_content_width)
+                    .collect::<Vec<_>>();
+                let sizes = grow(&bases, items, container.content.width);
+                for (item, size) in items.iter_mut().zip(&sizes) {
+                    let content_width = (size - item.horizontal_edges()).max(0.0);
+                    item.layout_flex_item(containing_block, content_width);
+                }
+
+                let cross_size = height.unwrap_or_else(|| {
+                    items
+                        .iter()
+                        .map(|item| item.dimensions.margin_box().height)
+                        .fold(0.0, f32::max)
+                });
+                let free_space = container.content.width - sizes.iter().sum::<f32>();
+                let (mut main, gap) = self.distribute(free_space, items.len());
+                for item in items.iter_mut() {
+                    let cross = self.align(item, cross_size, FlexDirection::Row);
+                    item.translate(main, cross);
+                    main += item.dimensions.margin_box().width + gap;
+                }
+                container.content.height = cross_size;
+            }
+            FlexDirection::Column => {
+                let cross_size = container.content.width;
+                for item in items.iter_mut() {
+                    let content_width = match (self.align_items, item.specified_length("width")) {
+                        (AlignItems::Stretch, None) => cross_size - item.horizontal_edges(),
+                        _ => item.max_content_width() - item.horizontal_edges(),
+                    };
+                    item.layout_flex_item(containing_block, content_width.max(0.0));
+                }
+
+                let bases = items
+                    .iter()
+                    .map(|item| item.dimensions.margin_box().height)
+                    .collect::<Vec<_>>();
+                let main_size = height.unwrap_or_else(|| bases.iter().sum());
+                let sizes = grow(&bases, items, main_size);
+                let free_space = main_size - sizes.iter().sum::<f32>();
+                let (mut main, gap) = self.distribute(free_space, items.len());
+                for ((item, base), size) in items.iter_mut().zip(&bases).zip(&sizes) {
+                    item.dimensions.content.height += size - base;
+                    let cross = self.align(item, cross_size, FlexDirection::Column);
+                    item.translate(cross, main);
+                    main += size + gap;
+                }
+                container.content.height = main_size;
+            }
+        }
+    }
+
+    // The offset of the first item and the gap between items on the main axis (justify-content).
+    fn distribute(&self, free_space: f32, count: usize) -> (f32, f32) {
+        if free_space <= 0.0 || count == 0 {
+            return (0.0, 0.0);
+        }
+        match self.justify_content {
+            JustifyContent::FlexStart => (0.0, 0.0),
+            JustifyContent::Center => (free_space / 2.0, 0.0),
+            JustifyContent::SpaceBetween if count == 1 => (0.0, 0.0),
+            JustifyContent::SpaceBetween => (0.0, free_space / (count - 1) as f32),
+            JustifyContent::SpaceAround => {
+                let gap = free_space / count as f32;
+                (gap / 2.0, gap)
+            }
+        }
+    }
+
+    // The offset of an item on the cross axis (align-items). Stretched items are resized instead.
+    fn align(&self, item: &mut LayoutBox, cross_size: f32, direction: FlexDirection) -> f32 {
+        let item_size = match direction {
+            FlexDirection::Row => item.dimensions.margin_box().height,
+            FlexDirection::Column => item.dimensions.margin_box().width,
+        };
+        match self.align_items {
+            AlignItems::Stretch => {
+                if let (FlexDirection::Row, None) = (direction, item.specified_length("height")) {
+                    item.dimensions.content.height += cross_size - item_size;
+                }
+                0.0
+            }
+            AlignItems::FlexStart => 0.0,
+            AlignItems::Center => (cross_size - item_size) / 2.0,
+            AlignItems::FlexEnd => cross_size - item_size,
+        }
+    }
+}
+
+// Hand out the space left over after the base sizes to the items, in proportion to their flex-grow.
+fn grow(bases: &[f32], items: &[LayoutBox], available: f32) -> Vec<f32> {
+    let free_space = available - bases.iter().sum::<f32>();
+    let total_grow = items.iter().map(LayoutBox::flex_grow).sum::<f32>();
+    if free_space <= 0.0 || total_grow == 0.0 {
+        return bases.to_vec();
+    }
+    bases
+        .iter()
+        .zip(items)
+        .map(|(base, item)| base + free_space * item.flex_grow() / total_grow)
+        .collect()
+}
+
 fn build_layout_tree<'a>(style_tree: &'a StyleNode<'a>) -> LayoutBox<'a> {
     let mut root = LayoutBox::new(match style_tree.display() {
         Display::Block => BoxType::BlockNode(style_tree),
+        Display::Flex => BoxType::FlexNode(style_tree),
         Display::Inline => BoxType::InlineNode(style_tree),
         Display::None => panic!("Root node has display: none."),
     });
+    if let Display::Flex = style_tree.display() {
+        for child in &style_tree.children {
+            if !matches!(child.display(), Display::None) {
+                root.children.push(build_flex_item(child));
+            }
+        }
+        return root;
+    }
+
+    // A block whose children are all inline lays them out itself, without an anonymous block.
+    let only_inline_children = style_tree
+        .children
+        .iter()
+        .all(|child| matches!(child.display(), Display::Inline | Display::None));
     for child in &style_tree.children {
         match child.display() {
-            Display::Block => root.children.push(build_layout_tree(child)),
+            Display::Block | Display::Flex => root.children.push(build_layout_tree(child)),
+            Display::Inline if only_inline_children => root.children.push(build_layout_tree(child)),
             Display::Inline => root
                 .get_inline_container()
                 .children
@@ -262,6 +721,20 @@ fn build_layout_tree<'a>(style_tree: &'a StyleNode<'a>) -> LayoutBox<'a> {
     root
 }
 
+// Every child of a flex container is block-level. Text gets wrapped in an anonymous block.
+fn build_flex_item<'a>(style_tree: &'a StyleNode<'a>) -> LayoutBox<'a> {
+    if let NodeType::Text(_) = style_tree.node.node_type {
+        let mut item = LayoutBox::new(BoxType::AnonymousBlock);
+        item.children.push(build_layout_tree(style_tree));
+        return item;
+    }
+    let mut item = build_layout_tree(style_tree);
+    if let BoxType::InlineNode(node) = item.box_type {
+        item.box_type = BoxType::BlockNode(node);
+    }
+    item
+}
+
 /// Transform a style tree into a layout tree.
 pub fn layout_tree<'a>(node: &'a StyleNode<'a>, mut containing_block: Dimensions) -> LayoutBox<'a> {
     // The layout algorithm expects the container height to start at 0.
@@ -270,5 +743,209 @@ pub fn layout_tree<'a>(node: &'a StyleNode<'a>, mut containing_block: Dimensions
 
     let mut root_box = build_layout_tree(node);
     root_box.layout(containing_block);
-    return root_box;
+    root_box
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{css, dom, style};
+
+    fn viewport() -> Dimensions {
+        Dimensions {
+            content: Rect {
+                x: 0.0,
+                y: 0.0,
+                width: 800.0,
+                height: 600.0,
+            },
+            ..Default::default()
+        }
+    }
+
+    fn line_texts(layout_box: &LayoutBox) -> Vec<Vec<String>> {
+        layout_box
+            .lines
+            .iter()
+            .map(|line| line.items.iter().map(|item| item.text.clone()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn wrap_inline_content_into_lines() {
+        let dom_tree = dom::parse(
+            "<p>Hello <span>big</span> world <span>wrapping text</span>!</p>".to_string(),
+        );
+        let style_sheet = css::parse("p { display: block; width: 80px; }".to_string());
+        let style_tree = style::style_tree(&dom_tree, &style_sheet);
+        let layout_box = layout_tree(&style_tree, viewport());
+
+        // 80px fits 10 characters per line.
+        assert_eq!(
+            line_texts(&layout_box),
+            vec![
+                vec!["Hello", "big"],
+                vec!["world"],
+                vec!["wrapping"],
+                vec!["text", "!"],
+            ]
+        );
+        assert!(layout_box.lines.iter().all(|line| line.height == 16.0));
+        assert_eq!(layout_box.dimensions.content.height, 64.0);
+
+        let big = &layout_box.lines[0].items[1];
+        assert_eq!((big.rect.x, big.rect.y, big.rect.width), (48.0, 0.0, 24.0));
+        assert_eq!(big.nodes.len(), 2);
+        let bang = &layout_box.lines[3].items[1];
+        assert_eq!((bang.rect.x, bang.rect.y), (32.0, 48.0));
+    }
+
+    #[test]
+    fn wrap_inline_content_in_anonymous_block() {
+        let dom_tree = dom::parse("<div><p>Title</p>Some <span>more</span> text</div>".to_string());
+        let style_sheet = css::parse("div, p { display: block; }".to_string());
+        let style_tree = style::style_tree(&dom_tree, &style_sheet);
+        let layout_box = layout_tree(&style_tree, viewport());
+
+        assert_eq!(layout_box.children.len(), 2);
+        assert_eq!(line_texts(&layout_box.children[0]), vec![vec!["Title"]]);
+        let anonymous_block = &layout_box.children[1];
+        assert!(matches!(anonymous_block.box_type, BoxType::AnonymousBlock));
+        assert_eq!(
+            line_texts(anonymous_block),
+            vec![vec!["Some", "more", "text"]]
+        );
+        assert_eq!(anonymous_block.lines[0].items[0].rect.y, 16.0);
+        assert_eq!(layout_box.dimensions.content.height, 32.0);
+    }
+
+    // The border box of each child of the root, as (x, y, width, height).
+    fn child_boxes(html: &str, css: &str) -> Vec<(f32, f32, f32, f32)> {
+        let dom_tree = dom::parse(html.to_string());
+        let style_sheet = css::parse(css.to_string());
+        let style_tree = style::style_tree(&dom_tree, &style_sheet);
+        let layout_box = layout_tree(&style_tree, viewport());
+        layout_box
+            .children
+            .iter()
+            .map(|child| {
+                let rect = child.dimensions.border_box();
+                (rect.x, rect.y, rect.width, rect.height)
+            })
+            .collect()
+    }
+
+    const FLEX_ITEMS: &str =
+        "<div class=\"flex\"><div class=\"a\"></div><div class=\"b\"></div><div class=\"c\"></div></div>";
+
+    #[test]
+    fn flex_row_grows_items_into_free_space() {
+        let css = ".flex { display: flex; height: 50px; }
+            .a { width: 100px; }
+            .b { width: 100px; flex-grow: 1; padding: 10px; }
+            .c { flex-grow: 3; }";
+        assert_eq!(
+            child_boxes(FLEX_ITEMS, css),
+            vec![
+                (0.0, 0.0, 100.0, 50.0),
+                // The padding is part of the base size, so b starts from 120px.
+                (100.0, 0.0, 120.0 + 580.0 / 4.0, 50.0),
+                (220.0 + 580.0 / 4.0, 0.0, 580.0 * 3.0 / 4.0, 50.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn flex_row_justify_content_and_align_items() {
+        let css = ".flex { display: flex; justify-content: space-between; align-items: center; }
+            .a { width: 100px; height: 20px; }
+            .b { width: 100px; height: 40px; }
+            .c { width: 100px; height: 10px; margin: 5px; }";
+        assert_eq!(
+            child_boxes(FLEX_ITEMS, css),
+            vec![
+                (0.0, 10.0, 100.0, 20.0),
+                (345.0, 0.0, 100.0, 40.0),
+                (695.0, 15.0, 100.0, 10.0),
+            ]
+        );
+
+        let css = ".flex { display: flex; justify-content: center; align-items: flex-end; }
+            .a, .b { width: 100px; height: 20px; }
+            .c { width: 100px; height: 40px; }";
+        assert_eq!(
+            child_boxes(FLEX_ITEMS, css),
+            vec![
+                (250.0, 20.0, 100.0, 20.0),
+                (350.0, 20.0, 100.0, 20.0),
+                (450.0, 0.0, 100.0, 40.0),
+            ]
+        );
+
+        let css = ".flex { display: flex; justify-content: space-around; }
+            .a, .b { width: 100px; height: 20px; }";
+        assert_eq!(
+            child_boxes(FLEX_ITEMS, css),
+            vec![
+                (100.0, 0.0, 100.0, 20.0),
+                (400.0, 0.0, 100.0, 20.0),
+                (700.0, 0.0, 0.0, 20.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn flex_column() {
+        let css = ".flex { display: flex; flex-direction: column; height: 300px; }
+            .a { height: 50px; }
+            .b { height: 50px; width: 200px; }
+            .c { flex-grow: 1; }";
+        assert_eq!(
+            child_boxes(FLEX_ITEMS, css),
+            vec![
+                (0.0, 0.0, 800.0, 50.0),
+                (0.0, 50.0, 200.0, 50.0),
+                (0.0, 100.0, 800.0, 200.0),
+            ]
+        );
+
+        let css = ".flex { display: flex; flex-direction: column; align-items: center; }
+            .a, .b, .c { height: 50px; width: 200px; }";
+        assert_eq!(
+            child_boxes(FLEX_ITEMS, css),
+            vec![
+                (300.0, 0.0, 200.0, 50.0),
+                (300.0, 50.0, 200.0, 50.0),
+                (300.0, 100.0, 200.0, 50.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn flex_items_sized_by_text() {
+        let dom_tree =
+            dom::parse("<div><span>Home</span>About us<span>Contact</span></div>".to_string());
+        let style_sheet =
+            css::parse("div { display: flex; justify-content: space-between; }".to_string());
+        let style_tree = style::style_tree(&dom_tree, &style_sheet);
+        let layout_box = layout_tree(&style_tree, viewport());
+        let items = layout_box
+            .children
+            .iter()
+            .map(|child| {
+                let rect = child.dimensions.content;
+                (rect.x, rect.width, rect.height)
+            })
+            .collect::<Vec<_>>();
+        let gap = (800.0 - 19.0 * 8.0) / 2.0;
+        assert_eq!(
+            items,
+            vec![
+                (0.0, 32.0, 16.0),
+                (32.0 + gap, 64.0, 16.0),
+                (96.0 + gap * 2.0, 56.0, 16.0),
+            ]
+        );
+        assert_eq!(line_texts(&layout_box.children[1]), vec![vec!["About us"]]);
+    }
 }
@@ -1,12 +1,22 @@
 use image::{ImageBuffer, Rgba};
 
+mod browser;
 mod css;
 mod dom;
+mod font;
 mod layout;
 mod paint;
 mod style;
 
 fn main() {
+    // `cargo run -- <file>` renders an HTML file relative to the current directory.
+    if let Some(path) = std::env::args().nth(1) {
+        let base_dir = std::env::current_dir().expect("failed to get current directory");
+        let canvas = browser::Browser::new(base_dir).navigate(&path);
+        save_png(canvas, "output.png");
+        return;
+    }
+
     let html = "<div class=\"a\">
   <div class=\"b\">
     <div class=\"c\">
@@ -21,7 +31,7 @@ fn main() {
     </div>
   </div>
 </div>";
-    let css = "* { display: block; padding: 12px; }
+    let css = "* { display: block; padding: 12px; border: 2px solid #000000; }
 .a { background: #ff0000; }
 .b { background: #ffa500; }
 .c { background: #ffff00; }
@@ -46,10 +56,12 @@ fn main() {
     let style_tree = style::style_tree(&dom_tree, &style_sheet);
     let layout_tree = layout::layout_tree(&style_tree, initial_containing_block);
     let canvas = paint::paint(&layout_tree, initial_containing_block.content);
+    save_png(canvas, "output.png");
+}
 
-    // Save an image:
+fn save_png(canvas: paint::Canvas, filename: &str) {
     let (w, h) = (canvas.width as u32, canvas.height as u32);
     let buffer: Vec<Rgba<u8>> = unsafe { std::mem::transmute(canvas.pixels) };
     let img = ImageBuffer::from_fn(w, h, Box::new(|x, y| buffer[(y * w + x) as usize]));
-    img.save("output.png").expect("Error saving png image");
+    img.save(filename).expect("Error saving png image");
 }
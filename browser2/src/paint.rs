@@ -1,4 +1,5 @@
 use crate::css::{Color, Value};
+use crate::font;
 use crate::layout::{BoxType, LayoutBox, Rect};
 
 type DisplayList = Vec<DisplayCommand>;
@@ -6,6 +7,7 @@ type DisplayList = Vec<DisplayCommand>;
 #[derive(Debug)]
 enum DisplayCommand {
     SolidColor(Color, Rect),
+    Text(Color, Rect, String),
 }
 
 fn build_display_list(layout_root: &LayoutBox) -> DisplayList {
@@ -17,6 +19,7 @@ fn build_display_list(layout_root: &LayoutBox) -> DisplayList {
 fn render_layout_box(list: &mut DisplayList, layout_box: &LayoutBox) {
     render_background(list, layout_box);
     render_borders(list, layout_box);
+    render_lines(list, layout_box);
 
     for child in &layout_box.children {
         render_layout_box(list, child);
@@ -24,80 +27,111 @@ fn render_layout_box(list: &mut DisplayList, layout_box: &LayoutBox) {
 }
 
 fn render_background(list: &mut DisplayList, layout_box: &LayoutBox) {
-    get_color(layout_box, "background").map(|color| {
+    if let Some(color) = get_color(layout_box, "background") {
         list.push(DisplayCommand::SolidColor(
             color,
             layout_box.dimensions.border_box(),
         ))
-    });
+    }
 }
 
-fn get_color(layout_box: &LayoutBox, name: &str) -> Option<Color> {
-    match &layout_box.box_type {
-        BoxType::BlockNode(style_node) | BoxType::InlineNode(style_node) => {
-            match style_node.value(name) {
-                Some(Value::ColorValue(color)) => Some(color),
-                _ => None,
+// Inline elements paint their background behind each piece of text they contain.
+fn render_lines(list: &mut DisplayList, layout_box: &LayoutBox) {
+    for item in layout_box.lines.iter().flat_map(|line| &line.items) {
+        for style_node in &item.nodes {
+            if let Some(Value::Color(color)) = style_node.value("background") {
+                list.push(DisplayCommand::SolidColor(color, item.rect));
             }
         }
+
+        let text_node = item.nodes[item.nodes.len() - 1];
+        let color = match text_node.value("color") {
+            Some(Value::Color(color)) => color,
+            _ => BLACK,
+        };
+        list.push(DisplayCommand::Text(color, item.rect, item.text.clone()));
+    }
+}
+
+fn get_color(layout_box: &LayoutBox, name: &str) -> Option<Color> {
+    match &layout_box.box_type {
+        BoxType::BlockNode(style_node)
+        | BoxType::FlexNode(style_node)
+        | BoxType::InlineNode(style_node) => match style_node.value(name) {
+            Some(Value::Color(color)) => Some(color),
+            _ => None,
+        },
         _ => None,
     }
 }
 
 fn render_borders(list: &mut DisplayList, layout_box: &LayoutBox) {
-    let color = match get_color(layout_box, "border-color") {
-        Some(color) => color,
-        None => return,
-    };
-
     let d = &layout_box.dimensions;
     let border_box = d.border_box();
 
-    // Left border
-    list.push(DisplayCommand::SolidColor(
-        color,
-        Rect {
-            x: border_box.x,
-            y: border_box.y,
-            width: d.border.left,
-            height: border_box.height,
-        },
-    ));
-
-    // Right border
-    list.push(DisplayCommand::SolidColor(
-        color,
-        Rect {
-            x: border_box.x + border_box.width - d.border.right,
-            y: border_box.y,
-            width: d.border.right,
-            height: border_box.height,
-        },
-    ));
-
-    // Top border
-    list.push(DisplayCommand::SolidColor(
-        color,
-        Rect {
-            x: border_box.x,
-            y: border_box.y,
-            width: border_box.width,
-            height: d.border.top,
-        },
-    ));
-
-    // Bottom border
-    list.push(DisplayCommand::SolidColor(
-        color,
-        Rect {
-            x: border_box.x,
-            y: border_box.y + border_box.height - d.border.bottom,
-            width: border_box.width,
-            height: d.border.bottom,
-        },
-    ));
+    let edges = [
+        (
+            "top",
+            Rect {
+                x: border_box.x,
+                y: border_box.y,
+                width: border_box.width,
+                height: d.border.top,
+            },
+        ),
+        (
+            "right",
+            Rect {
+                x: border_box.x + border_box.width - d.border.right,
+                y: border_box.y,
+                width: d.border.right,
+                height: border_box.height,
+            },
+        ),
+        (
+            "bottom",
+            Rect {
+                x: border_box.x,
+                y: border_box.y + border_box.height - d.border.bottom,
+                width: border_box.width,
+                height: d.border.bottom,
+            },
+        ),
+        (
+            "left",
+            Rect {
+                x: border_box.x,
+                y: border_box.y,
+                width: d.border.left,
+                height: border_box.height,
+            },
+        ),
+    ];
+    for (side, rect) in edges {
+        if rect.width > 0.0 && rect.height > 0.0 {
+            list.push(DisplayCommand::SolidColor(
+                border_color(layout_box, side),
+                rect,
+            ));
+        }
+    }
 }
 
+// Borders without a color of their own are painted with the foreground color.
+fn border_color(layout_box: &LayoutBox, side: &str) -> Color {
+    get_color(layout_box, &format!("border-{}-color", side))
+        .or_else(|| get_color(layout_box, "border-color"))
+        .or_else(|| get_color(layout_box, "color"))
+        .unwrap_or(BLACK)
+}
+
+const BLACK: Color = Color {
+    r: 0,
+    g: 0,
+    b: 0,
+    a: 255,
+};
+
 #[derive(Debug)]
 pub struct Canvas {
     pub pixels: Vec<Color>,
@@ -115,31 +149,76 @@ impl Canvas {
         };
         Canvas {
             pixels: vec![white; width * height],
-            width: width,
-            height: height,
+            width,
+            height,
         }
     }
 
     fn paint_item(&mut self, item: &DisplayCommand) {
         match item {
-            &DisplayCommand::SolidColor(color, rect) => {
-                // Clip the rectangle to the canvas boundaries.
-                let x0 = rect.x.clamp(0.0, self.width as f32) as usize;
-                let y0 = rect.y.clamp(0.0, self.height as f32) as usize;
-                let x1 = (rect.x + rect.width).clamp(0.0, self.width as f32) as usize;
-                let y1 = (rect.y + rect.height).clamp(0.0, self.height as f32) as usize;
-
-                for y in y0..y1 {
-                    for x in x0..x1 {
-                        // TODO: alpha compositing with existing pixel
-                        self.pixels[x + y * self.width] = color;
+            &DisplayCommand::SolidColor(color, rect) => self.fill_rect(color, rect),
+            DisplayCommand::Text(color, rect, text) => self.draw_text(*color, *rect, text),
+        }
+    }
+
+    fn fill_rect(&mut self, color: Color, rect: Rect) {
+        // Clip the rectangle to the canvas boundaries.
+        let x0 = rect.x.clamp(0.0, self.width as f32) as usize;
+        let y0 = rect.y.clamp(0.0, self.height as f32) as usize;
+        let x1 = (rect.x + rect.width).clamp(0.0, self.width as f32) as usize;
+        let y1 = (rect.y + rect.height).clamp(0.0, self.height as f32) as usize;
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let pixel = &mut self.pixels[x + y * self.width];
+                *pixel = blend(color, *pixel);
+            }
+        }
+    }
+
+    // Draw each character into its cell, dot by dot, leaving a small margin around the glyph.
+    fn draw_text(&mut self, color: Color, rect: Rect, text: &str) {
+        let char_width = font::char_width(rect.height);
+        let dot_width = char_width / 8.0;
+        let dot_height = rect.height / 8.0;
+        for (i, c) in text.chars().enumerate() {
+            let cell_x = rect.x + i as f32 * char_width;
+            for (row, bits) in font::glyph(c).iter().enumerate() {
+                for column in 0..font::GLYPH_WIDTH {
+                    if bits & (1 << (font::GLYPH_WIDTH - 1 - column)) == 0 {
+                        continue;
                     }
+                    let dot = Rect {
+                        x: cell_x + (column + 1) as f32 * dot_width,
+                        y: rect.y + (row as f32 + 0.5) * dot_height,
+                        width: dot_width,
+                        height: dot_height,
+                    };
+                    self.fill_rect(color, dot);
                 }
             }
         }
     }
 }
 
+// Composite `src` over `dst` ("source over" with premultiplied alpha).
+fn blend(src: Color, dst: Color) -> Color {
+    let src_alpha = src.a as f32 / 255.0;
+    let dst_alpha = dst.a as f32 / 255.0 * (1.0 - src_alpha);
+    let alpha = src_alpha + dst_alpha;
+    if alpha == 0.0 {
+        return src;
+    }
+    let channel =
+        |s: u8, d: u8| ((s as f32 * src_alpha + d as f32 * dst_alpha) / alpha).round() as u8;
+    Color {
+        r: channel(src.r, dst.r),
+        g: channel(src.g, dst.g),
+        b: channel(src.b, dst.b),
+        a: (alpha * 255.0).round() as u8,
+    }
+}
+
 pub fn paint(layout_root: &LayoutBox, bounds: Rect) -> Canvas {
     let display_list = build_display_list(layout_root);
     let mut canvas = Canvas::new(bounds.width as usize, bounds.height as usize);
@@ -148,3 +227,150 @@ pub fn paint(layout_root: &LayoutBox, bounds: Rect) -> Canvas {
     }
     canvas
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::{layout_tree, Dimensions};
+    use crate::{css, dom, style};
+
+    #[test]
+    fn paint_text_glyphs() {
+        let dom_tree = dom::parse("<p>-</p>".to_string());
+        let style_sheet = css::parse("p { display: block; color: #ff0000; }".to_string());
+        let style_tree = style::style_tree(&dom_tree, &style_sheet);
+        let viewport = Rect {
+            x: 0.0,
+            y: 0.0,
+            width: 16.0,
+            height: 16.0,
+        };
+        let layout_root = layout_tree(
+            &style_tree,
+            Dimensions {
+                content: viewport,
+                ..Default::default()
+            },
+        );
+        let canvas = paint(&layout_root, viewport);
+
+        // The dash is the fourth row of the glyph, which covers y = 7..9 in a 16px cell.
+        let red = Color {
+            r: 255,
+            g: 0,
+            b: 0,
+            a: 255,
+        };
+        let painted = (0..canvas.height)
+            .flat_map(|y| (0..canvas.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| canvas.pixels[x + y * canvas.width] == red)
+            .collect::<Vec<_>>();
+        let expected = (7..9)
+            .flat_map(|y| (1..6).map(move |x| (x, y)))
+            .collect::<Vec<_>>();
+        assert_eq!(painted, expected);
+    }
+
+    #[test]
+    fn blend_translucent_colors_over_white() {
+        let white = Color {
+            r: 255,
+            g: 255,
+            b: 255,
+            a: 255,
+        };
+        let translucent_red = Color {
+            r: 255,
+            g: 0,
+            b: 0,
+            a: 128,
+        };
+        let transparent = Color { a: 0, ..BLACK };
+        assert_eq!(
+            blend(translucent_red, white),
+            Color {
+                r: 255,
+                g: 127,
+                b: 127,
+                a: 255
+            }
+        );
+        assert_eq!(blend(transparent, white), white);
+        assert_eq!(blend(BLACK, white), BLACK);
+    }
+
+    #[test]
+    fn paint_borders_with_foreground_color_by_default() {
+        let dom_tree = dom::parse("<div></div>".to_string());
+        let style_sheet = css::parse(
+            "div { display: block; width: 4px; height: 4px; color: #ff0000; border: 2px solid; border-left-color: #0000ff; }"
+                .to_string(),
+        );
+        let style_tree = style::style_tree(&dom_tree, &style_sheet);
+        let viewport = Rect {
+            x: 0.0,
+            y: 0.0,
+            width: 10.0,
+            height: 10.0,
+        };
+        let layout_root = layout_tree(
+            &style_tree,
+            Dimensions {
+                content: viewport,
+                ..Default::default()
+            },
+        );
+        let canvas = paint(&layout_root, viewport);
+
+        let red = Color {
+            r: 255,
+            g: 0,
+            b: 0,
+            a: 255,
+        };
+        let blue = Color {
+            r: 0,
+            g: 0,
+            b: 255,
+            a: 255,
+        };
+        let white = Color {
+            r: 255,
+            g: 255,
+            b: 255,
+            a: 255,
+        };
+        let pixel = |x: usize, y: usize| canvas.pixels[x + y * canvas.width];
+        // The 8x8 border box has a 2px border around a 4x4 content box.
+        assert_eq!(pixel(5, 0), red);
+        assert_eq!(pixel(7, 5), red);
+        assert_eq!(pixel(5, 7), red);
+        assert_eq!(pixel(0, 5), blue);
+        assert_eq!(pixel(1, 1), blue);
+        assert_eq!(pixel(4, 4), white);
+        assert_eq!(pixel(8, 8), white);
+    }
+
+    #[test]
+    fn border_without_style_is_not_painted() {
+        let dom_tree = dom::parse("<div></div>".to_string());
+        let style_sheet = css::parse(
+            "div { display: block; border-width: 2px; border-color: #ff0000; }".to_string(),
+        );
+        let style_tree = style::style_tree(&dom_tree, &style_sheet);
+        let layout_root = layout_tree(
+            &style_tree,
+            Dimensions {
+                content: Rect {
+                    x: 0.0,
+                    y: 0.0,
+                    width: 10.0,
+                    height: 10.0,
+                },
+                ..Default::default()
+            },
+        );
+        assert_eq!(layout_root.dimensions.border.left, 0.0);
+        assert_eq!(layout_root.dimensions.border.top, 0.0);
+    }
+}
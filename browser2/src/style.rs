@@ -1,10 +1,14 @@
 use std::collections::HashMap;
 
-use crate::css::{Rule, Selector, SimpleSelector, Specificity, StyleSheet, Value};
+use crate::css::{Rule, Selector, SimpleSelector, Specificity, StyleSheet, Unit, Value};
 use crate::dom::{ElementData, Node, NodeType};
+use crate::font;
 
 type PropertyMap = HashMap<String, Value>;
 
+// Properties that take the parent's value when a node doesn't specify them.
+const INHERITED_PROPERTIES: [&str; 2] = ["color", "font-size"];
+
 #[derive(Debug)]
 pub struct StyleNode<'a> {
     pub node: &'a Node,
@@ -15,18 +19,20 @@ pub struct StyleNode<'a> {
 pub enum Display {
     Inline,
     Block,
+    Flex,
     None,
 }
 
 impl<'a> StyleNode<'a> {
     pub fn value(&self, name: &str) -> Option<Value> {
-        self.specified_values.get(name).map(|v| v.clone())
+        self.specified_values.get(name).cloned()
     }
 
     pub fn display(&self) -> Display {
         match self.value("display") {
             Some(Value::Keyword(s)) => match s.as_ref() {
                 "block" => Display::Block,
+                "flex" => Display::Flex,
                 "none" => Display::None,
                 _ => Display::Inline,
             },
@@ -34,15 +40,48 @@ impl<'a> StyleNode<'a> {
         }
     }
 
+    pub fn font_size(&self) -> f32 {
+        match self.value("font-size") {
+            Some(Value::Length(size, _)) => size,
+            _ => font::DEFAULT_FONT_SIZE,
+        }
+    }
+
     pub fn lookup(&self, name: &str, fallback_name: &str, default: &Value) -> Value {
         self.value(name)
             .unwrap_or_else(|| self.value(fallback_name).unwrap_or_else(|| default.clone()))
     }
+
+    // The used width of one side of the border, which is 0 unless the side has a visible
+    // `border-style`.
+    pub fn border_width(&self, side: &str) -> Value {
+        let zero = Value::Length(0.0, Unit::Px);
+        let none = Value::Keyword("none".to_string());
+        match self.lookup(&format!("border-{}-style", side), "border-style", &none) {
+            Value::Keyword(style) if style != "none" && style != "hidden" => {
+                self.lookup(&format!("border-{}-width", side), "border-width", &zero)
+            }
+            _ => zero,
+        }
+    }
 }
 
-fn matches(elem: &ElementData, selector: &Selector) -> bool {
+// `ancestors` are the elements containing `elem`, from the root down to its parent.
+fn matches(elem: &ElementData, ancestors: &[&ElementData], selector: &Selector) -> bool {
     match *selector {
         Selector::Simple(ref simple_selector) => matches_simple_selector(elem, simple_selector),
+        Selector::Descendant(ref ancestor_selector, ref selector) => {
+            matches(elem, ancestors, selector)
+                && (0..ancestors.len())
+                    .rev()
+                    .any(|i| matches(ancestors[i], &ancestors[..i], ancestor_selector))
+        }
+        Selector::Child(ref parent_selector, ref selector) => {
+            matches(elem, ancestors, selector)
+                && ancestors
+                    .split_last()
+                    .is_some_and(|(parent, rest)| matches(parent, rest, parent_selector))
+        }
     }
 }
 
@@ -73,25 +112,37 @@ fn matches_simple_selector(elem: &ElementData, selector: &SimpleSelector) -> boo
 
 type MatchRule<'a> = (Specificity, &'a Rule);
 
-fn match_rule<'a>(elem: &ElementData, rule: &'a Rule) -> Option<MatchRule<'a>> {
+fn match_rule<'a>(
+    elem: &ElementData,
+    ancestors: &[&ElementData],
+    rule: &'a Rule,
+) -> Option<MatchRule<'a>> {
     rule.selectors
         .iter()
-        .find(|selector| matches(elem, *selector))
+        .find(|selector| matches(elem, ancestors, selector))
         .map(|selector| (selector.specificity(), rule))
 }
 
-fn matching_rules<'a>(elem: &ElementData, stylesheet: &'a StyleSheet) -> Vec<MatchRule<'a>> {
+fn matching_rules<'a>(
+    elem: &ElementData,
+    ancestors: &[&ElementData],
+    stylesheet: &'a StyleSheet,
+) -> Vec<MatchRule<'a>> {
     stylesheet
         .rules
         .iter()
-        .filter_map(|rule| match_rule(elem, rule))
+        .filter_map(|rule| match_rule(elem, ancestors, rule))
         .collect()
 }
 
-fn specified_values(elem: &ElementData, stylesheet: &StyleSheet) -> PropertyMap {
+fn specified_values(
+    elem: &ElementData,
+    ancestors: &[&ElementData],
+    stylesheet: &StyleSheet,
+) -> PropertyMap {
     let mut values = HashMap::new();
-    let mut rules = matching_rules(elem, stylesheet);
-    rules.sort_by(|&(a, _), &(b, _)| a.cmp(&b));
+    let mut rules = matching_rules(elem, ancestors, stylesheet);
+    rules.sort_by_key(|&(a, _)| a);
     for (_, rule) in rules {
         for declaration in &rule.declarations {
             values.insert(declaration.name.clone(), declaration.value.clone());
@@ -101,16 +152,135 @@ fn specified_values(elem: &ElementData, stylesheet: &StyleSheet) -> PropertyMap
 }
 
 pub fn style_tree<'a>(root: &'a Node, stylesheet: &'a StyleSheet) -> StyleNode<'a> {
+    style_node(root, stylesheet, &HashMap::new(), &mut Vec::new())
+}
+
+fn style_node<'a>(
+    node: &'a Node,
+    stylesheet: &'a StyleSheet,
+    parent_values: &PropertyMap,
+    ancestors: &mut Vec<&'a ElementData>,
+) -> StyleNode<'a> {
+    let mut values = match node.node_type {
+        NodeType::Element(ref elem) => specified_values(elem, ancestors, stylesheet),
+        NodeType::Text(_) => HashMap::new(),
+    };
+    for name in INHERITED_PROPERTIES {
+        if let (false, Some(value)) = (values.contains_key(name), parent_values.get(name)) {
+            values.insert(name.to_string(), value.clone());
+        }
+    }
+
+    if let NodeType::Element(ref elem) = node.node_type {
+        ancestors.push(elem);
+    }
+    let children = node
+        .children
+        .iter()
+        .map(|child| style_node(child, stylesheet, &values, ancestors))
+        .collect();
+    if let NodeType::Element(_) = node.node_type {
+        ancestors.pop();
+    }
+
     StyleNode {
-        node: root,
-        specified_values: match root.node_type {
-            NodeType::Element(ref elem) => specified_values(elem, stylesheet),
-            NodeType::Text(_) => HashMap::new(),
-        },
-        children: root
-            .children
+        node,
+        specified_values: values,
+        children,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::{self, Color};
+    use crate::dom;
+
+    fn color_of_root(html: &str, css: &str) -> Option<Value> {
+        let dom_tree = dom::parse(html.to_string());
+        let style_sheet = css::parse(css.to_string());
+        style_tree(&dom_tree, &style_sheet).value("color")
+    }
+
+    fn color(r: u8, g: u8, b: u8) -> Option<Value> {
+        Some(Value::Color(Color { r, g, b, a: 255 }))
+    }
+
+    #[test]
+    fn more_specific_selector_wins() {
+        let html = "<div id=\"main\" class=\"important\"></div>";
+
+        // Later rules with lower specificity don't override earlier, more specific ones.
+        let css =
+            "#main { color: #ff0000; } .important { color: #00ff00; } div { color: #0000ff; }";
+        assert_eq!(color_of_root(html, css), color(255, 0, 0));
+        let css = ".important { color: #00ff00; } div { color: #0000ff; }";
+        assert_eq!(color_of_root(html, css), color(0, 255, 0));
+        let css = "div { color: #0000ff; }";
+        assert_eq!(color_of_root(html, css), color(0, 0, 255));
+    }
+
+    #[test]
+    fn later_rule_wins_on_equal_specificity() {
+        let html = "<div class=\"a b\"></div>";
+        let css = ".a { color: #ff0000; } .b { color: #00ff00; }";
+        assert_eq!(color_of_root(html, css), color(0, 255, 0));
+        let css = ".b { color: #00ff00; } .a { color: #ff0000; }";
+        assert_eq!(color_of_root(html, css), color(255, 0, 0));
+    }
+
+    #[test]
+    fn specificity_counts_ids_classes_and_tags() {
+        let style_sheet = css::parse("div#main.a.b, *, .a { color: #000000; }".to_string());
+        let specificities = style_sheet.rules[0]
+            .selectors
             .iter()
-            .map(|child| style_tree(child, stylesheet))
-            .collect(),
+            .map(|selector| selector.specificity())
+            .collect::<Vec<_>>();
+        assert_eq!(specificities, vec![(1, 2, 1), (0, 1, 0), (0, 0, 0)]);
+    }
+
+    // Collect the `color` of every element, in document order.
+    fn colors<'a>(node: &StyleNode<'a>, result: &mut Vec<Option<Value>>) {
+        if let NodeType::Element(_) = node.node.node_type {
+            result.push(node.specified_values.get("color").cloned());
+        }
+        for child in &node.children {
+            colors(child, result);
+        }
+    }
+
+    #[test]
+    fn match_descendant_and_child_selectors() {
+        let dom_tree =
+            dom::parse("<div class=\"nav\"><ul><li></li></ul><li></li></div><li></li>".to_string());
+        let style_sheet = css::parse(
+            ".nav li { color: #ff0000; } div > ul { color: #00ff00; } .nav > li { color: #0000ff; }"
+                .to_string(),
+        );
+        let mut result = Vec::new();
+        colors(&style_tree(&dom_tree, &style_sheet), &mut result);
+        assert_eq!(
+            result,
+            vec![
+                None,             // html
+                None,             // div.nav
+                color(0, 255, 0), // div > ul
+                color(255, 0, 0), // .nav li, at any depth
+                color(0, 0, 255), // .nav > li overrides the equally specific .nav li
+                None,             // li outside .nav
+            ]
+        );
+
+        let style_sheet = css::parse("html > ul, div li li { color: #ff0000; }".to_string());
+        let mut result = Vec::new();
+        colors(&style_tree(&dom_tree, &style_sheet), &mut result);
+        assert!(result.iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn descendant_selector_specificity() {
+        let style_sheet = css::parse("div .nav > li#home { color: #000000; }".to_string());
+        assert_eq!(style_sheet.rules[0].selectors[0].specificity(), (1, 1, 2));
     }
 }
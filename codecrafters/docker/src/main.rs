@@ -1,27 +1,84 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use flate2::read::GzDecoder;
-use libc::{c_char, c_int};
+use libc::{c_char, c_int, c_ulong, c_void};
 use reqwest::Client;
 use serde::Deserialize;
+use sha2::{Digest, Sha256};
 use tar::Archive;
 use tempfile::{tempdir, TempDir};
 
 use std::{
     env::{self, set_current_dir},
     ffi::CString,
-    fs::{copy, create_dir_all, set_permissions, File, Permissions},
-    io::{stderr, stdout, Write},
-    os::unix::prelude::PermissionsExt,
-    process::{exit, Command},
+    fs::{copy, create_dir_all, read, rename, set_permissions, write, File, Permissions},
+    io::{stderr, stdout, BufRead, BufReader, Error, Write},
+    os::unix::prelude::{AsRawFd, CommandExt, OsStrExt, PermissionsExt},
+    path::{Path, PathBuf},
+    process::{self, exit, Command},
+    ptr::null,
 };
 
 const EXEC_MODE: u32 = 0o777; // Read/write/execute for owner and group, read/execute for others
 const RW_MODE: u32 = 0o666; // Read/write for owner and group, read for others
 const CLONE_NEWPID: c_int = 0x20000000;
+const CLONE_NEWNS: c_int = 0x00020000;
+const CLONE_NEWNET: c_int = 0x40000000;
+const MS_BIND: c_ulong = 0x1000;
+const MS_REC: c_ulong = 0x4000;
+const MS_PRIVATE: c_ulong = 0x40000;
+const MNT_DETACH: c_int = 0x2;
+const DOWNLOAD_ATTEMPTS: usize = 3;
+// the container is connected to the host through a veth pair on this subnet
+const CONTAINER_SUBNET: &str = "172.17.0.0/16";
+const HOST_VETH: &str = "veth0";
+const HOST_ADDRESS: &str = "172.17.0.1";
+const CONTAINER_VETH: &str = "eth0";
+const CONTAINER_ADDRESS: &str = "172.17.0.2";
+// the environment every container gets, unless overridden with -e or --env-file
+const DEFAULT_ENV: &[(&str, &str)] = &[
+    (
+        "PATH",
+        "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin",
+    ),
+    ("HOME", "/root"),
+];
+// manifest formats we can handle, the registry picks the first one it supports
+const MANIFEST_MEDIA_TYPES: &[&str] = &[
+    "application/vnd.docker.distribution.manifest.v2+json",
+    "application/vnd.docker.distribution.manifest.list.v2+json",
+    "application/vnd.oci.image.manifest.v1+json",
+    "application/vnd.oci.image.index.v1+json",
+];
 
 extern "C" {
     fn chroot(name: *const c_char) -> c_int;
-    fn unshare(flags: c_int);
+    fn unshare(flags: c_int) -> c_int;
+    fn mount(
+        source: *const c_char,
+        target: *const c_char,
+        filesystemtype: *const c_char,
+        mountflags: c_ulong,
+        data: *const c_void,
+    ) -> c_int;
+    fn umount2(target: *const c_char, flags: c_int) -> c_int;
+    fn setns(fd: c_int, nstype: c_int) -> c_int;
+}
+
+// Unmounts a volume when dropped. This has to happen before the temporary directory is
+// dropped, otherwise removing it would recurse into the host directory and delete its files.
+struct BindMount {
+    // the mount point seen from the host, and from inside the container once chrooted
+    targets: [CString; 2],
+}
+
+impl Drop for BindMount {
+    fn drop(&mut self) {
+        for target in &self.targets {
+            if unsafe { umount2(target.as_ptr(), MNT_DETACH) } == 0 {
+                break;
+            }
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -35,13 +92,60 @@ struct FsLayer {
     blob_sum: String,
 }
 
+// the deprecated v1 manifest, only used if the registry doesn't support v2
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct ManifestResponse {
-    name: String,
     fs_layers: Vec<FsLayer>,
 }
 
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Descriptor {
+    media_type: String,
+    size: u64,
+    digest: String,
+}
+
+// see: https://docs.docker.com/registry/spec/manifest-v2-2/
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ManifestV2 {
+    schema_version: u32,
+    // OCI manifests may leave this out
+    media_type: Option<String>,
+    config: Descriptor,
+    layers: Vec<Descriptor>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Platform {
+    architecture: String,
+    os: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlatformManifest {
+    digest: String,
+    platform: Platform,
+}
+
+// a multi-platform image points to one manifest per platform
+#[derive(Debug, Deserialize)]
+struct ManifestList {
+    manifests: Vec<PlatformManifest>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Manifest {
+    List(ManifestList),
+    V2(ManifestV2),
+    V1(ManifestResponse),
+}
+
 fn create_dev_null(temp_dir: &TempDir) -> Result<()> {
     let dev_dir_path = temp_dir.path().join("dev");
     create_dir_all(&dev_dir_path)?;
@@ -51,25 +155,431 @@ fn create_dev_null(temp_dir: &TempDir) -> Result<()> {
     Ok(())
 }
 
+// Fetch the manifest of `reference` (a tag or a digest) and return the digests of its layers,
+// base layer first.
+async fn fetch_layer_digests(
+    client: &Client,
+    token: &str,
+    repository: &str,
+    reference: &str,
+) -> Result<Vec<String>> {
+    let mut reference = reference.to_string();
+    loop {
+        let manifest_endpoint = format!(
+            "https://registry.hub.docker.com/v2/{}/manifests/{}",
+            repository, reference
+        );
+        let manifest = client
+            .get(&manifest_endpoint)
+            .header("Authorization", format!("Bearer {}", token))
+            .header("Accept", MANIFEST_MEDIA_TYPES.join(", "))
+            .send()
+            .await?
+            .json::<Manifest>()
+            .await
+            .with_context(|| format!("Failed to parse the manifest of {}", manifest_endpoint))?;
+
+        match manifest {
+            Manifest::List(ManifestList { manifests }) => {
+                let platform = manifests
+                    .into_iter()
+                    .find(|m| m.platform.os == "linux" && m.platform.architecture == architecture())
+                    .ok_or_else(|| anyhow!("No image found for linux/{}", architecture()))?;
+                reference = platform.digest;
+            }
+            Manifest::V2(ManifestV2 { layers, .. }) => {
+                return Ok(layers.into_iter().map(|layer| layer.digest).collect());
+            }
+            // v1 manifests list the layers from the newest to the base one
+            Manifest::V1(ManifestResponse { fs_layers }) => {
+                return Ok(fs_layers
+                    .into_iter()
+                    .rev()
+                    .map(|layer| layer.blob_sum)
+                    .collect());
+            }
+        }
+    }
+}
+
+async fn fetch_and_unpack_layer(
+    client: &Client,
+    token: &str,
+    repository: &str,
+    digest: &str,
+    dir: &TempDir,
+    cache_dir: &Path,
+) -> Result<()> {
+    let cache_path = cache_dir.join(hex_digest(digest)?);
+    let layer_blob = match read_cached_blob(&cache_path, digest) {
+        Some(blob) => blob,
+        None => {
+            let blob = download_blob(client, token, repository, digest).await?;
+            verify_digest(&blob, digest)?;
+            // write to a temporary file first, so that an interrupted write is never taken
+            // for a cached layer
+            let temp_path = cache_path.with_extension("tmp");
+            write(&temp_path, &blob)?;
+            rename(&temp_path, &cache_path)?;
+            blob
+        }
+    };
+
+    // layer data is tar.gzip format. This information is included in the manifest.
+    let tar = GzDecoder::new(&*layer_blob);
+    let mut archive = Archive::new(tar);
+    archive.unpack(dir)?;
+    Ok(())
+}
+
+// a cached blob which doesn't match its digest anymore is downloaded again
+fn read_cached_blob(cache_path: &Path, digest: &str) -> Option<Vec<u8>> {
+    let blob = read(cache_path).ok().filter(|blob| !blob.is_empty())?;
+    verify_digest(&blob, digest).ok()?;
+    Some(blob)
+}
+
+async fn download_blob(
+    client: &Client,
+    token: &str,
+    repository: &str,
+    digest: &str,
+) -> Result<Vec<u8>> {
+    let layer_endpoint = format!(
+        "https://registry.hub.docker.com/v2/{}/blobs/{}",
+        repository, digest,
+    );
+    for _ in 0..DOWNLOAD_ATTEMPTS {
+        let response = client
+            .get(&layer_endpoint)
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await?;
+        let content_length = response.content_length();
+        let blob = response.bytes().await?;
+        // a connection closed in the middle of the body leaves a partial download
+        match content_length {
+            Some(len) if len != blob.len() as u64 => continue,
+            _ => return Ok(blob.to_vec()),
+        }
+    }
+    bail!(
+        "Failed to download {} after {} attempts",
+        digest,
+        DOWNLOAD_ATTEMPTS
+    )
+}
+
+// ~/.cache/from-scratch-docker/blobs/sha256/
+fn blob_cache_dir() -> Result<PathBuf> {
+    let home = env::var("HOME").context("HOME is not set, so layers can't be cached")?;
+    Ok(PathBuf::from(home).join(".cache/from-scratch-docker/blobs/sha256"))
+}
+
+fn verify_digest(blob: &[u8], digest: &str) -> Result<()> {
+    let expected = hex_digest(digest)?;
+    let actual = hex::encode(Sha256::digest(blob));
+    if actual != expected {
+        bail!(
+            "Digest mismatch: expected {}, got sha256:{}",
+            digest,
+            actual
+        );
+    }
+    Ok(())
+}
+
+fn hex_digest(digest: &str) -> Result<&str> {
+    digest
+        .strip_prefix("sha256:")
+        .ok_or_else(|| anyhow!("Unsupported digest algorithm: {}", digest))
+}
+
+// the architecture name used in image manifests
+fn architecture() -> &'static str {
+    match env::consts::ARCH {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        arch => arch,
+    }
+}
+
+// Create a veth pair and set up the host side of it, so that the container can reach the
+// internet through the host with NAT. Must be called before unsharing the network namespace.
+// Returns the temporary name of the container end, which may not be called eth0 yet because
+// the host can have an interface with that name.
+fn setup_host_network(host_netns: &File) -> Result<String> {
+    let container_veth = format!("veth{}", process::id());
+    run(
+        "ip",
+        &[
+            "link",
+            "add",
+            HOST_VETH,
+            "type",
+            "veth",
+            "peer",
+            "name",
+            &container_veth,
+        ],
+    )?;
+    if let Err(e) = configure_host_veth() {
+        delete_veth(host_netns);
+        return Err(e);
+    }
+    Ok(container_veth)
+}
+
+fn configure_host_veth() -> Result<()> {
+    run(
+        "ip",
+        &[
+            "addr",
+            "add",
+            &format!("{}/16", HOST_ADDRESS),
+            "dev",
+            HOST_VETH,
+        ],
+    )?;
+    run("ip", &["link", "set", HOST_VETH, "up"])?;
+
+    write("/proc/sys/net/ipv4/ip_forward", "1").context("Failed to enable IP forwarding")?;
+    let masquerade = |operation| {
+        run(
+            "iptables",
+            &[
+                "-t",
+                "nat",
+                operation,
+                "POSTROUTING",
+                "-s",
+                CONTAINER_SUBNET,
+                "!",
+                "-o",
+                HOST_VETH,
+                "-j",
+                "MASQUERADE",
+            ],
+        )
+    };
+    // The rule outlives the container, so it's only added once. Without it the container
+    // can still reach the host, so a missing iptables doesn't stop the container from running.
+    if masquerade("-C").is_err() {
+        let _ = masquerade("-A");
+    }
+    Ok(())
+}
+
+// Remove the veth pair after a failed setup, otherwise the next run can't create it again.
+// Deleting one end deletes the other one too.
+fn delete_veth(host_netns: &File) {
+    let _ = run_in_netns(host_netns, "ip", &["link", "delete", HOST_VETH]);
+}
+
+// Move the container end of the veth pair into the network namespace this process has just
+// unshared, and configure it. `host_netns` is the namespace the process was in before.
+fn setup_container_network(host_netns: &File, container_veth: &str) -> Result<()> {
+    // `ip` has to run in the host namespace, where the interface still is
+    let pid = process::id().to_string();
+    let moved = run_in_netns(
+        host_netns,
+        "ip",
+        &["link", "set", container_veth, "netns", &pid],
+    );
+    if let Err(e) = moved {
+        delete_veth(host_netns);
+        return Err(e.context(format!(
+            "Failed to move {} into the container",
+            container_veth
+        )));
+    }
+    // from here on the pair goes away together with the container's network namespace
+
+    run(
+        "ip",
+        &["link", "set", container_veth, "name", CONTAINER_VETH],
+    )?;
+    run("ip", &["link", "set", "lo", "up"])?;
+    run(
+        "ip",
+        &[
+            "addr",
+            "add",
+            &format!("{}/16", CONTAINER_ADDRESS),
+            "dev",
+            CONTAINER_VETH,
+        ],
+    )?;
+    run("ip", &["link", "set", CONTAINER_VETH, "up"])?;
+    run("ip", &["route", "add", "default", "via", HOST_ADDRESS])?;
+    Ok(())
+}
+
+fn run(program: &str, args: &[&str]) -> Result<()> {
+    check_output(Command::new(program).args(args), program, args)
+}
+
+// like `run`, but in the network namespace `netns` instead of the one this process is in
+fn run_in_netns(netns: &File, program: &str, args: &[&str]) -> Result<()> {
+    let netns = netns.as_raw_fd();
+    let mut command = Command::new(program);
+    command.args(args);
+    unsafe {
+        command.pre_exec(move || match setns(netns, CLONE_NEWNET) {
+            0 => Ok(()),
+            _ => Err(Error::last_os_error()),
+        });
+    }
+    check_output(&mut command, program, args)
+}
+
+fn check_output(command: &mut Command, program: &str, args: &[&str]) -> Result<()> {
+    let output = command
+        .output()
+        .with_context(|| format!("Failed to run {}", program))?;
+    if !output.status.success() {
+        bail!(
+            "'{} {}' failed: {}",
+            program,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+fn mount_volumes(volumes: &[(PathBuf, PathBuf)], temp_dir: &TempDir) -> Result<Vec<BindMount>> {
+    // the process is already in its own mount namespace, this keeps the bind mounts
+    // from propagating back to the host's namespace
+    let root = CString::new("/")?;
+    unsafe {
+        mount(null(), root.as_ptr(), null(), MS_REC | MS_PRIVATE, null());
+    }
+
+    let mut mounts = Vec::new();
+    for (host_path, container_path) in volumes {
+        if !host_path.exists() {
+            bail!("Volume host path does not exist: {}", host_path.display());
+        }
+        let relative_path = container_path.strip_prefix("/").with_context(|| {
+            format!(
+                "Volume container path must be absolute: {}",
+                container_path.display()
+            )
+        })?;
+        // the mount point has to exist, and be of the same kind as what is mounted on it
+        let target = temp_dir.path().join(relative_path);
+        if host_path.is_dir() {
+            create_dir_all(&target)?;
+        } else {
+            create_dir_all(target.parent().unwrap())?;
+            File::create(&target)?;
+        }
+
+        let source = CString::new(host_path.as_os_str().as_bytes())?;
+        let host_target = CString::new(target.as_os_str().as_bytes())?;
+        let container_target = CString::new(container_path.as_os_str().as_bytes())?;
+        let result = unsafe {
+            mount(
+                source.as_ptr(),
+                host_target.as_ptr(),
+                null(),
+                MS_BIND | MS_REC,
+                null(),
+            )
+        };
+        if result != 0 {
+            bail!(
+                "Failed to mount {} on {}: {}",
+                host_path.display(),
+                container_path.display(),
+                Error::last_os_error()
+            );
+        }
+        mounts.push(BindMount {
+            targets: [host_target, container_target],
+        });
+    }
+    Ok(mounts)
+}
+
+fn parse_env_pair(pair: &str) -> Result<(String, String)> {
+    // only the first `=` separates the key, the value may contain more of them
+    let (key, value) = pair
+        .split_once('=')
+        .with_context(|| format!("Expected KEY=VALUE, got '{}'", pair))?;
+    if key.is_empty() {
+        bail!("Expected KEY=VALUE, got '{}'", pair);
+    }
+    Ok((key.to_string(), value.to_string()))
+}
+
+// KEY=VALUE lines, blank lines and lines starting with # are skipped
+fn read_env_file(path: &str) -> Result<Vec<(String, String)>> {
+    let file = File::open(path).with_context(|| format!("Failed to open env file {}", path))?;
+    let mut env_pairs = Vec::new();
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.with_context(|| format!("Failed to read env file {}", path))?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        env_pairs.push(
+            parse_env_pair(line)
+                .with_context(|| format!("Failed to parse env file {} at line {}", path, i + 1))?,
+        );
+    }
+    Ok(env_pairs)
+}
+
 fn copy_executable_binary(command: &str, temp_dir: &TempDir) -> Result<()> {
     let dist_path = temp_dir.path().join(command.trim_start_matches("/"));
-    create_dir_all(&dist_path.parent().unwrap())?;
+    create_dir_all(dist_path.parent().unwrap())?;
     copy(command, &dist_path)?;
     set_permissions(&dist_path, Permissions::from_mode(EXEC_MODE))?;
     Ok(())
 }
 
-// Usage: your_docker.sh run <image> <command> <arg1> <arg2> ...
+// Usage: your_docker.sh run [-v <host_path>:<container_path>] [-e KEY=VALUE] [--env-file <path>]...
+//                           <image> <command> <arg1> <arg2> ...
 #[tokio::main]
 async fn main() -> Result<()> {
     let args: Vec<_> = env::args().collect();
-    let image = if args[2].contains(":") {
-        String::from(&args[2])
+    let mut volumes = Vec::new();
+    let mut env_pairs = DEFAULT_ENV
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect::<Vec<_>>();
+    let mut i = 2;
+    while args.get(i).is_some_and(|arg| arg.starts_with('-')) {
+        let value = args
+            .get(i + 1)
+            .with_context(|| format!("{} requires a value", args[i]))?;
+        match args[i].as_str() {
+            "-e" => env_pairs.push(parse_env_pair(value)?),
+            "--env-file" => env_pairs.extend(read_env_file(value)?),
+            "-v" => {
+                let (host_path, container_path) = value
+                    .split_once(':')
+                    .with_context(|| format!("Invalid volume: {}", value))?;
+                volumes.push((PathBuf::from(host_path), PathBuf::from(container_path)));
+            }
+            option => bail!("Unknown option: {}", option),
+        }
+        i += 2;
+    }
+    let args = &args[i..];
+    if args.len() < 2 {
+        bail!("Usage: your_docker.sh run [-v <host_path>:<container_path>] [-e KEY=VALUE] [--env-file <path>]... <image> <command> <arg1> <arg2> ...");
+    }
+
+    let image = if args[0].contains(":") {
+        String::from(&args[0])
     } else {
-        format!("{}:latest", &args[2])
+        format!("{}:latest", &args[0])
     };
-    let command = &args[3];
-    let command_args = &args[4..];
+    let command = &args[1];
+    let command_args = &args[2..];
 
     // create temporary directory and /dev/null
     let dir = tempdir()?;
@@ -92,38 +602,30 @@ async fn main() -> Result<()> {
         .json::<AuthResponse>()
         .await?;
 
-    let image_manifest_endpoint = format!(
-        "https://registry.hub.docker.com/v2/library/{}/manifests/{}",
-        &image_metadata[0], &image_metadata[1],
-    );
-    let ManifestResponse { name, fs_layers } = client
-        .get(&image_manifest_endpoint)
-        .header("Authorization", format!("Bearer {}", &token))
-        .send()
-        .await?
-        .json::<ManifestResponse>()
-        .await?;
-
-    for layer in fs_layers {
-        let layer_endpoint = format!(
-            "https://registry.hub.docker.com/v2/{}/blobs/{}",
-            &name, &layer.blob_sum,
-        );
-        // layer data is tar.gzip format. This information is included in the manifest.
-        // see: https://docs.docker.com/registry/spec/manifest-v2-2/
-        let layer_blob = client
-            .get(&layer_endpoint)
-            .header("Authorization", format!("Bearer {}", &token))
-            .send()
-            .await?
-            .bytes()
-            .await?;
+    let repository = format!("library/{}", image_metadata[0]);
+    let layer_digests =
+        fetch_layer_digests(&client, &token, &repository, image_metadata[1]).await?;
+    let cache_dir = blob_cache_dir()?;
+    create_dir_all(&cache_dir)?;
+    for digest in layer_digests {
+        fetch_and_unpack_layer(&client, &token, &repository, &digest, &dir, &cache_dir).await?;
+    }
 
-        // write layer data to gzipped tar file and extract it
-        let tar = GzDecoder::new(&*layer_blob);
-        let mut archive = Archive::new(tar);
-        archive.unpack(&dir)?;
+    // give the container its own mount and network namespaces. The veth pair goes away
+    // together with the network namespace when the process exits.
+    let host_netns = File::open("/proc/self/ns/net")?;
+    let container_veth = setup_host_network(&host_netns)?;
+    unsafe {
+        if unshare(CLONE_NEWNS | CLONE_NEWNET) != 0 {
+            let error = Error::last_os_error();
+            delete_veth(&host_netns);
+            bail!("Failed to create the container namespaces: {}", error);
+        }
     }
+    setup_container_network(&host_netns, &container_veth)?;
+
+    // declared after `dir`, so that the volumes are unmounted before it's removed
+    let _mounts = mount_volumes(&volumes, &dir)?;
 
     // chroot to temporary directory and set current directory to /
     let dir_path = CString::new(dir.path().to_str().unwrap())?;
@@ -137,8 +639,11 @@ async fn main() -> Result<()> {
         unshare(CLONE_NEWPID);
     }
 
+    // later pairs override earlier ones, so the defaults can be replaced
     let output = Command::new(command)
         .args(command_args)
+        .env_clear()
+        .envs(env_pairs)
         .output()
         .with_context(|| {
             format!(
@@ -0,0 +1,98 @@
+use anyhow::{anyhow, Result};
+
+use crate::object::read_object;
+
+const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+pub struct CommitObject {
+    pub tree: String,
+    pub parents: Vec<String>,
+    pub author: String,
+    #[allow(dead_code)]
+    pub committer: String,
+    pub message: String,
+}
+
+impl CommitObject {
+    pub fn read(hash: &str) -> Result<CommitObject> {
+        let (kind, body) = read_object(hash)?;
+        if kind != "commit" {
+            return Err(anyhow!("{} is a {}, not a commit object", hash, kind));
+        }
+        parse_commit_object(&String::from_utf8(body)?)
+    }
+}
+
+// headers come first, one per line, followed by a blank line and the message
+pub fn parse_commit_object(raw: &str) -> Result<CommitObject> {
+    let (headers, message) = raw.split_once("\n\n").unwrap_or((raw, ""));
+
+    let mut tree = None;
+    let mut parents = Vec::new();
+    let mut author = None;
+    let mut committer = None;
+    for line in headers.lines() {
+        match line.split_once(' ') {
+            Some(("tree", value)) => tree = Some(value.to_string()),
+            Some(("parent", value)) => parents.push(value.to_string()),
+            Some(("author", value)) => author = Some(value.to_string()),
+            Some(("committer", value)) => committer = Some(value.to_string()),
+            // gpgsig and other extra headers, including their continuation lines
+            _ => {}
+        }
+    }
+
+    let missing = |field| anyhow!("Invalid commit object: missing {}", field);
+    Ok(CommitObject {
+        tree: tree.ok_or_else(|| missing("tree"))?,
+        parents,
+        author: author.ok_or_else(|| missing("author"))?,
+        committer: committer.ok_or_else(|| missing("committer"))?,
+        message: message.to_string(),
+    })
+}
+
+// splits `Name <email> timestamp timezone` into the identity and a date like
+// `Thu Oct 16 12:34:56 2026 +0900`, in the author's own timezone
+pub fn split_signature(signature: &str) -> Result<(&str, String)> {
+    let invalid = || anyhow!("Invalid signature: {}", signature);
+    let (rest, timezone) = signature.rsplit_once(' ').ok_or_else(invalid)?;
+    let (identity, timestamp) = rest.rsplit_once(' ').ok_or_else(invalid)?;
+    let timestamp: i64 = timestamp.parse().map_err(|_| invalid())?;
+
+    let offset: i64 = timezone.parse().map_err(|_| invalid())?;
+    let offset_secs = (offset / 100 * 60 + offset % 100) * 60;
+    Ok((identity, format_date(timestamp + offset_secs, timezone)))
+}
+
+fn format_date(local_secs: i64, timezone: &str) -> String {
+    let days = local_secs.div_euclid(86400);
+    let secs_of_day = local_secs.rem_euclid(86400);
+
+    // convert days since 1970-01-01 to a civil date (Howard Hinnant's algorithm)
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{} {} {} {:02}:{:02}:{:02} {} {}",
+        WEEKDAYS[days.rem_euclid(7) as usize],
+        MONTHS[month as usize - 1],
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60,
+        year,
+        timezone
+    )
+}
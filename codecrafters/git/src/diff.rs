@@ -0,0 +1,155 @@
+#[derive(Clone, Copy, PartialEq)]
+enum Edit {
+    Equal,
+    Delete,
+    Insert,
+}
+
+pub enum DiffLine<'a> {
+    Context(&'a str),
+    Delete(&'a str),
+    Insert(&'a str),
+}
+
+pub struct DiffHunk<'a> {
+    pub old_start: usize,
+    pub old_len: usize,
+    pub new_start: usize,
+    pub new_len: usize,
+    pub lines: Vec<DiffLine<'a>>,
+}
+
+impl DiffHunk<'_> {
+    // `@@ -1,3 +1,4 @@`; an empty range starts at the line before it, like GNU diff
+    pub fn header(&self) -> String {
+        let range = |start: usize, len: usize| match len {
+            0 => format!("{},0", start),
+            1 => format!("{}", start + 1),
+            _ => format!("{},{}", start + 1, len),
+        };
+        format!(
+            "@@ -{} +{} @@",
+            range(self.old_start, self.old_len),
+            range(self.new_start, self.new_len)
+        )
+    }
+}
+
+// groups the changes between `old` and `new` into hunks with up to `context`
+// unchanged lines around them; hunks whose context would overlap are merged
+pub fn diff<'a>(old: &[&'a str], new: &[&'a str], context: usize) -> Vec<DiffHunk<'a>> {
+    let edits = edit_script(old, new);
+
+    // the position in both sides before each edit
+    let mut positions = Vec::with_capacity(edits.len() + 1);
+    let (mut x, mut y) = (0, 0);
+    for edit in &edits {
+        positions.push((x, y));
+        match edit {
+            Edit::Equal => (x, y) = (x + 1, y + 1),
+            Edit::Delete => x += 1,
+            Edit::Insert => y += 1,
+        }
+    }
+    positions.push((x, y));
+
+    let changes = (0..edits.len())
+        .filter(|&i| edits[i] != Edit::Equal)
+        .collect::<Vec<_>>();
+    let mut hunks = Vec::new();
+    let mut i = 0;
+    while i < changes.len() {
+        let start = changes[i].saturating_sub(context);
+        let mut last = changes[i];
+        while i + 1 < changes.len() && changes[i + 1] - last <= 2 * context {
+            i += 1;
+            last = changes[i];
+        }
+        let end = (last + context + 1).min(edits.len());
+        i += 1;
+
+        let lines = (start..end)
+            .map(|j| {
+                let (x, y) = positions[j];
+                match edits[j] {
+                    Edit::Equal => DiffLine::Context(old[x]),
+                    Edit::Delete => DiffLine::Delete(old[x]),
+                    Edit::Insert => DiffLine::Insert(new[y]),
+                }
+            })
+            .collect();
+        let (old_start, new_start) = positions[start];
+        let (old_end, new_end) = positions[end];
+        hunks.push(DiffHunk {
+            old_start,
+            old_len: old_end - old_start,
+            new_start,
+            new_len: new_end - new_start,
+            lines,
+        });
+    }
+
+    hunks
+}
+
+// Myers' O(ND) algorithm: for each number of edits d, `v[k]` holds the furthest x
+// reached on diagonal k = x - y. The `v` of every round is kept so the shortest
+// path can be walked back from the end.
+fn edit_script(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max = n + m;
+    let index = |k: isize| (k + max) as usize;
+    let mut v = vec![0isize; 2 * max as usize + 2];
+    let mut trace = Vec::new();
+
+    'search: for d in 0..=max {
+        trace.push(v.clone());
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]) {
+                v[index(k + 1)]
+            } else {
+                v[index(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index(k)] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = v[index(prev_k)];
+        let prev_y = prev_x - prev_k;
+
+        while x > prev_x && y > prev_y {
+            edits.push(Edit::Equal);
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            edits.push(if x == prev_x {
+                Edit::Insert
+            } else {
+                Edit::Delete
+            });
+        }
+        (x, y) = (prev_x, prev_y);
+    }
+
+    edits.reverse();
+    edits
+}
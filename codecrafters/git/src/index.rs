@@ -0,0 +1,193 @@
+use anyhow::{anyhow, Result};
+use sha1::{Digest, Sha1};
+use std::{fs, os::unix::fs::MetadataExt, path::Path};
+
+const INDEX_PATH: &str = ".git/index";
+const ENTRY_FIXED_SIZE: usize = 62;
+
+pub struct IndexEntry {
+    pub ctime_sec: u32,
+    pub ctime_nsec: u32,
+    pub mtime_sec: u32,
+    pub mtime_nsec: u32,
+    pub dev: u32,
+    pub ino: u32,
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub size: u32,
+    pub sha1: [u8; 20],
+    pub flags: u16,
+    pub name: String,
+}
+
+impl IndexEntry {
+    pub fn from_file(path: &str, sha1: [u8; 20]) -> Result<IndexEntry> {
+        let metadata = fs::symlink_metadata(path)?;
+        let mode = if metadata.file_type().is_symlink() {
+            0o120000
+        } else if metadata.mode() & 0o111 != 0 {
+            0o100755
+        } else {
+            0o100644
+        };
+
+        Ok(IndexEntry {
+            ctime_sec: metadata.ctime() as u32,
+            ctime_nsec: metadata.ctime_nsec() as u32,
+            mtime_sec: metadata.mtime() as u32,
+            mtime_nsec: metadata.mtime_nsec() as u32,
+            dev: metadata.dev() as u32,
+            ino: metadata.ino() as u32,
+            mode,
+            uid: metadata.uid(),
+            gid: metadata.gid(),
+            size: metadata.size() as u32,
+            sha1,
+            flags: path.len().min(0xfff) as u16,
+            name: path.to_string(),
+        })
+    }
+}
+
+pub struct Index {
+    pub entries: Vec<IndexEntry>,
+}
+
+impl Index {
+    pub fn exists() -> bool {
+        Path::new(INDEX_PATH).exists()
+    }
+
+    pub fn read() -> Result<Index> {
+        if !Index::exists() {
+            return Ok(Index {
+                entries: Vec::new(),
+            });
+        }
+
+        Index::parse(&fs::read(INDEX_PATH)?)
+    }
+
+    pub fn parse(data: &[u8]) -> Result<Index> {
+        if data.len() < 12 || &data[..4] != b"DIRC" {
+            return Err(anyhow!("Invalid index file: missing DIRC header"));
+        }
+        let version = read_u32(data, 4)?;
+        if version != 2 {
+            return Err(anyhow!("Unsupported index version: {}", version));
+        }
+        let count = read_u32(data, 8)?;
+
+        let mut entries = Vec::with_capacity(count as usize);
+        let mut pos = 12;
+        for _ in 0..count {
+            let field = |i: usize| read_u32(data, pos + i * 4);
+            let sha1_start = pos + 40;
+            let sha1: [u8; 20] = data
+                .get(sha1_start..sha1_start + 20)
+                .ok_or_else(|| anyhow!("Invalid index file: truncated entry"))?
+                .try_into()?;
+            let flags = u16::from_be_bytes(
+                data.get(sha1_start + 20..sha1_start + 22)
+                    .ok_or_else(|| anyhow!("Invalid index file: truncated entry"))?
+                    .try_into()?,
+            );
+
+            let name_start = pos + ENTRY_FIXED_SIZE;
+            let name = data
+                .get(name_start..)
+                .and_then(|rest| rest.iter().position(|&b| b == 0).map(|len| &rest[..len]))
+                .ok_or_else(|| anyhow!("Invalid index file: unterminated entry name"))?;
+            let name_len = name.len();
+            let name = String::from_utf8(name.to_vec())?;
+
+            entries.push(IndexEntry {
+                ctime_sec: field(0)?,
+                ctime_nsec: field(1)?,
+                mtime_sec: field(2)?,
+                mtime_nsec: field(3)?,
+                dev: field(4)?,
+                ino: field(5)?,
+                mode: field(6)?,
+                uid: field(7)?,
+                gid: field(8)?,
+                size: field(9)?,
+                sha1,
+                flags,
+                name,
+            });
+            pos += entry_size(name_len);
+        }
+
+        Ok(Index { entries })
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(b"DIRC");
+        data.extend_from_slice(&2u32.to_be_bytes());
+        data.extend_from_slice(&(self.entries.len() as u32).to_be_bytes());
+
+        for entry in &self.entries {
+            let start = data.len();
+            for field in [
+                entry.ctime_sec,
+                entry.ctime_nsec,
+                entry.mtime_sec,
+                entry.mtime_nsec,
+                entry.dev,
+                entry.ino,
+                entry.mode,
+                entry.uid,
+                entry.gid,
+                entry.size,
+            ] {
+                data.extend_from_slice(&field.to_be_bytes());
+            }
+            data.extend_from_slice(&entry.sha1);
+            data.extend_from_slice(&entry.flags.to_be_bytes());
+            data.extend_from_slice(entry.name.as_bytes());
+            data.resize(start + entry_size(entry.name.len()), 0);
+        }
+
+        let checksum = Sha1::digest(&data);
+        data.extend_from_slice(&checksum);
+        data
+    }
+
+    pub fn write(&self) -> Result<()> {
+        fs::write(INDEX_PATH, self.serialize())?;
+        Ok(())
+    }
+
+    pub fn add(&mut self, entry: IndexEntry) {
+        match self.entries.binary_search_by(|e| e.name.cmp(&entry.name)) {
+            Ok(i) => self.entries[i] = entry,
+            Err(i) => self.entries.insert(i, entry),
+        }
+    }
+
+    // removes `path` itself or, if it names a directory (`""` for the root), every entry below it
+    pub fn remove(&mut self, path: &str) {
+        if path.is_empty() {
+            self.entries.clear();
+            return;
+        }
+        let dir = format!("{}/", path);
+        self.entries
+            .retain(|e| e.name != path && !e.name.starts_with(&dir));
+    }
+}
+
+// entries are NUL-terminated and padded to a multiple of 8 bytes
+fn entry_size(name_len: usize) -> usize {
+    (ENTRY_FIXED_SIZE + name_len + 8) / 8 * 8
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Result<u32> {
+    let bytes = data
+        .get(pos..pos + 4)
+        .ok_or_else(|| anyhow!("Invalid index file: truncated entry"))?;
+    Ok(u32::from_be_bytes(bytes.try_into()?))
+}
@@ -1,8 +1,304 @@
 use anyhow::Result;
-use flate2::read::{ZlibDecoder, ZlibEncoder};
-use flate2::Compression;
-use sha1::{Digest, Sha1};
-use std::{env, fs, io::Read, io::Write};
+use std::{
+    collections::{BTreeMap, HashMap},
+    env, fs,
+    io::{self, IsTerminal},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+mod commit;
+mod diff;
+mod index;
+mod object;
+mod refs;
+mod worktree;
+
+use commit::{split_signature, CommitObject};
+use diff::DiffLine;
+use index::{Index, IndexEntry};
+use object::{
+    hash_object, read_object, read_tree, read_tree_recursive, serialize_tree_object, write_object,
+    TreeEntry,
+};
+
+// prints blobs with their full path when recursing, like `git ls-tree -r`
+fn print_tree(hash: &str, prefix: &str, recursive: bool) -> Result<()> {
+    for entry in read_tree(hash)? {
+        let path = format!("{}{}", prefix, entry.name);
+        let hash = hex::encode(entry.hash);
+        if recursive && entry.kind() == "tree" {
+            print_tree(&hash, &format!("{}/", path), recursive)?;
+        } else {
+            println!("{:0>6} {} {}\t{}", entry.mode, entry.kind(), hash, path);
+        }
+    }
+
+    Ok(())
+}
+
+// writes blobs and trees for everything under `dir` except `.git`; empty directories
+// are skipped like git does, so this returns None for them
+fn write_tree(dir: &Path) -> Result<Option<String>> {
+    let mut entries = Vec::new();
+    for dir_entry in fs::read_dir(dir)? {
+        let dir_entry = dir_entry?;
+        let name = worktree::file_name(&dir_entry)?;
+        if name == ".git" {
+            continue;
+        }
+
+        let path = dir_entry.path();
+        let (mode, hash) = if dir_entry.file_type()?.is_dir() {
+            match write_tree(&path)? {
+                Some(hash) => ("40000", hash),
+                None => continue,
+            }
+        } else {
+            let (mode, content) = worktree::read_file(&path)?;
+            (mode, write_object("blob", &content)?)
+        };
+
+        let mut sha1 = [0u8; 20];
+        hex::decode_to_slice(&hash, &mut sha1)?;
+        entries.push(TreeEntry {
+            mode: mode.to_string(),
+            name,
+            hash: sha1,
+        });
+    }
+
+    if entries.is_empty() {
+        return Ok(None);
+    }
+    let tree = serialize_tree_object(&mut entries);
+    Ok(Some(write_object("tree", &tree)?))
+}
+
+fn print_status() -> Result<()> {
+    let mut tracked = HashMap::new();
+    if let Some(head) = refs::resolve_head()? {
+        for entry in read_tree_recursive(&CommitObject::read(&head)?.tree)? {
+            tracked.insert(entry.name.clone(), entry);
+        }
+    }
+
+    let mut changes = Vec::new();
+    let mut untracked = Vec::new();
+    let files = worktree::list_files(Path::new("."))?;
+    for path in &files {
+        let Some(entry) = tracked.get(path) else {
+            untracked.push(path);
+            continue;
+        };
+        let (mode, content) = worktree::read_file(Path::new(path))?;
+        if mode != entry.mode || hash_object("blob", &content) != hex::encode(entry.hash) {
+            changes.push(("modified", path));
+        }
+    }
+    for path in tracked.keys() {
+        if files.binary_search(path).is_err() {
+            changes.push(("deleted", path));
+        }
+    }
+    changes.sort_by_key(|&(_, path)| path);
+
+    if changes.is_empty() && untracked.is_empty() {
+        println!("nothing to commit, working tree clean");
+        return Ok(());
+    }
+    if !changes.is_empty() {
+        println!("Changes not staged for commit:");
+        for (status, path) in &changes {
+            println!("\t{:<12}{}", format!("{}:", status), path);
+        }
+        println!();
+    }
+    if !untracked.is_empty() {
+        println!("Untracked files:");
+        for path in &untracked {
+            println!("\t{}", path);
+        }
+        println!();
+    }
+
+    Ok(())
+}
+
+// builds trees from index entries, whose names are full `/`-separated paths
+fn write_index_tree(entries: Vec<(&str, &IndexEntry)>) -> Result<String> {
+    let mut tree_entries = Vec::new();
+    let mut subdirs: BTreeMap<&str, Vec<(&str, &IndexEntry)>> = BTreeMap::new();
+    for (path, entry) in entries {
+        match path.split_once('/') {
+            Some((dir, rest)) => subdirs.entry(dir).or_default().push((rest, entry)),
+            None => tree_entries.push(TreeEntry {
+                mode: format!("{:o}", entry.mode),
+                name: path.to_string(),
+                hash: entry.sha1,
+            }),
+        }
+    }
+
+    for (dir, children) in subdirs {
+        let mut hash = [0u8; 20];
+        hex::decode_to_slice(write_index_tree(children)?, &mut hash)?;
+        tree_entries.push(TreeEntry {
+            mode: String::from("40000"),
+            name: dir.to_string(),
+            hash,
+        });
+    }
+
+    let tree = serialize_tree_object(&mut tree_entries);
+    write_object("tree", &tree)
+}
+
+// the files of the tree a commit points to, by path
+fn commit_files(hash: &str) -> Result<HashMap<String, TreeEntry>> {
+    let tree = CommitObject::read(hash)?.tree;
+    Ok(read_tree_recursive(&tree)?
+        .into_iter()
+        .map(|entry| (entry.name.clone(), entry))
+        .collect())
+}
+
+fn restore_file(entry: &TreeEntry) -> Result<()> {
+    let (kind, content) = read_object(&hex::encode(entry.hash))?;
+    if kind != "blob" {
+        return Err(anyhow::anyhow!(
+            "cannot check out {} object at {}",
+            kind,
+            entry.name
+        ));
+    }
+    worktree::write_file(&entry.name, &entry.mode, &content)
+}
+
+fn checkout_branch(branch: &str) -> Result<()> {
+    let Some(target) = refs::resolve(&format!("refs/heads/{}", branch))? else {
+        return Err(anyhow::anyhow!(
+            "pathspec '{}' did not match any branch",
+            branch
+        ));
+    };
+    let current = match refs::resolve_head()? {
+        Some(head) => commit_files(&head)?,
+        None => HashMap::new(),
+    };
+
+    // staged files are compared against the index, anything else against HEAD
+    let tracked: Vec<(String, String)> = if Index::exists() {
+        let index = Index::read()?;
+        index
+            .entries
+            .iter()
+            .map(|e| (e.name.clone(), hex::encode(e.sha1)))
+            .collect()
+    } else {
+        current
+            .values()
+            .map(|e| (e.name.clone(), hex::encode(e.hash)))
+            .collect()
+    };
+    let mut dirty = Vec::new();
+    for (path, hash) in tracked {
+        let unchanged = worktree::read_file(Path::new(&path))
+            .is_ok_and(|(_, content)| hash_object("blob", &content) == hash);
+        if !unchanged {
+            dirty.push(path);
+        }
+    }
+    if !dirty.is_empty() {
+        dirty.sort();
+        return Err(anyhow::anyhow!(
+            "Your local changes to the following files would be overwritten by checkout:\n\t{}\nPlease commit your changes before you switch branches.",
+            dirty.join("\n\t")
+        ));
+    }
+
+    let files = commit_files(&target)?;
+    for path in current.keys() {
+        if !files.contains_key(path) {
+            worktree::remove_file(path)?;
+        }
+    }
+    let mut index = Index {
+        entries: Vec::new(),
+    };
+    for entry in files.values() {
+        restore_file(entry)?;
+        index.add(IndexEntry::from_file(&entry.name, entry.hash)?);
+    }
+    index.write()?;
+
+    fs::write(".git/HEAD", format!("ref: refs/heads/{}\n", branch))?;
+    println!("Switched to branch '{}'", branch);
+    Ok(())
+}
+
+const DIFF_CONTEXT: usize = 3;
+
+// prints a unified diff between two versions of a file, where a missing side
+// means the file was added or deleted
+fn print_diff(old: Option<(&str, &[u8])>, new: Option<(&str, &[u8])>, color: bool) {
+    let paint = |code: &str, text: &str| {
+        if color {
+            format!("\x1b[{}m{}\x1b[m", code, text)
+        } else {
+            text.to_string()
+        }
+    };
+    let old_label = old.map_or(String::from("/dev/null"), |(name, _)| format!("a/{}", name));
+    let new_label = new.map_or(String::from("/dev/null"), |(name, _)| format!("b/{}", name));
+    let old_content = old.map_or(&[][..], |(_, content)| content);
+    let new_content = new.map_or(&[][..], |(_, content)| content);
+
+    let old_name = old.or(new).map_or("", |(name, _)| name);
+    let new_name = new.or(old).map_or("", |(name, _)| name);
+    println!(
+        "{}",
+        paint("1", &format!("diff --git a/{} b/{}", old_name, new_name))
+    );
+    if old_content.contains(&0) || new_content.contains(&0) {
+        println!("Binary files {} and {} differ", old_label, new_label);
+        return;
+    }
+    println!("{}", paint("1", &format!("--- {}", old_label)));
+    println!("{}", paint("1", &format!("+++ {}", new_label)));
+
+    let old_text = String::from_utf8_lossy(old_content);
+    let new_text = String::from_utf8_lossy(new_content);
+    let old_lines = old_text.split_inclusive('\n').collect::<Vec<_>>();
+    let new_lines = new_text.split_inclusive('\n').collect::<Vec<_>>();
+    for hunk in diff::diff(&old_lines, &new_lines, DIFF_CONTEXT) {
+        println!("{}", paint("36", &hunk.header()));
+        for line in &hunk.lines {
+            let (prefix, text, code) = match line {
+                DiffLine::Context(text) => (' ', text, None),
+                DiffLine::Delete(text) => ('-', text, Some("31")),
+                DiffLine::Insert(text) => ('+', text, Some("32")),
+            };
+            let line = format!("{}{}", prefix, text.trim_end_matches('\n'));
+            match code {
+                Some(code) => println!("{}", paint(code, &line)),
+                None => println!("{}", line),
+            }
+            if !text.ends_with('\n') {
+                println!("\\ No newline at end of file");
+            }
+        }
+    }
+}
+
+// `Name <email> timestamp timezone`, read from the same variables real git uses
+fn signature(role: &str) -> Result<String> {
+    let name = env::var(format!("GIT_{}_NAME", role)).unwrap_or_else(|_| String::from("user"));
+    let email = env::var(format!("GIT_{}_EMAIL", role))
+        .unwrap_or_else(|_| String::from("user@example.com"));
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    Ok(format!("{} <{}> {} +0000", name, email, timestamp))
+}
 
 fn main() -> Result<()> {
     let args: Vec<_> = env::args().collect();
@@ -24,17 +320,8 @@ fn main() -> Result<()> {
                 ));
             }
 
-            let hash = &args[3];
-            let data = fs::read(format!(".git/objects/{}/{}", &hash[..2], &hash[2..]))?;
-            let mut decoder = ZlibDecoder::new(&*data);
-            let mut raw_content = String::new();
-            decoder.read_to_string(&mut raw_content)?;
-
-            if let Some((_, content)) = raw_content.split_once("\0") {
-                print!("{}", &content);
-            } else {
-                return Err(anyhow::anyhow!("Invalid blob object"));
-            }
+            let (_, content) = read_object(&args[3])?;
+            print!("{}", String::from_utf8_lossy(&content));
 
             Ok(())
         }
@@ -45,19 +332,244 @@ fn main() -> Result<()> {
                 ));
             }
 
-            let file_content = fs::read_to_string(&args[3])?;
-            let content = format!("blob {}\0{}", file_content.chars().count(), file_content);
-            let hash = hex::encode(Sha1::digest(&content.as_bytes()));
+            let file_content = fs::read(&args[3])?;
+            let hash = write_object("blob", &file_content)?;
+            println!("{}", hash);
+
+            Ok(())
+        }
+        "ls-tree" => {
+            let (recursive, hash) = match &args[2..] {
+                [hash] => (false, hash),
+                [flag, hash] if flag == "-r" => (true, hash),
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "Invalid arguments.\nusage: git ls-tree [-r] <tree-hash>"
+                    ))
+                }
+            };
+
+            print_tree(hash, "", recursive)
+        }
+        "write-tree" => {
+            // the working directory is only used before anything has been staged
+            let hash = if Index::exists() {
+                let index = Index::read()?;
+                write_index_tree(index.entries.iter().map(|e| (e.name.as_str(), e)).collect())?
+            } else {
+                match write_tree(Path::new("."))? {
+                    Some(hash) => hash,
+                    None => write_object("tree", &[])?,
+                }
+            };
+            println!("{}", hash);
+
+            Ok(())
+        }
+        "commit-tree" => {
+            let usage = || {
+                anyhow::anyhow!(
+                    "Invalid arguments.\nusage: git commit-tree <tree> [-p <parent>]... -m <message>"
+                )
+            };
+            let tree = args.get(2).ok_or_else(usage)?;
+            let mut parents = Vec::new();
+            let mut message = None;
+            let mut options = args[3..].iter();
+            while let Some(option) = options.next() {
+                match option.as_str() {
+                    "-p" => parents.push(options.next().ok_or_else(usage)?),
+                    "-m" => message = Some(options.next().ok_or_else(usage)?),
+                    _ => return Err(usage()),
+                }
+            }
+            let message = message.ok_or_else(usage)?;
+            read_tree(tree)?;
+
+            let mut content = format!("tree {}\n", tree);
+            for parent in parents {
+                content.push_str(&format!("parent {}\n", parent));
+            }
+            content.push_str(&format!("author {}\n", signature("AUTHOR")?));
+            content.push_str(&format!("committer {}\n", signature("COMMITTER")?));
+            content.push_str(&format!("\n{}\n", message.trim_end_matches('\n')));
+
+            let hash = write_object("commit", content.as_bytes())?;
             println!("{}", hash);
 
-            let mut zlib_content = Vec::new();
-            let mut encoder = ZlibEncoder::new(content.as_bytes(), Compression::fast());
-            encoder.read_to_end(&mut zlib_content)?;
+            Ok(())
+        }
+        "log" => {
+            let mut next = match args.get(2) {
+                Some(hash) => Some(hash.clone()),
+                None => refs::resolve_head()?,
+            };
+
+            // only the first parent is followed, like `git log --first-parent`
+            while let Some(hash) = next {
+                let commit = CommitObject::read(&hash)?;
+                let (author, date) = split_signature(&commit.author)?;
+                println!("commit {}", hash);
+                println!("Author: {}", author);
+                println!("Date:   {}", date);
+                println!();
+                for line in commit.message.trim_end().lines() {
+                    println!("    {}", line);
+                }
+                next = commit.parents.into_iter().next();
+                if next.is_some() {
+                    println!();
+                }
+            }
+
+            Ok(())
+        }
+        "status" => print_status(),
+        "branch" => match &args[2..] {
+            [] => {
+                let current = refs::current_branch()?;
+                for branch in refs::list_branches()? {
+                    let marker = if current.as_ref() == Some(&branch) {
+                        '*'
+                    } else {
+                        ' '
+                    };
+                    println!("{} {}", marker, branch);
+                }
+
+                Ok(())
+            }
+            [flag, name] if flag == "-d" => {
+                let hash = refs::delete_branch(name)?;
+                println!("Deleted branch {} (was {}).", name, &hash[..7]);
+
+                Ok(())
+            }
+            [name] => {
+                let Some(head) = refs::resolve_head()? else {
+                    return Err(anyhow::anyhow!("not a valid object name: 'HEAD'"));
+                };
+                refs::create_branch(name, &head)
+            }
+            _ => Err(anyhow::anyhow!(
+                "Invalid arguments.\nusage: git branch [-d] [<name>]"
+            )),
+        },
+        "checkout" => match &args[2..] {
+            [separator, paths @ ..] if separator == "--" && !paths.is_empty() => {
+                let Some(head) = refs::resolve_head()? else {
+                    return Err(anyhow::anyhow!("HEAD does not point to a commit"));
+                };
+                let files = commit_files(&head)?;
+                for path in paths {
+                    let entry = files.get(path.trim_start_matches("./")).ok_or_else(|| {
+                        anyhow::anyhow!("pathspec '{}' did not match any file known to git", path)
+                    })?;
+                    restore_file(entry)?;
+                }
+
+                Ok(())
+            }
+            [branch] => checkout_branch(branch),
+            _ => Err(anyhow::anyhow!(
+                "Invalid arguments.\nusage: git checkout <branch>\n       git checkout -- <file>..."
+            )),
+        },
+        "diff" => {
+            let color = io::stdout().is_terminal();
+            match &args[2..] {
+                [] => {
+                    let Some(head) = refs::resolve_head()? else {
+                        return Ok(());
+                    };
+                    let mut files = commit_files(&head)?.into_values().collect::<Vec<_>>();
+                    files.sort_by(|a, b| a.name.cmp(&b.name));
+                    for entry in files {
+                        let (_, old) = read_object(&hex::encode(entry.hash))?;
+                        let path = Path::new(&entry.name);
+                        if fs::symlink_metadata(path).is_err() {
+                            print_diff(Some((&entry.name, &old)), None, color);
+                            continue;
+                        }
+                        let (_, new) = worktree::read_file(path)?;
+                        if old != new {
+                            print_diff(Some((&entry.name, &old)), Some((&entry.name, &new)), color);
+                        }
+                    }
+
+                    Ok(())
+                }
+                [old_hash, new_hash] => {
+                    let (old_kind, old) = read_object(old_hash)?;
+                    let (new_kind, new) = read_object(new_hash)?;
+                    if old_kind != "blob" || new_kind != "blob" {
+                        return Err(anyhow::anyhow!(
+                            "git diff <hash> <hash> compares blob objects"
+                        ));
+                    }
+                    if old != new {
+                        print_diff(Some((old_hash, &old)), Some((new_hash, &new)), color);
+                    }
+
+                    Ok(())
+                }
+                _ => Err(anyhow::anyhow!(
+                    "Invalid arguments.\nusage: git diff [<blob> <blob>]"
+                )),
+            }
+        }
+        "ls-files" => {
+            let index = Index::read()?;
+            for entry in &index.entries {
+                println!("{}", entry.name);
+            }
+
+            Ok(())
+        }
+        "add" => {
+            if args.len() < 3 {
+                return Err(anyhow::anyhow!(
+                    "Invalid arguments.\nusage: git add <file>..."
+                ));
+            }
+
+            let mut index = Index::read()?;
+            for arg in &args[2..] {
+                let path = match arg.trim_start_matches("./").trim_end_matches('/') {
+                    "." => "",
+                    path => path,
+                };
+                let metadata = match fs::symlink_metadata(arg) {
+                    Ok(metadata) => metadata,
+                    // staging a deleted path removes it from the index
+                    Err(_) if index.entries.iter().any(|e| e.name == path) => {
+                        index.remove(path);
+                        continue;
+                    }
+                    Err(e) => return Err(anyhow::anyhow!("failed to add {}: {}", arg, e)),
+                };
 
-            fs::create_dir(format!(".git/objects/{}", &hash[..2]))?;
-            let mut blob_file =
-                fs::File::create(format!(".git/objects/{}/{}", &hash[..2], &hash[2..]))?;
-            blob_file.write_all(&zlib_content)?;
+                let files = if metadata.is_dir() {
+                    index.remove(path);
+                    worktree::list_files(Path::new(arg))?
+                        .into_iter()
+                        .map(|file| match path {
+                            "" => file,
+                            _ => format!("{}/{}", path, file),
+                        })
+                        .collect()
+                } else {
+                    vec![path.to_string()]
+                };
+                for file in files {
+                    let (_, content) = worktree::read_file(Path::new(&file))?;
+                    let hash = write_object("blob", &content)?;
+                    let mut sha1 = [0u8; 20];
+                    hex::decode_to_slice(&hash, &mut sha1)?;
+                    index.add(IndexEntry::from_file(&file, sha1)?);
+                }
+            }
+            index.write()?;
 
             Ok(())
         }
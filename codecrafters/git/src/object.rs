@@ -0,0 +1,151 @@
+use anyhow::{anyhow, Result};
+use flate2::read::{ZlibDecoder, ZlibEncoder};
+use flate2::Compression;
+use sha1::{Digest, Sha1};
+use std::{fs, io::Read, io::Write};
+
+pub struct TreeEntry {
+    pub mode: String,
+    pub name: String,
+    pub hash: [u8; 20],
+}
+
+impl TreeEntry {
+    pub fn kind(&self) -> &'static str {
+        match self.mode.as_str() {
+            "40000" | "040000" => "tree",
+            "160000" => "commit",
+            _ => "blob",
+        }
+    }
+}
+
+impl TreeEntry {
+    // git orders tree entries as if directory names had a trailing '/'
+    fn sort_key(&self) -> Vec<u8> {
+        let mut key = self.name.as_bytes().to_vec();
+        if self.kind() == "tree" {
+            key.push(b'/');
+        }
+        key
+    }
+}
+
+fn encode_object(kind: &str, body: &[u8]) -> (String, Vec<u8>) {
+    let mut content = format!("{} {}\0", kind, body.len()).into_bytes();
+    content.extend_from_slice(body);
+    (hex::encode(Sha1::digest(&content)), content)
+}
+
+// like `git hash-object` without `-w`: the hash the object would be stored under
+pub fn hash_object(kind: &str, body: &[u8]) -> String {
+    encode_object(kind, body).0
+}
+
+pub fn write_object(kind: &str, body: &[u8]) -> Result<String> {
+    let (hash, content) = encode_object(kind, body);
+
+    let mut zlib_content = Vec::new();
+    let mut encoder = ZlibEncoder::new(&*content, Compression::fast());
+    encoder.read_to_end(&mut zlib_content)?;
+
+    fs::create_dir_all(format!(".git/objects/{}", &hash[..2]))?;
+    let mut object_file = fs::File::create(format!(".git/objects/{}/{}", &hash[..2], &hash[2..]))?;
+    object_file.write_all(&zlib_content)?;
+
+    Ok(hash)
+}
+
+// returns the object type and the content following the `<type> <size>\0` header
+pub fn read_object(hash: &str) -> Result<(String, Vec<u8>)> {
+    if hash.len() != 40 {
+        return Err(anyhow!("Invalid object name: {}", hash));
+    }
+    let data = fs::read(format!(".git/objects/{}/{}", &hash[..2], &hash[2..]))
+        .map_err(|e| anyhow!("Failed to read object {}: {}", hash, e))?;
+    let mut decoder = ZlibDecoder::new(&*data);
+    let mut raw_content = Vec::new();
+    decoder.read_to_end(&mut raw_content)?;
+
+    let header_end = raw_content
+        .iter()
+        .position(|&b| b == 0)
+        .ok_or_else(|| anyhow!("Invalid object {}: missing header", hash))?;
+    let header = std::str::from_utf8(&raw_content[..header_end])?;
+    let (kind, _) = header
+        .split_once(' ')
+        .ok_or_else(|| anyhow!("Invalid object {}: malformed header", hash))?;
+
+    Ok((kind.to_string(), raw_content[header_end + 1..].to_vec()))
+}
+
+pub fn read_tree(hash: &str) -> Result<Vec<TreeEntry>> {
+    let (kind, body) = read_object(hash)?;
+    if kind != "tree" {
+        return Err(anyhow!("{} is a {}, not a tree object", hash, kind));
+    }
+    parse_tree_object(&body)
+}
+
+// all non-tree entries below `hash`, named by their full `/`-separated path
+pub fn read_tree_recursive(hash: &str) -> Result<Vec<TreeEntry>> {
+    let mut entries = Vec::new();
+    collect_tree_entries(hash, "", &mut entries)?;
+    Ok(entries)
+}
+
+fn collect_tree_entries(hash: &str, prefix: &str, entries: &mut Vec<TreeEntry>) -> Result<()> {
+    for entry in read_tree(hash)? {
+        let name = format!("{}{}", prefix, entry.name);
+        if entry.kind() == "tree" {
+            collect_tree_entries(&hex::encode(entry.hash), &format!("{}/", name), entries)?;
+        } else {
+            entries.push(TreeEntry { name, ..entry });
+        }
+    }
+
+    Ok(())
+}
+
+// each entry is `<mode> <name>\0` followed by the 20-byte raw SHA-1
+pub fn parse_tree_object(data: &[u8]) -> Result<Vec<TreeEntry>> {
+    let mut entries = Vec::new();
+    let mut rest = data;
+    while !rest.is_empty() {
+        let space = rest
+            .iter()
+            .position(|&b| b == b' ')
+            .ok_or_else(|| anyhow!("Invalid tree object: missing mode"))?;
+        let nul = rest
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(|| anyhow!("Invalid tree object: unterminated entry name"))?;
+        let hash = rest
+            .get(nul + 1..nul + 21)
+            .ok_or_else(|| anyhow!("Invalid tree object: truncated entry"))?
+            .try_into()?;
+
+        entries.push(TreeEntry {
+            mode: String::from_utf8(rest[..space].to_vec())?,
+            name: String::from_utf8(rest[space + 1..nul].to_vec())?,
+            hash,
+        });
+        rest = &rest[nul + 21..];
+    }
+
+    Ok(entries)
+}
+
+pub fn serialize_tree_object(entries: &mut [TreeEntry]) -> Vec<u8> {
+    entries.sort_by_key(TreeEntry::sort_key);
+
+    let mut data = Vec::new();
+    for entry in entries.iter() {
+        data.extend_from_slice(entry.mode.trim_start_matches('0').as_bytes());
+        data.push(b' ');
+        data.extend_from_slice(entry.name.as_bytes());
+        data.push(0);
+        data.extend_from_slice(&entry.hash);
+    }
+    data
+}
@@ -0,0 +1,135 @@
+use anyhow::{anyhow, Result};
+use std::{fs, path::Path};
+
+const HEAD_PATH: &str = ".git/HEAD";
+const HEADS_DIR: &str = ".git/refs/heads";
+
+// follows symbolic refs (`ref: refs/heads/master`) until it reaches a commit hash;
+// returns None for a branch that has no commits yet
+pub fn resolve(name: &str) -> Result<Option<String>> {
+    let path = Path::new(".git").join(name);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(_) => return Ok(read_packed_ref(name)),
+    };
+
+    match content.trim().strip_prefix("ref: ") {
+        Some(target) => resolve(target),
+        None => Ok(Some(content.trim().to_string())),
+    }
+}
+
+pub fn resolve_head() -> Result<Option<String>> {
+    if !Path::new(HEAD_PATH).exists() {
+        return Err(anyhow!("not a git repository: {} not found", HEAD_PATH));
+    }
+    resolve("HEAD")
+}
+
+// the branch HEAD points to, or None when HEAD is detached
+pub fn current_branch() -> Result<Option<String>> {
+    let head = fs::read_to_string(HEAD_PATH)?;
+    Ok(head
+        .trim()
+        .strip_prefix("ref: refs/heads/")
+        .map(str::to_string))
+}
+
+pub fn list_branches() -> Result<Vec<String>> {
+    let mut branches = Vec::new();
+    if Path::new(HEADS_DIR).exists() {
+        collect_branches(Path::new(HEADS_DIR), "", &mut branches)?;
+    }
+    if let Ok(packed_refs) = fs::read_to_string(".git/packed-refs") {
+        for line in packed_refs.lines() {
+            if let Some((_, name)) = line.split_once(" refs/heads/") {
+                if !branches.iter().any(|branch| branch == name) {
+                    branches.push(name.to_string());
+                }
+            }
+        }
+    }
+    branches.sort();
+    Ok(branches)
+}
+
+fn collect_branches(dir: &Path, prefix: &str, branches: &mut Vec<String>) -> Result<()> {
+    for dir_entry in fs::read_dir(dir)? {
+        let dir_entry = dir_entry?;
+        let name = format!("{}{}", prefix, dir_entry.file_name().to_string_lossy());
+        if dir_entry.file_type()?.is_dir() {
+            collect_branches(&dir_entry.path(), &format!("{}/", name), branches)?;
+        } else {
+            branches.push(name);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn create_branch(name: &str, hash: &str) -> Result<()> {
+    validate_branch_name(name)?;
+    if resolve(&format!("refs/heads/{}", name))?.is_some() {
+        return Err(anyhow!("a branch named '{}' already exists", name));
+    }
+
+    let path = Path::new(HEADS_DIR).join(name);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, format!("{}\n", hash))?;
+    Ok(())
+}
+
+pub fn delete_branch(name: &str) -> Result<String> {
+    let Some(hash) = resolve(&format!("refs/heads/{}", name))? else {
+        return Err(anyhow!("branch '{}' not found", name));
+    };
+    if current_branch()?.as_deref() == Some(name) {
+        return Err(anyhow!("cannot delete branch '{}' checked out", name));
+    }
+
+    let path = Path::new(HEADS_DIR).join(name);
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    if let Ok(packed_refs) = fs::read_to_string(".git/packed-refs") {
+        let packed_ref = format!(" refs/heads/{}", name);
+        let remaining = packed_refs
+            .lines()
+            .filter(|line| !line.ends_with(&packed_ref))
+            .map(|line| format!("{}\n", line))
+            .collect::<String>();
+        fs::write(".git/packed-refs", remaining)?;
+    }
+    Ok(hash)
+}
+
+// a subset of the rules in `git check-ref-format`
+fn validate_branch_name(name: &str) -> Result<()> {
+    let invalid = name.is_empty()
+        || name.starts_with(['-', '/', '.'])
+        || name.ends_with(['/', '.'])
+        || name.ends_with(".lock")
+        || name.contains("..")
+        || name.contains("@{")
+        || name.contains("//")
+        || name.contains("/.")
+        || name == "@"
+        || name
+            .chars()
+            .any(|c| c.is_ascii_control() || " ~^:?*[\\".contains(c));
+    if invalid {
+        return Err(anyhow!("'{}' is not a valid branch name", name));
+    }
+    Ok(())
+}
+
+// refs moved out of `.git/refs` by `git gc` are stored as `<hash> <name>` lines
+fn read_packed_ref(name: &str) -> Option<String> {
+    let packed_refs = fs::read_to_string(".git/packed-refs").ok()?;
+    packed_refs.lines().find_map(|line| {
+        let (hash, ref_name) = line.split_once(' ')?;
+        (ref_name == name).then(|| hash.to_string())
+    })
+}
@@ -0,0 +1,93 @@
+use anyhow::{anyhow, Result};
+use std::{
+    ffi::OsStr,
+    fs,
+    os::unix::{self, ffi::OsStrExt, fs::PermissionsExt},
+    path::Path,
+};
+
+// the tree mode and blob content of a working-tree file; symlinks are stored as
+// their target path, like git does
+pub fn read_file(path: &Path) -> Result<(&'static str, Vec<u8>)> {
+    let metadata = fs::symlink_metadata(path)?;
+    if metadata.file_type().is_symlink() {
+        let target = fs::read_link(path)?;
+        return Ok(("120000", target.into_os_string().into_encoded_bytes()));
+    }
+
+    let mode = if metadata.permissions().mode() & 0o111 != 0 {
+        "100755"
+    } else {
+        "100644"
+    };
+    Ok((mode, fs::read(path)?))
+}
+
+// the reverse of `read_file`: creates missing parent directories and replaces
+// whatever is currently at `path`
+pub fn write_file(path: &str, mode: &str, content: &[u8]) -> Result<()> {
+    let path = Path::new(path);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::symlink_metadata(path).is_ok() {
+        fs::remove_file(path)?;
+    }
+
+    if mode == "120000" {
+        unix::fs::symlink(OsStr::from_bytes(content), path)?;
+        return Ok(());
+    }
+    fs::write(path, content)?;
+    let permissions = if mode == "100755" { 0o755 } else { 0o644 };
+    fs::set_permissions(path, fs::Permissions::from_mode(permissions))?;
+    Ok(())
+}
+
+// removes `path` along with any parent directories it leaves empty
+pub fn remove_file(path: &str) -> Result<()> {
+    let path = Path::new(path);
+    if fs::symlink_metadata(path).is_ok() {
+        fs::remove_file(path)?;
+    }
+    for dir in path.ancestors().skip(1) {
+        if dir.as_os_str().is_empty() || fs::remove_dir(dir).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+// every file below `dir` except those in `.git`, as sorted `/`-separated relative paths
+pub fn list_files(dir: &Path) -> Result<Vec<String>> {
+    let mut files = Vec::new();
+    collect_files(dir, "", &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+fn collect_files(dir: &Path, prefix: &str, files: &mut Vec<String>) -> Result<()> {
+    for dir_entry in fs::read_dir(dir)? {
+        let dir_entry = dir_entry?;
+        let name = file_name(&dir_entry)?;
+        if name == ".git" {
+            continue;
+        }
+
+        let path = format!("{}{}", prefix, name);
+        if dir_entry.file_type()?.is_dir() {
+            collect_files(&dir_entry.path(), &format!("{}/", path), files)?;
+        } else {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn file_name(dir_entry: &fs::DirEntry) -> Result<String> {
+    dir_entry
+        .file_name()
+        .into_string()
+        .map_err(|name| anyhow!("Invalid file name: {}", name.to_string_lossy()))
+}
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use crate::{
+    bulk_array_reply, bulk_string, encode_array, wrong_number_of_arguments, InMemoryDb, RedisValue,
+    WRONGTYPE,
+};
+
+pub fn hset(db: &mut InMemoryDb, args: &[&str]) -> String {
+    if args.len() < 3 || args.len().is_multiple_of(2) {
+        return wrong_number_of_arguments("hset");
+    }
+
+    if db.get_mut(args[0]).is_none() {
+        db.insert(
+            args[0].to_string(),
+            (RedisValue::Hash(HashMap::new()), None),
+        );
+    }
+    let Some(RedisValue::Hash(hash)) = db.get_mut(args[0]) else {
+        return String::from(WRONGTYPE);
+    };
+    let added = args[1..]
+        .chunks(2)
+        .filter(|pair| {
+            hash.insert(pair[0].to_string(), pair[1].as_bytes().to_vec())
+                .is_none()
+        })
+        .count();
+    format!(":{}\r\n", added)
+}
+
+pub fn hget(db: &mut InMemoryDb, args: &[&str]) -> String {
+    if args.len() != 2 {
+        return wrong_number_of_arguments("hget");
+    }
+
+    match db.get_mut(args[0]) {
+        Some(RedisValue::Hash(hash)) => match hash.get(args[1]) {
+            Some(value) => bulk_string(value),
+            None => String::from("$-1\r\n"),
+        },
+        Some(_) => String::from(WRONGTYPE),
+        None => String::from("$-1\r\n"),
+    }
+}
+
+pub fn hmget(db: &mut InMemoryDb, args: &[&str]) -> String {
+    if args.len() < 2 {
+        return wrong_number_of_arguments("hmget");
+    }
+
+    let empty = HashMap::new();
+    let hash = match db.get_mut(args[0]) {
+        Some(RedisValue::Hash(hash)) => hash,
+        Some(_) => return String::from(WRONGTYPE),
+        None => &empty,
+    };
+
+    let values = args[1..]
+        .iter()
+        .map(|field| hash.get(*field).map(Vec::as_slice))
+        .collect::<Vec<_>>();
+    bulk_array_reply(&values)
+}
+
+pub fn hgetall(db: &mut InMemoryDb, args: &[&str]) -> String {
+    if args.len() != 1 {
+        return wrong_number_of_arguments("hgetall");
+    }
+
+    match db.get_mut(args[0]) {
+        Some(RedisValue::Hash(hash)) => encode_array(
+            hash.iter()
+                .flat_map(|(field, value)| [field.as_bytes(), value.as_slice()])
+                .collect(),
+        ),
+        Some(_) => String::from(WRONGTYPE),
+        None => String::from("*0\r\n"),
+    }
+}
+
+pub fn hdel(db: &mut InMemoryDb, args: &[&str]) -> String {
+    if args.len() < 2 {
+        return wrong_number_of_arguments("hdel");
+    }
+
+    let hash = match db.get_mut(args[0]) {
+        Some(RedisValue::Hash(hash)) => hash,
+        Some(_) => return String::from(WRONGTYPE),
+        None => return String::from(":0\r\n"),
+    };
+    let deleted = args[1..]
+        .iter()
+        .filter(|field| hash.remove(**field).is_some())
+        .count();
+    if hash.is_empty() {
+        db.remove(args[0]);
+    }
+    format!(":{}\r\n", deleted)
+}
@@ -0,0 +1,103 @@
+use std::sync::atomic::Ordering;
+
+use crate::{bulk_string, RedisValue, Server};
+
+const SECTIONS: &[&str] = &["server", "clients", "memory", "stats", "keyspace"];
+
+pub fn info(server: &Server, args: &[&str]) -> String {
+    let sections = match args.first().map(|section| section.to_lowercase()) {
+        None => SECTIONS.to_vec(),
+        Some(section) if ["all", "everything", "default"].contains(&section.as_str()) => {
+            SECTIONS.to_vec()
+        }
+        // an unknown section gives an empty reply, like Redis
+        Some(section) => SECTIONS.iter().copied().filter(|s| *s == section).collect(),
+    };
+
+    let stats = server
+        .dbs
+        .iter()
+        .map(|db| db.lock().unwrap().stats())
+        .collect::<Vec<_>>();
+    let mut lines = Vec::new();
+    for section in sections {
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        match section {
+            "server" => {
+                lines.push(String::from("# Server"));
+                lines.push(String::from("redis_version:7.0.0"));
+                lines.push(format!(
+                    "uptime_in_seconds:{}",
+                    server.started_at.elapsed().as_secs()
+                ));
+                lines.push(String::from("tcp_port:6379"));
+            }
+            "clients" => {
+                lines.push(String::from("# Clients"));
+                lines.push(format!(
+                    "connected_clients:{}",
+                    server.connected_clients.load(Ordering::Relaxed)
+                ));
+            }
+            "memory" => {
+                let used_memory = server
+                    .dbs
+                    .iter()
+                    .map(|db| {
+                        let db = db.lock().unwrap();
+                        db.map
+                            .iter()
+                            .map(|(key, (value, _))| key.len() + approximate_size(value))
+                            .sum::<usize>()
+                    })
+                    .sum::<usize>();
+                lines.push(String::from("# Memory"));
+                lines.push(format!("used_memory:{}", used_memory));
+            }
+            "stats" => {
+                lines.push(String::from("# Stats"));
+                lines.push(format!(
+                    "total_commands_processed:{}",
+                    stats.iter().map(|s| s.commands_processed).sum::<u64>()
+                ));
+                lines.push(format!(
+                    "expired_keys:{}",
+                    stats.iter().map(|s| s.expired_keys).sum::<u64>()
+                ));
+            }
+            "keyspace" => {
+                lines.push(String::from("# Keyspace"));
+                for (index, stats) in stats.iter().enumerate() {
+                    if stats.total_keys > 0 {
+                        lines.push(format!(
+                            "db{}:keys={},expires={},avg_ttl={}",
+                            index, stats.total_keys, stats.expires, stats.avg_ttl
+                        ));
+                    }
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    let mut send_str = lines.join("\r\n");
+    if !send_str.is_empty() {
+        send_str.push_str("\r\n");
+    }
+    bulk_string(send_str.as_bytes())
+}
+
+// the bytes taken up by the contents of a value, ignoring the overhead of the collections
+fn approximate_size(value: &RedisValue) -> usize {
+    match value {
+        RedisValue::String(value) => value.len(),
+        RedisValue::List(list) => list.iter().map(Vec::len).sum(),
+        RedisValue::Hash(hash) => hash.iter().map(|(f, v)| f.len() + v.len()).sum(),
+        RedisValue::Set(set) => set.iter().map(Vec::len).sum(),
+        RedisValue::SortedSet(sorted_set) => {
+            sorted_set.iter().map(|(_, member)| member.len() + 8).sum()
+        }
+    }
+}
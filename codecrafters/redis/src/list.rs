@@ -0,0 +1,95 @@
+use std::collections::VecDeque;
+
+use crate::{
+    bulk_string, encode_array, wrong_number_of_arguments, InMemoryDb, RedisValue, NOT_AN_INTEGER,
+    WRONGTYPE,
+};
+
+pub fn lpush(db: &mut InMemoryDb, args: &[&str]) -> String {
+    if args.len() < 2 {
+        return wrong_number_of_arguments("lpush");
+    }
+
+    if db.get_mut(args[0]).is_none() {
+        db.insert(
+            args[0].to_string(),
+            (RedisValue::List(VecDeque::new()), None),
+        );
+    }
+    let Some(RedisValue::List(list)) = db.get_mut(args[0]) else {
+        return String::from(WRONGTYPE);
+    };
+    for value in &args[1..] {
+        list.push_front(value.as_bytes().to_vec());
+    }
+    format!(":{}\r\n", list.len())
+}
+
+pub fn lpop(db: &mut InMemoryDb, args: &[&str]) -> String {
+    if args.is_empty() || args.len() > 2 {
+        return wrong_number_of_arguments("lpop");
+    }
+    let count = match args.get(1).map(|count| count.parse::<usize>()) {
+        Some(Ok(count)) => Some(count),
+        Some(Err(_)) => {
+            return String::from("-ERR value is out of range, must be positive\r\n");
+        }
+        None => None,
+    };
+
+    let list = match db.get_mut(args[0]) {
+        Some(RedisValue::List(list)) => list,
+        Some(_) => return String::from(WRONGTYPE),
+        None if count.is_some() => return String::from("*-1\r\n"),
+        None => return String::from("$-1\r\n"),
+    };
+    let popped = list
+        .drain(..count.unwrap_or(1).min(list.len()))
+        .collect::<Vec<_>>();
+    // an empty list is the same as a missing key
+    if list.is_empty() {
+        db.remove(args[0]);
+    }
+
+    match count {
+        Some(_) => encode_array(popped.iter().map(Vec::as_slice).collect()),
+        None => bulk_string(&popped[0]),
+    }
+}
+
+pub fn lrange(db: &mut InMemoryDb, args: &[&str]) -> String {
+    if args.len() != 3 {
+        return wrong_number_of_arguments("lrange");
+    }
+    let (Ok(start), Ok(stop)) = (args[1].parse::<i64>(), args[2].parse::<i64>()) else {
+        return String::from(NOT_AN_INTEGER);
+    };
+
+    let list = match db.get_mut(args[0]) {
+        Some(RedisValue::List(list)) => list,
+        Some(_) => return String::from(WRONGTYPE),
+        None => return String::from("*0\r\n"),
+    };
+
+    // negative indices count from the end of the list, like Python slices
+    let len = list.len() as i64;
+    let start = if start < 0 {
+        (len + start).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 {
+        len + stop
+    } else {
+        stop.min(len - 1)
+    };
+    if start > stop {
+        return String::from("*0\r\n");
+    }
+
+    encode_array(
+        list.range(start as usize..=stop as usize)
+            .map(Vec::as_slice)
+            .collect(),
+    )
+}
@@ -1,32 +1,215 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::str;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::{Duration, SystemTime};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::{TcpListener, TcpStream};
 
+use persistence::AofWriter;
+use pubsub::{Channels, Subscriber};
+
+mod hash;
+mod info;
+mod list;
+mod persistence;
+mod pubsub;
+mod scripting;
+mod set;
+mod sorted_set;
+
 type ExpireDate = Option<SystemTime>;
 
+const DB_COUNT: usize = 16;
+const MAX_MULTIBULK_LENGTH: usize = 1024 * 1024;
+const DEFAULT_SAVE_INTERVAL: Duration = Duration::from_secs(60);
+const WRONGTYPE: &str = "-WRONGTYPE Operation against a key holding the wrong kind of value\r\n";
+const NOT_AN_INTEGER: &str = "-ERR value is not an integer or out of range\r\n";
+const NOT_A_FLOAT: &str = "-ERR value is not a valid float\r\n";
+
+enum RedisValue {
+    String(Vec<u8>),
+    List(VecDeque<Vec<u8>>),
+    Hash(HashMap<String, Vec<u8>>),
+    Set(HashSet<Vec<u8>>),
+    SortedSet(sorted_set::SortedSet),
+}
+
+// state shared by every connection
+struct Server {
+    dbs: Vec<Mutex<InMemoryDb>>,
+    channels: Channels,
+    aof: Mutex<AofWriter>,
+    started_at: Instant,
+    connected_clients: AtomicUsize,
+}
+
+// commands queued with MULTI, run by EXEC
+#[derive(Default)]
+struct Transaction {
+    commands: Vec<Vec<String>>,
+    // set when a command couldn't be queued, EXEC then discards the whole transaction
+    aborted: bool,
+}
+
+impl Transaction {
+    fn queue(&mut self, lines: &[&str]) -> String {
+        match lines[0].to_uppercase().as_str() {
+            // these have to wait for other connections, which can't run commands while
+            // EXEC holds the AOF lock
+            "PUBLISH" | "SUBSCRIBE" | "UNSUBSCRIBE" => {
+                self.aborted = true;
+                format!(
+                    "-ERR Command '{}' is not allowed in a transaction\r\n",
+                    lines[0].to_lowercase()
+                )
+            }
+            _ => {
+                self.commands
+                    .push(lines.iter().map(|line| line.to_string()).collect());
+                String::from("+QUEUED\r\n")
+            }
+        }
+    }
+}
+
 struct InMemoryDb {
-    mutex: Mutex<HashMap<String, (Vec<u8>, ExpireDate)>>,
+    map: HashMap<String, (RedisValue, ExpireDate)>,
+    expired_keys: AtomicU64,
+    commands_processed: AtomicU64,
+}
+
+struct DbStats {
+    total_keys: usize,
+    // the number of keys which have an expiry set
+    expires: usize,
+    // in milliseconds, over the keys which have an expiry set
+    avg_ttl: u64,
+    expired_keys: u64,
+    commands_processed: u64,
 }
 
 impl InMemoryDb {
     fn new() -> Self {
         Self {
-            mutex: Mutex::new(HashMap::new()),
+            map: HashMap::new(),
+            expired_keys: AtomicU64::new(0),
+            commands_processed: AtomicU64::new(0),
         }
     }
 
-    fn get(&self, key: String) -> Option<(Vec<u8>, ExpireDate)> {
-        let lock = self.mutex.lock().unwrap();
-        Some(lock.get(&key)?.clone())
+    fn stats(&self) -> DbStats {
+        let now = SystemTime::now();
+        let ttls = self
+            .map
+            .values()
+            .filter_map(|(_, expire_date)| expire_date.as_ref())
+            .filter_map(|expire_date| expire_date.duration_since(now).ok())
+            .map(|ttl| ttl.as_millis() as u64)
+            .collect::<Vec<_>>();
+
+        DbStats {
+            total_keys: self.map.len(),
+            expires: ttls.len(),
+            avg_ttl: ttls.iter().sum::<u64>() / ttls.len().max(1) as u64,
+            expired_keys: self.expired_keys.load(Ordering::Relaxed),
+            commands_processed: self.commands_processed.load(Ordering::Relaxed),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<(&RedisValue, ExpireDate)> {
+        match self.map.get(key)? {
+            (_, Some(expire_date)) if *expire_date < SystemTime::now() => None,
+            (value, expire_date) => Some((value, *expire_date)),
+        }
+    }
+
+    // expired keys are removed lazily, when they are looked up
+    fn get_mut(&mut self, key: &str) -> Option<&mut RedisValue> {
+        if let Some((_, Some(expire_date))) = self.map.get(key) {
+            if *expire_date < SystemTime::now() {
+                self.map.remove(key);
+                self.expired_keys.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.map.get_mut(key).map(|(value, _)| value)
+    }
+
+    fn insert(&mut self, key: String, value: (RedisValue, ExpireDate)) {
+        self.map.insert(key, value);
     }
 
-    fn insert(&self, key: String, value: (Vec<u8>, ExpireDate)) {
-        let mut lock = self.mutex.lock().unwrap();
-        lock.insert(key, value);
+    fn remove(&mut self, key: &str) {
+        self.map.remove(key);
+    }
+
+    // returns false if the key doesn't exist
+    fn set_expire(&mut self, key: &str, expire_date: ExpireDate) -> bool {
+        if self.get(key).is_none() {
+            return false;
+        }
+        if let Some((_, stored)) = self.map.get_mut(key) {
+            *stored = expire_date;
+        }
+        true
+    }
+
+    fn incr_by(&mut self, key: &str, increment: i64) -> Result<i64, &'static str> {
+        let current = match self.get_mut(key) {
+            Some(RedisValue::String(value)) => str::from_utf8(value)
+                .ok()
+                .and_then(|value| value.parse::<i64>().ok())
+                .ok_or(NOT_AN_INTEGER)?,
+            Some(_) => return Err(WRONGTYPE),
+            None => 0,
+        };
+        let result = current
+            .checked_add(increment)
+            .ok_or("-ERR increment or decrement would overflow\r\n")?;
+        self.set_string_keeping_expiry(key, result.to_string().into_bytes());
+        Ok(result)
+    }
+
+    fn incr_by_float(&mut self, key: &str, increment: f64) -> Result<String, &'static str> {
+        let current = match self.get_mut(key) {
+            Some(RedisValue::String(value)) => str::from_utf8(value)
+                .ok()
+                .and_then(|value| value.parse::<f64>().ok())
+                .ok_or(NOT_A_FLOAT)?,
+            Some(_) => return Err(WRONGTYPE),
+            None => 0.0,
+        };
+        let result = current + increment;
+        if !result.is_finite() {
+            return Err("-ERR increment would produce NaN or Infinity\r\n");
+        }
+        // f64's Display already omits trailing zeros, e.g. 3.0 is written as "3"
+        let result = result.to_string();
+        self.set_string_keeping_expiry(key, result.clone().into_bytes());
+        Ok(result)
+    }
+
+    fn append(&mut self, key: &str, value: &[u8]) -> Result<usize, &'static str> {
+        match self.get_mut(key) {
+            Some(RedisValue::String(stored)) => {
+                stored.extend_from_slice(value);
+                Ok(stored.len())
+            }
+            Some(_) => Err(WRONGTYPE),
+            None => {
+                self.insert(key.to_string(), (RedisValue::String(value.to_vec()), None));
+                Ok(value.len())
+            }
+        }
+    }
+
+    fn set_string_keeping_expiry(&mut self, key: &str, value: Vec<u8>) {
+        match self.get_mut(key) {
+            Some(stored) => *stored = RedisValue::String(value),
+            None => self.insert(key.to_string(), (RedisValue::String(value), None)),
+        }
     }
 }
 
@@ -35,96 +218,592 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Logs from your program will appear here!");
 
     let listener = TcpListener::bind("127.0.0.1:6379").await?;
-    let db: Arc<InMemoryDb> = Arc::new(InMemoryDb::new());
+    let (dbs, aof) = persistence::restore(persistence::AOF_PATH, persistence::RDB_PATH)?;
+    let server = Arc::new(Server {
+        dbs,
+        channels: Channels::default(),
+        aof: Mutex::new(aof),
+        started_at: Instant::now(),
+        connected_clients: AtomicUsize::new(0),
+    });
+
+    let save_interval = save_interval()?;
+    let snapshot_server = server.clone();
+    thread::spawn(move || loop {
+        thread::sleep(save_interval);
+        if let Err(e) = persistence::save(&snapshot_server.dbs, persistence::RDB_PATH) {
+            println!("Failed to save {}: {}", persistence::RDB_PATH, e);
+        }
+    });
 
     loop {
         let (socket, _) = listener.accept().await?;
-        let db = db.clone();
+        let server = server.clone();
         tokio::spawn(async move {
-            handle_connection(socket, db).await.unwrap();
+            server.connected_clients.fetch_add(1, Ordering::Relaxed);
+            let result = handle_connection(socket, &server).await;
+            server.connected_clients.fetch_sub(1, Ordering::Relaxed);
+            result.unwrap();
         });
     }
 }
 
+// how often the databases are saved to disk, set with `--save-interval <seconds>`
+fn save_interval() -> Result<Duration, Box<dyn std::error::Error>> {
+    let args = std::env::args().collect::<Vec<_>>();
+    match args.iter().position(|arg| arg == "--save-interval") {
+        Some(i) => {
+            let seconds = args
+                .get(i + 1)
+                .ok_or("--save-interval requires a value")?
+                .parse::<u64>()?;
+            Ok(Duration::from_secs(seconds))
+        }
+        None => Ok(DEFAULT_SAVE_INTERVAL),
+    }
+}
+
 async fn handle_connection(
     mut stream: TcpStream,
-    db: Arc<InMemoryDb>,
+    server: &Server,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    let Server { dbs, channels, .. } = server;
     let mut buffer = [0; 1024];
+    let mut current_db = 0;
+    // Some while a transaction is being queued with MULTI
+    let mut transaction: Option<Transaction> = None;
+    let (mut subscriber, mut messages) = Subscriber::new();
+    // bytes received but not yet parsed into a complete command
+    let mut pending = Vec::new();
 
     loop {
-        let n_bytes = stream.read(&mut buffer).await?;
-        let receive_str = str::from_utf8(&buffer[..n_bytes])?;
-        let lines = receive_str
-            .split("\r\n")
-            .into_iter()
-            .filter_map(|item| match item.chars().next()? {
-                '$' | '*' => None,
-                _ => Some(item),
-            })
-            .collect::<Vec<_>>();
-
-        if lines.len() == 0 {
+        let n_bytes = tokio::select! {
+            n_bytes = stream.read(&mut buffer) => n_bytes?,
+            Some(message) = messages.recv() => {
+                stream.write_all(&message).await?;
+                continue;
+            }
+        };
+        if n_bytes == 0 {
             break;
         }
+        pending.extend_from_slice(&buffer[..n_bytes]);
 
-        match lines[0].to_uppercase().as_str() {
-            "PING" => {
-                if lines.len() == 1 {
-                    let send_str = "+PONG\r\n";
-                    stream.write(send_str.as_bytes()).await?;
+        // a read may end in the middle of a multi-byte character
+        let receive_str = match str::from_utf8(&pending) {
+            Ok(receive_str) => receive_str,
+            Err(e) => str::from_utf8(&pending[..e.valid_up_to()])?,
+        };
+        let mut send_str = String::new();
+        let mut consumed = 0;
+        loop {
+            let (lines, n_bytes) = match parse_command(&receive_str[consumed..]) {
+                Ok(Some(command)) => command,
+                Ok(None) => break,
+                Err(e) => {
+                    // like Redis, give up on a connection that doesn't speak RESP
+                    send_str.push_str(&format!("-ERR Protocol error: {}\r\n", e));
+                    stream.write_all(send_str.as_bytes()).await?;
+                    return Ok(());
                 }
+            };
+            consumed += n_bytes;
+            if lines.is_empty() {
+                continue;
             }
-            "ECHO" => {
-                if lines.len() == 2 {
-                    let send_str = format!("${}\r\n{}\r\n", lines[1].len(), lines[1]);
-                    stream.write(send_str.as_bytes()).await?;
+
+            // commands that work on the state of the connection rather than on the db
+            let command = lines[0].to_uppercase();
+            if let Some(transaction) = transaction.as_mut() {
+                if !matches!(command.as_str(), "MULTI" | "EXEC" | "DISCARD") {
+                    send_str.push_str(&transaction.queue(&lines));
+                    continue;
                 }
             }
-            "SET" => match lines.len() {
-                3 => {
-                    db.insert(lines[1].to_string(), (lines[2].as_bytes().to_vec(), None));
-                    let send_str = "+OK\r\n";
-                    stream.write(send_str.as_bytes()).await?;
-                }
-                5 => {
-                    let expire_date = SystemTime::now()
-                        .checked_add(Duration::from_millis(lines[4].parse::<u64>().unwrap()));
-                    db.insert(
-                        lines[1].to_string(),
-                        (lines[2].as_bytes().to_vec(), expire_date),
-                    );
-                    let send_str = "+OK\r\n";
-                    stream.write(send_str.as_bytes()).await?;
+            let reply = match command.as_str() {
+                "SUBSCRIBE" => subscriber.subscribe(channels, &lines[1..]),
+                "UNSUBSCRIBE" => subscriber.unsubscribe(channels, &lines[1..]),
+                "PING" if subscriber.is_subscribed() => encode_array(vec![b"pong", b""]),
+                _ if subscriber.is_subscribed() => format!(
+                    "-ERR Can't execute '{}': only SUBSCRIBE / UNSUBSCRIBE / PING are allowed in this context\r\n",
+                    lines[0].to_lowercase()
+                ),
+                "MULTI" if transaction.is_some() => {
+                    String::from("-ERR MULTI calls can not be nested\r\n")
                 }
-                _ => {
-                    println!("Error: wrong number of arguments for 'set' command");
-                }
-            },
-            "GET" => {
-                if lines.len() != 2 {
-                    println!("Error: wrong number of arguments for 'get' command");
-                    continue;
+                "MULTI" => {
+                    transaction = Some(Transaction::default());
+                    String::from("+OK\r\n")
                 }
+                "EXEC" => match transaction.take() {
+                    Some(transaction) => exec(&transaction, server, &mut current_db),
+                    None => String::from("-ERR EXEC without MULTI\r\n"),
+                },
+                "DISCARD" => match transaction.take() {
+                    Some(_) => String::from("+OK\r\n"),
+                    None => String::from("-ERR DISCARD without MULTI\r\n"),
+                },
+                "SELECT" => select(&lines, &mut current_db),
+                "PUBLISH" => pubsub::publish(channels, &lines[1..]).await,
+                "INFO" => info::info(server, &lines[1..]),
+                "BGREWRITEAOF" => bgrewriteaof(&mut server.aof.lock().unwrap(), dbs),
+                _ => execute_and_log(
+                    &dbs[current_db],
+                    current_db,
+                    &mut server.aof.lock().unwrap(),
+                    &lines,
+                ),
+            };
+            send_str.push_str(&reply);
+        }
+        pending.drain(..consumed);
+
+        stream.write_all(send_str.as_bytes()).await?;
+    }
+
+    subscriber.unsubscribe(channels, &[]);
+    Ok(())
+}
+
+// Every command locks the AOF first, so that a rewrite sees either both the command's
+// effect and its log entry or neither.
+fn execute_and_log(
+    db: &Mutex<InMemoryDb>,
+    db_index: usize,
+    aof: &mut AofWriter,
+    lines: &[&str],
+) -> String {
+    let mut db = db.lock().unwrap();
+    let reply = execute(&mut db, lines);
+    if let Err(e) = aof.append(db_index, lines, &reply, &db) {
+        println!("Failed to write {}: {}", persistence::AOF_PATH, e);
+    }
+    reply
+}
+
+// Holding the AOF lock for the whole transaction keeps every other connection from running
+// commands in between. An error reply doesn't abort the transaction, it's just returned in
+// place of that command's result.
+fn exec(transaction: &Transaction, server: &Server, current_db: &mut usize) -> String {
+    if transaction.aborted {
+        return String::from("-EXECABORT Transaction discarded because of previous errors.\r\n");
+    }
+
+    let mut aof = server.aof.lock().unwrap();
+    let mut send_str = format!("*{}\r\n", transaction.commands.len());
+    for command in &transaction.commands {
+        let lines = command.iter().map(String::as_str).collect::<Vec<_>>();
+        let reply = match lines[0].to_uppercase().as_str() {
+            "SELECT" => select(&lines, current_db),
+            "INFO" => info::info(server, &lines[1..]),
+            "BGREWRITEAOF" => bgrewriteaof(&mut aof, &server.dbs),
+            _ => execute_and_log(&server.dbs[*current_db], *current_db, &mut aof, &lines),
+        };
+        send_str.push_str(&reply);
+    }
+    send_str
+}
+
+fn bgrewriteaof(aof: &mut AofWriter, dbs: &[Mutex<InMemoryDb>]) -> String {
+    match aof.rewrite(dbs) {
+        // the rewrite is actually done in the foreground, but clients expect this reply
+        Ok(()) => String::from("+Background append only file rewriting started\r\n"),
+        Err(e) => format!("-ERR Failed to rewrite the AOF: {}\r\n", e),
+    }
+}
+
+// Parse one command from the start of `input`, returning its arguments and the number of
+// bytes it takes up, or None if the command has not been received completely yet.
+fn parse_command(input: &str) -> Result<Option<(Vec<&str>, usize)>, &'static str> {
+    let Some((header, mut rest)) = input.split_once("\r\n") else {
+        return Ok(None);
+    };
+    let Some(count) = header.strip_prefix('*') else {
+        // inline commands, e.g. "PING\r\n" typed into telnet
+        return Ok(Some((
+            header.split_whitespace().collect(),
+            header.len() + 2,
+        )));
+    };
 
-                let mut send_str = String::from("$-1\r\n");
-                if let Some((value, expire_date)) = db.get(lines[1].to_string()) {
-                    let value = str::from_utf8(&value)?;
-                    send_str = format!("${}\r\n{}\r\n", value.len(), value);
-                    if let Some(expire_date) = expire_date {
-                        if expire_date < SystemTime::now() {
-                            send_str = String::from("$-1\r\n");
-                        }
+    // like Redis, a huge count is rejected before anything is allocated for it
+    let count = count
+        .parse::<usize>()
+        .ok()
+        .filter(|count| *count <= MAX_MULTIBULK_LENGTH)
+        .ok_or("invalid multibulk length")?;
+    let mut lines = Vec::with_capacity(count);
+    for _ in 0..count {
+        let Some((len, body)) = rest.split_once("\r\n") else {
+            return Ok(None);
+        };
+        let len = len
+            .strip_prefix('$')
+            .and_then(|len| len.parse::<usize>().ok())
+            .ok_or("invalid bulk length")?;
+        if body.len() < len + 2 {
+            return Ok(None);
+        }
+        let (Some(line), Some("\r\n")) = (body.get(..len), body.get(len..len + 2)) else {
+            return Err("invalid bulk length");
+        };
+        lines.push(line);
+        rest = &body[len + 2..];
+    }
+    Ok(Some((lines, input.len() - rest.len())))
+}
+
+fn select(lines: &[&str], current_db: &mut usize) -> String {
+    if lines.len() != 2 {
+        return wrong_number_of_arguments("select");
+    }
+
+    match lines[1].parse::<usize>() {
+        Ok(index) if index < DB_COUNT => {
+            *current_db = index;
+            String::from("+OK\r\n")
+        }
+        Ok(_) => String::from("-ERR DB index is out of range\r\n"),
+        Err(_) => String::from(NOT_AN_INTEGER),
+    }
+}
+
+fn execute(db: &mut InMemoryDb, lines: &[&str]) -> String {
+    db.commands_processed.fetch_add(1, Ordering::Relaxed);
+    let command = lines[0].to_uppercase();
+    match command.as_str() {
+        "PING" => String::from("+PONG\r\n"),
+        "ECHO" => {
+            if lines.len() != 2 {
+                return wrong_number_of_arguments("echo");
+            }
+
+            bulk_string(lines[1].as_bytes())
+        }
+        "SET" => match lines.len() {
+            3 => {
+                db.insert(
+                    lines[1].to_string(),
+                    (RedisValue::String(lines[2].as_bytes().to_vec()), None),
+                );
+                String::from("+OK\r\n")
+            }
+            5 => {
+                let Ok(millis) = lines[4].parse::<u64>() else {
+                    return String::from(NOT_AN_INTEGER);
+                };
+                let expire_date = SystemTime::now().checked_add(Duration::from_millis(millis));
+                db.insert(
+                    lines[1].to_string(),
+                    (
+                        RedisValue::String(lines[2].as_bytes().to_vec()),
+                        expire_date,
+                    ),
+                );
+                String::from("+OK\r\n")
+            }
+            _ => wrong_number_of_arguments("set"),
+        },
+        "GET" => {
+            if lines.len() != 2 {
+                return wrong_number_of_arguments("get");
+            }
+
+            match db.get_mut(lines[1]) {
+                Some(RedisValue::String(value)) => bulk_string(value),
+                Some(_) => String::from(WRONGTYPE),
+                None => String::from("$-1\r\n"),
+            }
+        }
+        "INCR" | "DECR" => {
+            if lines.len() != 2 {
+                return wrong_number_of_arguments(&lines[0].to_lowercase());
+            }
+
+            let increment = if command == "INCR" { 1 } else { -1 };
+            match db.incr_by(lines[1], increment) {
+                Ok(value) => format!(":{}\r\n", value),
+                Err(e) => String::from(e),
+            }
+        }
+        "INCRBY" | "DECRBY" => {
+            if lines.len() != 3 {
+                return wrong_number_of_arguments(&lines[0].to_lowercase());
+            }
+
+            let increment = match lines[2].parse::<i64>() {
+                Ok(increment) if command == "INCRBY" => increment,
+                Ok(increment) => match increment.checked_neg() {
+                    Some(increment) => increment,
+                    None => return String::from("-ERR decrement would overflow\r\n"),
+                },
+                Err(_) => return String::from(NOT_AN_INTEGER),
+            };
+            match db.incr_by(lines[1], increment) {
+                Ok(value) => format!(":{}\r\n", value),
+                Err(e) => String::from(e),
+            }
+        }
+        "INCRBYFLOAT" => {
+            if lines.len() != 3 {
+                return wrong_number_of_arguments("incrbyfloat");
+            }
+
+            let Ok(increment) = lines[2].parse::<f64>() else {
+                return String::from(NOT_A_FLOAT);
+            };
+            match db.incr_by_float(lines[1], increment) {
+                Ok(value) => bulk_string(value.as_bytes()),
+                Err(e) => String::from(e),
+            }
+        }
+        "DEL" => {
+            if lines.len() < 2 {
+                return wrong_number_of_arguments("del");
+            }
+
+            let deleted = lines[1..]
+                .iter()
+                .filter(|key| {
+                    let exists = db.get(key).is_some();
+                    db.remove(key);
+                    exists
+                })
+                .count();
+            format!(":{}\r\n", deleted)
+        }
+        "MSET" => {
+            if lines.len() < 3 || lines.len().is_multiple_of(2) {
+                return wrong_number_of_arguments("mset");
+            }
+
+            for pair in lines[1..].chunks(2) {
+                db.insert(
+                    pair[0].to_string(),
+                    (RedisValue::String(pair[1].as_bytes().to_vec()), None),
+                );
+            }
+            String::from("+OK\r\n")
+        }
+        "MGET" => {
+            if lines.len() < 2 {
+                return wrong_number_of_arguments("mget");
+            }
+
+            let values = lines[1..]
+                .iter()
+                .map(|key| match db.get(key) {
+                    Some((RedisValue::String(value), _)) => Some(value.as_slice()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            bulk_array_reply(&values)
+        }
+        "TTL" | "PTTL" => {
+            if lines.len() != 2 {
+                return wrong_number_of_arguments(&lines[0].to_lowercase());
+            }
+
+            let ttl = match db.get(lines[1]) {
+                Some((_, Some(expire_date))) => {
+                    let remaining = expire_date
+                        .duration_since(SystemTime::now())
+                        .unwrap_or_default()
+                        .as_millis() as i64;
+                    // TTL is rounded to the nearest second, like Redis does
+                    if command == "TTL" {
+                        (remaining + 500) / 1000
+                    } else {
+                        remaining
                     }
                 }
-                stream.write(send_str.as_bytes()).await?;
+                Some((_, None)) => -1,
+                None => -2,
+            };
+            format!(":{}\r\n", ttl)
+        }
+        "EXPIRE" | "PEXPIRE" | "PEXPIREAT" => {
+            if lines.len() != 3 {
+                return wrong_number_of_arguments(&lines[0].to_lowercase());
             }
-            _ => {
-                println!("Unsupported command: {:?}", lines[0]);
-                break;
+
+            let millis = match lines[2].parse::<i64>() {
+                Ok(seconds) if command == "EXPIRE" => seconds.checked_mul(1000),
+                Ok(millis) => Some(millis),
+                Err(_) => None,
+            };
+            let Some(millis) = millis else {
+                return String::from(NOT_AN_INTEGER);
+            };
+            // PEXPIREAT takes a unix time, the others a timeout relative to now
+            let now = SystemTime::now();
+            let base = if command == "PEXPIREAT" {
+                UNIX_EPOCH
+            } else {
+                now
+            };
+            let expire_date = match u64::try_from(millis) {
+                Ok(millis) => base.checked_add(Duration::from_millis(millis)),
+                Err(_) => Some(UNIX_EPOCH),
+            };
+
+            // a timeout in the past deletes the key right away
+            if expire_date.is_some_and(|expire_date| expire_date <= now) {
+                let exists = db.get(lines[1]).is_some();
+                db.remove(lines[1]);
+                return format!(":{}\r\n", exists as u8);
+            }
+            format!(":{}\r\n", db.set_expire(lines[1], expire_date) as u8)
+        }
+        "PERSIST" => {
+            if lines.len() != 2 {
+                return wrong_number_of_arguments("persist");
+            }
+
+            match db.get(lines[1]) {
+                Some((_, Some(_))) => format!(":{}\r\n", db.set_expire(lines[1], None) as u8),
+                _ => String::from(":0\r\n"),
             }
         }
+        "APPEND" => {
+            if lines.len() != 3 {
+                return wrong_number_of_arguments("append");
+            }
+
+            match db.append(lines[1], lines[2].as_bytes()) {
+                Ok(len) => format!(":{}\r\n", len),
+                Err(e) => String::from(e),
+            }
+        }
+        "LPUSH" => list::lpush(db, &lines[1..]),
+        "LPOP" => list::lpop(db, &lines[1..]),
+        "LRANGE" => list::lrange(db, &lines[1..]),
+        "HSET" => hash::hset(db, &lines[1..]),
+        "HGET" => hash::hget(db, &lines[1..]),
+        "HMGET" => hash::hmget(db, &lines[1..]),
+        "HGETALL" => hash::hgetall(db, &lines[1..]),
+        "HDEL" => hash::hdel(db, &lines[1..]),
+        "SADD" => set::sadd(db, &lines[1..]),
+        "SREM" => set::srem(db, &lines[1..]),
+        "SMEMBERS" => set::smembers(db, &lines[1..]),
+        "SCARD" => set::scard(db, &lines[1..]),
+        "SISMEMBER" => set::sismember(db, &lines[1..]),
+        "ZADD" => sorted_set::zadd(db, &lines[1..]),
+        "ZRANGE" => sorted_set::zrange(db, &lines[1..]),
+        "ZRANK" => sorted_set::zrank(db, &lines[1..]),
+        "ZSCORE" => sorted_set::zscore(db, &lines[1..]),
+        "EVAL" => scripting::eval(db, &lines[1..]),
+        _ => {
+            println!("Unsupported command: {:?}", lines[0]);
+            format!("-ERR unknown command '{}'\r\n", lines[0])
+        }
     }
+}
 
-    Ok(())
+fn wrong_number_of_arguments(command: &str) -> String {
+    format!(
+        "-ERR wrong number of arguments for '{}' command\r\n",
+        command
+    )
+}
+
+fn bulk_string(value: &[u8]) -> String {
+    let value = String::from_utf8_lossy(value);
+    format!("${}\r\n{}\r\n", value.len(), value)
+}
+
+fn encode_array(items: Vec<&[u8]>) -> String {
+    let mut send_str = format!("*{}\r\n", items.len());
+    for item in items {
+        send_str.push_str(&bulk_string(item));
+    }
+    send_str
+}
+
+// like encode_array, but missing values are encoded as nil
+fn bulk_array_reply(values: &[Option<&[u8]>]) -> String {
+    let mut send_str = format!("*{}\r\n", values.len());
+    for value in values {
+        match value {
+            Some(value) => send_str.push_str(&bulk_string(value)),
+            None => send_str.push_str("$-1\r\n"),
+        }
+    }
+    send_str
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use persistence::tests::temp_path;
+
+    fn server(aof_path: &str) -> Server {
+        Server {
+            dbs: (0..DB_COUNT)
+                .map(|_| Mutex::new(InMemoryDb::new()))
+                .collect(),
+            channels: Channels::default(),
+            aof: Mutex::new(AofWriter::open(aof_path).unwrap()),
+            started_at: Instant::now(),
+            connected_clients: AtomicUsize::new(0),
+        }
+    }
+
+    #[test]
+    fn reject_oversized_multibulk_length() {
+        assert_eq!(
+            parse_command("*1000000000000\r\n"),
+            Err("invalid multibulk length")
+        );
+        assert_eq!(
+            parse_command("*1048577\r\n"),
+            Err("invalid multibulk length")
+        );
+        // the largest count allowed only waits for the rest of the command
+        assert_eq!(parse_command("*1048576\r\n"), Ok(None));
+    }
+
+    #[test]
+    fn exec_server_commands() {
+        let aof_path = temp_path("exec.aof");
+        let server = server(&aof_path);
+        let mut transaction = Transaction::default();
+        for lines in [
+            vec!["SET", "key", "value"],
+            vec!["INFO", "keyspace"],
+            vec!["BGREWRITEAOF"],
+        ] {
+            assert_eq!(transaction.queue(&lines), "+QUEUED\r\n");
+        }
+
+        let reply = exec(&transaction, &server, &mut 0);
+        assert!(reply.starts_with("*3\r\n+OK\r\n"));
+        assert!(reply.contains("db0:keys=1,expires=0,avg_ttl=0"));
+        assert!(reply.ends_with("+Background append only file rewriting started\r\n"));
+        assert!(std::fs::read_to_string(&aof_path)
+            .unwrap()
+            .contains("value"));
+        std::fs::remove_file(aof_path).unwrap();
+    }
+
+    #[test]
+    fn publish_aborts_transaction() {
+        let aof_path = temp_path("publish.aof");
+        let server = server(&aof_path);
+        let mut transaction = Transaction::default();
+        assert_eq!(transaction.queue(&["SET", "key", "value"]), "+QUEUED\r\n");
+        assert_eq!(
+            transaction.queue(&["PUBLISH", "channel", "message"]),
+            "-ERR Command 'publish' is not allowed in a transaction\r\n"
+        );
+
+        let reply = exec(&transaction, &server, &mut 0);
+        assert_eq!(
+            reply,
+            "-EXECABORT Transaction discarded because of previous errors.\r\n"
+        );
+        // none of the queued commands were run
+        assert!(server.dbs[0].lock().unwrap().get("key").is_none());
+        std::fs::remove_file(aof_path).unwrap();
+    }
 }
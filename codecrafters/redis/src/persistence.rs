@@ -0,0 +1,487 @@
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, ErrorKind, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{encode_array, execute, parse_command, select, InMemoryDb, RedisValue, DB_COUNT};
+
+pub const RDB_PATH: &str = "dump.rdb";
+pub const AOF_PATH: &str = "appendonly.aof";
+
+// commands which modify the db and so have to be logged to the AOF
+const WRITE_COMMANDS: &[&str] = &[
+    "SET",
+    "DEL",
+    "MSET",
+    "APPEND",
+    "INCR",
+    "DECR",
+    "INCRBY",
+    "DECRBY",
+    "INCRBYFLOAT",
+    "LPUSH",
+    "LPOP",
+    "HSET",
+    "HDEL",
+    "SADD",
+    "SREM",
+    "ZADD",
+    "EXPIRE",
+    "PEXPIRE",
+    "PEXPIREAT",
+    "PERSIST",
+    "EVAL",
+];
+
+// The file is a simplified take on the real RDB format:
+//
+//   "REDIS0011"
+//   for each non-empty database:
+//     db index (u8), record count (u32)
+//     for each record:
+//       key length (u32), key, value type (u8), value length (u32), value,
+//       expiry in unix time milliseconds (u64, 0 when the key doesn't expire)
+//   0xFF
+//   CRC64 of everything above (u64, little endian)
+//
+// A list value is its items encoded one after another as length (u32) + bytes.
+// All other integers are big endian.
+const MAGIC: &[u8] = b"REDIS0011";
+const EOF: u8 = 0xff;
+const TYPE_STRING: u8 = 0;
+const TYPE_LIST: u8 = 1;
+
+impl InMemoryDb {
+    // serializes the records of this database, the caller is expected to hold its lock
+    pub fn snapshot(&self) -> Vec<u8> {
+        let now = SystemTime::now();
+        let mut records = Vec::new();
+        let mut count: u32 = 0;
+        for (key, (value, expire_date)) in &self.map {
+            let (value_type, value) = match value {
+                RedisValue::String(value) => (TYPE_STRING, value.clone()),
+                RedisValue::List(list) => {
+                    let mut value = Vec::new();
+                    for item in list {
+                        write_bytes(&mut value, item);
+                    }
+                    (TYPE_LIST, value)
+                }
+                // only strings and lists can be persisted for now
+                _ => continue,
+            };
+            let expire_millis = match expire_date {
+                Some(expire_date) if *expire_date < now => continue,
+                Some(expire_date) => expire_date
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |d| d.as_millis() as u64),
+                None => 0,
+            };
+
+            write_bytes(&mut records, key.as_bytes());
+            records.push(value_type);
+            write_bytes(&mut records, &value);
+            records.extend_from_slice(&expire_millis.to_be_bytes());
+            count += 1;
+        }
+
+        let mut snapshot = count.to_be_bytes().to_vec();
+        snapshot.extend_from_slice(&records);
+        snapshot
+    }
+}
+
+// Write all databases to `path`. The file is written to a temporary path first and
+// renamed into place, so a crash while saving never leaves a half written dump behind.
+pub fn save(dbs: &[Mutex<InMemoryDb>], path: &str) -> io::Result<()> {
+    let mut data = MAGIC.to_vec();
+    for (index, db) in dbs.iter().enumerate() {
+        let db = db.lock().unwrap();
+        if db.map.is_empty() {
+            continue;
+        }
+        data.push(index as u8);
+        data.extend_from_slice(&db.snapshot());
+    }
+    data.push(EOF);
+    let checksum = crc64(&data);
+    data.extend_from_slice(&checksum.to_le_bytes());
+
+    let temp_path = format!("{}.tmp", path);
+    fs::write(&temp_path, data)?;
+    fs::rename(temp_path, path)
+}
+
+// Read the databases back from `path`, skipping keys which expired while the server was down.
+// Returns false if there is no dump to load.
+pub fn load(dbs: &mut [InMemoryDb], path: &str) -> io::Result<bool> {
+    if !Path::new(path).exists() {
+        return Ok(false);
+    }
+    let data = fs::read(path)?;
+
+    let invalid = |message: &str| io::Error::new(ErrorKind::InvalidData, message.to_string());
+    if data.len() < MAGIC.len() + 9 || !data.starts_with(MAGIC) {
+        return Err(invalid("not a dump file"));
+    }
+    let (body, checksum) = data.split_at(data.len() - 8);
+    if crc64(body).to_le_bytes() != checksum {
+        return Err(invalid("checksum mismatch"));
+    }
+
+    let now = SystemTime::now();
+    let mut reader = Reader {
+        data: &body[MAGIC.len()..],
+    };
+    loop {
+        let index = reader.read_u8()?;
+        if index == EOF {
+            break;
+        }
+        let db = dbs
+            .get_mut(index as usize)
+            .ok_or_else(|| invalid("database index out of range"))?;
+
+        for _ in 0..reader.read_u32()? {
+            let key = String::from_utf8(reader.read_bytes()?.to_vec())
+                .map_err(|_| invalid("key is not valid utf-8"))?;
+            let value_type = reader.read_u8()?;
+            let value = reader.read_bytes()?;
+            let value = match value_type {
+                TYPE_STRING => RedisValue::String(value.to_vec()),
+                TYPE_LIST => {
+                    let mut items = Reader { data: value };
+                    let mut list = VecDeque::new();
+                    while !items.data.is_empty() {
+                        list.push_back(items.read_bytes()?.to_vec());
+                    }
+                    RedisValue::List(list)
+                }
+                _ => return Err(invalid("unknown value type")),
+            };
+            let expire_date = match reader.read_u64()? {
+                0 => None,
+                millis => Some(UNIX_EPOCH + Duration::from_millis(millis)),
+            };
+
+            if expire_date.is_some_and(|expire_date| expire_date < now) {
+                continue;
+            }
+            db.insert(key, (value, expire_date));
+        }
+    }
+    Ok(true)
+}
+
+// Rebuild the databases at startup and open the AOF for the commands that follow.
+// The AOF is more up to date than the last snapshot, so it is preferred. Keys loaded from a
+// snapshot are written to a fresh AOF right away, otherwise they would be missing from it
+// on the next restart.
+pub fn restore(aof_path: &str, rdb_path: &str) -> io::Result<(Vec<Mutex<InMemoryDb>>, AofWriter)> {
+    let mut dbs = (0..DB_COUNT).map(|_| InMemoryDb::new()).collect::<Vec<_>>();
+    let from_snapshot = if replay_aof(&mut dbs, aof_path)? {
+        println!("Replayed {}", aof_path);
+        false
+    } else if load(&mut dbs, rdb_path)? {
+        println!("Loaded {}", rdb_path);
+        true
+    } else {
+        false
+    };
+
+    let dbs = dbs.into_iter().map(Mutex::new).collect::<Vec<_>>();
+    let mut aof = AofWriter::open(aof_path)?;
+    if from_snapshot {
+        aof.rewrite(&dbs)?;
+    }
+    Ok((dbs, aof))
+}
+
+// Logs every write command as RESP, so that the db can be rebuilt by replaying them.
+pub struct AofWriter {
+    file: File,
+    path: String,
+    // the database the commands in the file currently apply to
+    selected_db: Option<usize>,
+}
+
+impl AofWriter {
+    pub fn open(path: &str) -> io::Result<Self> {
+        Ok(Self {
+            file: OpenOptions::new().create(true).append(true).open(path)?,
+            path: path.to_string(),
+            selected_db: None,
+        })
+    }
+
+    // `db` is the database the command was just executed against, still locked by the caller
+    pub fn append(
+        &mut self,
+        db_index: usize,
+        lines: &[&str],
+        reply: &str,
+        db: &InMemoryDb,
+    ) -> io::Result<()> {
+        let command = lines[0].to_uppercase();
+        if !WRITE_COMMANDS.contains(&command.as_str()) {
+            return Ok(());
+        }
+        // Other commands check their arguments before changing anything, so an error means
+        // nothing has to be logged. A script may fail after some of its writes were applied,
+        // though, and replaying it runs those writes again before failing the same way.
+        if reply.starts_with('-') && command != "EVAL" {
+            return Ok(());
+        }
+
+        let mut data = String::new();
+        if self.selected_db != Some(db_index) {
+            data.push_str(&encode_command(&["SELECT", &db_index.to_string()]));
+            self.selected_db = Some(db_index);
+        }
+        // relative timeouts are logged as absolute ones, otherwise replaying
+        // the file would extend them by the time the server was down
+        match command.as_str() {
+            "SET" if lines.len() == 5 => {
+                data.push_str(&encode_command(&lines[..3]));
+                data.push_str(&expire_command(lines[1], db));
+            }
+            "EXPIRE" | "PEXPIRE" => data.push_str(&expire_command(lines[1], db)),
+            _ => data.push_str(&encode_command(lines)),
+        }
+
+        self.file.write_all(data.as_bytes())?;
+        self.file.flush()
+    }
+
+    // Replace the file with the fewest commands needed to rebuild the current state.
+    // The caller holds the writer's lock, so no command can be logged in the meantime.
+    pub fn rewrite(&mut self, dbs: &[Mutex<InMemoryDb>]) -> io::Result<()> {
+        let mut data = String::new();
+        for (index, db) in dbs.iter().enumerate() {
+            let db = db.lock().unwrap();
+            if db.map.is_empty() {
+                continue;
+            }
+
+            data.push_str(&encode_command(&["SELECT", &index.to_string()]));
+            for key in db.map.keys() {
+                let Some((value, expire_date)) = db.get(key) else {
+                    continue;
+                };
+                let mut command = Vec::new();
+                match value {
+                    RedisValue::String(value) => {
+                        command.extend([b"SET".to_vec(), key.as_bytes().to_vec(), value.clone()]);
+                    }
+                    RedisValue::List(list) => {
+                        // LPUSH prepends, so the items are pushed from the tail
+                        command.extend([b"LPUSH".to_vec(), key.as_bytes().to_vec()]);
+                        command.extend(list.iter().rev().cloned());
+                    }
+                    RedisValue::Hash(hash) => {
+                        command.extend([b"HSET".to_vec(), key.as_bytes().to_vec()]);
+                        for (field, value) in hash {
+                            command.extend([field.as_bytes().to_vec(), value.clone()]);
+                        }
+                    }
+                    RedisValue::Set(set) => {
+                        command.extend([b"SADD".to_vec(), key.as_bytes().to_vec()]);
+                        command.extend(set.iter().cloned());
+                    }
+                    RedisValue::SortedSet(sorted_set) => {
+                        command.extend([b"ZADD".to_vec(), key.as_bytes().to_vec()]);
+                        for (score, member) in sorted_set.iter() {
+                            command.extend([score.to_string().into_bytes(), member.to_vec()]);
+                        }
+                    }
+                }
+                data.push_str(&encode_array(command.iter().map(Vec::as_slice).collect()));
+                if expire_date.is_some() {
+                    data.push_str(&expire_command(key, &db));
+                }
+            }
+        }
+
+        let temp_path = format!("{}.tmp", self.path);
+        fs::write(&temp_path, data)?;
+        fs::rename(&temp_path, &self.path)?;
+        // the old file has been replaced, so it has to be opened again
+        *self = AofWriter::open(&self.path)?;
+        Ok(())
+    }
+}
+
+// Rebuild the databases by executing every command in the AOF at `path`.
+// Returns false if there is no file to replay.
+pub fn replay_aof(dbs: &mut [InMemoryDb], path: &str) -> io::Result<bool> {
+    if !Path::new(path).exists() {
+        return Ok(false);
+    }
+    let data = fs::read_to_string(path)?;
+
+    let mut current_db = 0;
+    let mut consumed = 0;
+    loop {
+        let (lines, n_bytes) = match parse_command(&data[consumed..]) {
+            Ok(Some(command)) => command,
+            // a command cut off by a crash while it was being written is dropped
+            Ok(None) => break,
+            Err(e) => return Err(io::Error::new(ErrorKind::InvalidData, e)),
+        };
+        consumed += n_bytes;
+        if lines.is_empty() {
+            continue;
+        }
+
+        if lines[0].eq_ignore_ascii_case("SELECT") {
+            select(&lines, &mut current_db);
+        } else {
+            execute(&mut dbs[current_db], &lines);
+        }
+    }
+    Ok(true)
+}
+
+fn encode_command(lines: &[&str]) -> String {
+    encode_array(lines.iter().map(|line| line.as_bytes()).collect())
+}
+
+// the command which gives `key` the same expiry it has in `db` now
+fn expire_command(key: &str, db: &InMemoryDb) -> String {
+    match db.get(key) {
+        Some((_, Some(expire_date))) => {
+            let millis = expire_date
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_millis());
+            encode_command(&["PEXPIREAT", key, &millis.to_string()])
+        }
+        Some((_, None)) => encode_command(&["PERSIST", key]),
+        None => encode_command(&["DEL", key]),
+    }
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+    out.extend_from_slice(bytes);
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.data.len() < len {
+            return Err(io::Error::new(
+                ErrorKind::UnexpectedEof,
+                "dump file is truncated",
+            ));
+        }
+        let (taken, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(taken)
+    }
+
+    fn read_u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn read_u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn read_u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn read_bytes(&mut self) -> io::Result<&'a [u8]> {
+        let len = self.read_u32()? as usize;
+        self.take(len)
+    }
+}
+
+// CRC-64/Jones, the variant Redis uses for its RDB files
+fn crc64(data: &[u8]) -> u64 {
+    const POLY: u64 = 0x95ac_9329_ac4b_c9b5;
+    let mut crc = 0u64;
+    for byte in data {
+        crc ^= *byte as u64;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ POLY
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // a path in the temp dir which no other test uses, removed if it's left over from a previous run
+    pub(crate) fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("redis-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_file(&path);
+        path.to_string_lossy().into_owned()
+    }
+
+    fn execute_and_log(dbs: &[Mutex<InMemoryDb>], aof: &mut AofWriter, lines: &[&str]) -> String {
+        let mut db = dbs[0].lock().unwrap();
+        let reply = execute(&mut db, lines);
+        aof.append(0, lines, &reply, &db).unwrap();
+        reply
+    }
+
+    #[test]
+    fn keys_loaded_from_snapshot_survive_next_restart() -> io::Result<()> {
+        let aof_path = temp_path("restart.aof");
+        let rdb_path = temp_path("restart.rdb");
+
+        // a snapshot taken before there was any AOF
+        let mut db = InMemoryDb::new();
+        execute(&mut db, &["SET", "from_rdb", "1"]);
+        save(&[Mutex::new(db)], &rdb_path)?;
+
+        let (dbs, mut aof) = restore(&aof_path, &rdb_path)?;
+        execute_and_log(&dbs, &mut aof, &["SET", "from_aof", "2"]);
+        drop(aof);
+
+        // now that the AOF exists, it's the only file read on restart
+        let (dbs, _) = restore(&aof_path, &rdb_path)?;
+        let mut db = dbs[0].lock().unwrap();
+        assert_eq!(execute(&mut db, &["GET", "from_rdb"]), "$1\r\n1\r\n");
+        assert_eq!(execute(&mut db, &["GET", "from_aof"]), "$1\r\n2\r\n");
+
+        fs::remove_file(aof_path)?;
+        fs::remove_file(rdb_path)
+    }
+
+    #[test]
+    fn log_writes_of_failed_script() -> io::Result<()> {
+        let aof_path = temp_path("failed_script.aof");
+        let dbs = vec![Mutex::new(InMemoryDb::new())];
+        let mut aof = AofWriter::open(&aof_path)?;
+        let script = "redis.call('SET', KEYS[1], 'value') error('failed')";
+        let reply = execute_and_log(&dbs, &mut aof, &["EVAL", script, "1", "key"]);
+        assert!(reply.starts_with("-ERR"));
+        // a command which fails without changing anything isn't logged
+        let reply = execute_and_log(&dbs, &mut aof, &["INCR", "key"]);
+        assert!(reply.starts_with("-ERR"));
+        drop(aof);
+        assert!(!fs::read_to_string(&aof_path)?.contains("INCR"));
+
+        let mut replayed = vec![InMemoryDb::new()];
+        replay_aof(&mut replayed, &aof_path)?;
+        assert_eq!(
+            execute(&mut replayed[0], &["GET", "key"]),
+            "$5\r\nvalue\r\n"
+        );
+
+        fs::remove_file(aof_path)
+    }
+}
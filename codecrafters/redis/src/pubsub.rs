@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use tokio::sync::mpsc::{self, Receiver, Sender};
+
+use crate::{bulk_string, encode_array, wrong_number_of_arguments};
+
+pub type Channels = Arc<RwLock<HashMap<String, Vec<Sender<Vec<u8>>>>>>;
+
+// messages published while the subscriber is busy writing are buffered up to this count
+const MESSAGE_BUFFER: usize = 32;
+
+// The pub-sub state of a single connection. Published messages are sent as
+// already encoded RESP arrays, so they can be written to the stream as they are.
+pub struct Subscriber {
+    sender: Sender<Vec<u8>>,
+    channels: Vec<String>,
+}
+
+impl Subscriber {
+    pub fn new() -> (Self, Receiver<Vec<u8>>) {
+        let (sender, receiver) = mpsc::channel(MESSAGE_BUFFER);
+        let subscriber = Self {
+            sender,
+            channels: Vec::new(),
+        };
+        (subscriber, receiver)
+    }
+
+    pub fn is_subscribed(&self) -> bool {
+        !self.channels.is_empty()
+    }
+
+    pub fn subscribe(&mut self, channels: &Channels, args: &[&str]) -> String {
+        if args.is_empty() {
+            return wrong_number_of_arguments("subscribe");
+        }
+
+        let mut send_str = String::new();
+        for channel in args {
+            if !self.channels.iter().any(|c| c == channel) {
+                channels
+                    .write()
+                    .unwrap()
+                    .entry(channel.to_string())
+                    .or_default()
+                    .push(self.sender.clone());
+                self.channels.push(channel.to_string());
+            }
+            send_str.push_str(&self.confirmation("subscribe", Some(channel)));
+        }
+        send_str
+    }
+
+    // without arguments, unsubscribes from every channel
+    pub fn unsubscribe(&mut self, channels: &Channels, args: &[&str]) -> String {
+        let targets = if args.is_empty() {
+            self.channels.clone()
+        } else {
+            args.iter().map(|channel| channel.to_string()).collect()
+        };
+        if targets.is_empty() {
+            return self.confirmation("unsubscribe", None);
+        }
+
+        let mut send_str = String::new();
+        for channel in &targets {
+            if let Some(index) = self.channels.iter().position(|c| c == channel) {
+                self.channels.remove(index);
+                let mut channels = channels.write().unwrap();
+                if let Some(senders) = channels.get_mut(channel) {
+                    senders.retain(|sender| !sender.same_channel(&self.sender));
+                    if senders.is_empty() {
+                        channels.remove(channel);
+                    }
+                }
+            }
+            send_str.push_str(&self.confirmation("unsubscribe", Some(channel)));
+        }
+        send_str
+    }
+
+    fn confirmation(&self, kind: &str, channel: Option<&str>) -> String {
+        let channel = match channel {
+            Some(channel) => bulk_string(channel.as_bytes()),
+            None => String::from("$-1\r\n"),
+        };
+        format!(
+            "*3\r\n{}{}:{}\r\n",
+            bulk_string(kind.as_bytes()),
+            channel,
+            self.channels.len()
+        )
+    }
+}
+
+pub async fn publish(channels: &Channels, args: &[&str]) -> String {
+    if args.len() != 2 {
+        return wrong_number_of_arguments("publish");
+    }
+
+    // the lock must not be held while waiting for slow subscribers
+    let senders = {
+        let mut channels = channels.write().unwrap();
+        let Some(senders) = channels.get_mut(args[0]) else {
+            return String::from(":0\r\n");
+        };
+        // subscribers whose connection has gone away are cleaned up here
+        senders.retain(|sender| !sender.is_closed());
+        senders.clone()
+    };
+
+    let message = encode_array(vec![b"message", args[0].as_bytes(), args[1].as_bytes()]);
+    let mut received = 0;
+    for sender in senders {
+        if sender.send(message.clone().into_bytes()).await.is_ok() {
+            received += 1;
+        }
+    }
+    format!(":{}\r\n", received)
+}
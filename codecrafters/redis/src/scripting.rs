@@ -0,0 +1,185 @@
+use std::cell::RefCell;
+
+use mlua::{Lua, LuaOptions, StdLib, Table, Value, Variadic};
+
+use crate::{execute, wrong_number_of_arguments, InMemoryDb, NOT_AN_INTEGER};
+
+pub fn eval(db: &mut InMemoryDb, args: &[&str]) -> String {
+    if args.len() < 2 {
+        return wrong_number_of_arguments("eval");
+    }
+
+    let Ok(numkeys) = args[1].parse::<usize>() else {
+        return String::from(NOT_AN_INTEGER);
+    };
+    if numkeys > args.len() - 2 {
+        return String::from("-ERR Number of keys can't be greater than number of args\r\n");
+    }
+    let (keys, argv) = args[2..].split_at(numkeys);
+
+    match run_script(db, args[0], keys, argv) {
+        Ok(send_str) => send_str,
+        Err(e) => format!(
+            "-ERR Error running script: {}\r\n",
+            e.to_string().replace(['\r', '\n'], " ")
+        ),
+    }
+}
+
+// the whole script runs against the already locked db, so it is atomic
+// from the point of view of other connections
+fn run_script(
+    db: &mut InMemoryDb,
+    script: &str,
+    keys: &[&str],
+    argv: &[&str],
+) -> mlua::Result<String> {
+    // io, os, package and debug would let a client run commands or read files on the server
+    let lua = Lua::new_with(
+        StdLib::TABLE | StdLib::STRING | StdLib::MATH,
+        LuaOptions::default(),
+    )?;
+    let db = RefCell::new(db);
+
+    lua.scope(|scope| {
+        let redis = lua.create_table()?;
+        redis.set(
+            "call",
+            scope.create_function(|lua, args: Variadic<String>| {
+                let reply = call(&db, &args)?;
+                if let Some(message) = reply.strip_prefix('-') {
+                    return Err(mlua::Error::RuntimeError(message.trim_end().to_string()));
+                }
+                resp_to_lua(lua, &reply)
+            })?,
+        )?;
+        redis.set(
+            "pcall",
+            scope.create_function(|lua, args: Variadic<String>| {
+                let reply = call(&db, &args)?;
+                resp_to_lua(lua, &reply)
+            })?,
+        )?;
+
+        let globals = lua.globals();
+        globals.set("redis", redis)?;
+        globals.set("KEYS", keys.to_vec())?;
+        globals.set("ARGV", argv.to_vec())?;
+
+        let value = lua.load(script).set_name("user_script").eval::<Value>()?;
+        Ok(lua_to_resp(&value))
+    })
+}
+
+fn call(db: &RefCell<&mut InMemoryDb>, args: &[String]) -> mlua::Result<String> {
+    let Some(command) = args.first() else {
+        return Err(mlua::Error::RuntimeError(String::from(
+            "Please specify at least one argument for this redis lib call",
+        )));
+    };
+    if command.eq_ignore_ascii_case("EVAL") {
+        return Err(mlua::Error::RuntimeError(String::from(
+            "This Redis command is not allowed from script",
+        )));
+    }
+
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    let mut db = db.borrow_mut();
+    Ok(execute(&mut db, &args))
+}
+
+fn resp_to_lua<'lua>(lua: &'lua Lua, reply: &str) -> mlua::Result<Value<'lua>> {
+    let (value, _) = parse_reply(lua, reply)?;
+    Ok(value)
+}
+
+fn parse_reply<'lua, 'a>(lua: &'lua Lua, reply: &'a str) -> mlua::Result<(Value<'lua>, &'a str)> {
+    let invalid = || mlua::Error::RuntimeError(String::from("Invalid reply from redis command"));
+    let (line, mut rest) = reply.split_once("\r\n").ok_or_else(invalid)?;
+    if line.is_empty() {
+        return Err(invalid());
+    }
+
+    let (kind, body) = line.split_at(1);
+    let value = match kind {
+        "+" | "-" => {
+            let table = lua.create_table()?;
+            table.set(if kind == "+" { "ok" } else { "err" }, body)?;
+            Value::Table(table)
+        }
+        ":" => Value::Integer(body.parse().map_err(|_| invalid())?),
+        "$" => {
+            let Ok(len) = usize::try_from(body.parse::<i64>().map_err(|_| invalid())?) else {
+                return Ok((Value::Boolean(false), rest));
+            };
+            let value = lua.create_string(rest.get(..len).ok_or_else(invalid)?)?;
+            rest = rest.get(len + 2..).ok_or_else(invalid)?;
+            Value::String(value)
+        }
+        "*" => {
+            let Ok(count) = usize::try_from(body.parse::<i64>().map_err(|_| invalid())?) else {
+                return Ok((Value::Boolean(false), rest));
+            };
+            let table = lua.create_table()?;
+            for i in 1..=count {
+                let (item, next) = parse_reply(lua, rest)?;
+                table.raw_set(i, item)?;
+                rest = next;
+            }
+            Value::Table(table)
+        }
+        _ => return Err(invalid()),
+    };
+
+    Ok((value, rest))
+}
+
+fn lua_to_resp(value: &Value) -> String {
+    match value {
+        Value::Integer(n) => format!(":{}\r\n", n),
+        Value::Number(n) => format!(":{}\r\n", *n as i64),
+        Value::String(s) => {
+            let s = s.to_string_lossy();
+            format!("${}\r\n{}\r\n", s.len(), s)
+        }
+        Value::Boolean(true) => String::from(":1\r\n"),
+        Value::Table(table) => table_to_resp(table),
+        _ => String::from("$-1\r\n"),
+    }
+}
+
+fn table_to_resp(table: &Table) -> String {
+    if let Ok(Value::String(ok)) = table.raw_get::<_, Value>("ok") {
+        return format!("+{}\r\n", ok.to_string_lossy());
+    }
+    if let Ok(Value::String(err)) = table.raw_get::<_, Value>("err") {
+        return format!("-{}\r\n", err.to_string_lossy());
+    }
+
+    let items = table
+        .clone()
+        .sequence_values::<Value>()
+        .filter_map(Result::ok)
+        .collect::<Vec<_>>();
+    let mut send_str = format!("*{}\r\n", items.len());
+    for item in &items {
+        send_str.push_str(&lua_to_resp(item));
+    }
+    send_str
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripts_cannot_reach_the_host() {
+        let mut db = InMemoryDb::new();
+        let reply = eval(&mut db, &["return type(os)..type(io)..type(package)", "0"]);
+        assert_eq!(reply, "$9\r\nnilnilnil\r\n");
+        let reply = eval(&mut db, &["return type(os)..type(io)", "0"]);
+        assert_eq!(reply, "$6\r\nnilnil\r\n");
+        let reply = eval(&mut db, &["return string.upper(ARGV[1])", "0", "ok"]);
+        assert_eq!(reply, "$2\r\nOK\r\n");
+    }
+}
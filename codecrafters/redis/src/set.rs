@@ -0,0 +1,77 @@
+use std::collections::HashSet;
+
+use crate::{encode_array, wrong_number_of_arguments, InMemoryDb, RedisValue, WRONGTYPE};
+
+pub fn sadd(db: &mut InMemoryDb, args: &[&str]) -> String {
+    if args.len() < 2 {
+        return wrong_number_of_arguments("sadd");
+    }
+
+    if db.get_mut(args[0]).is_none() {
+        db.insert(args[0].to_string(), (RedisValue::Set(HashSet::new()), None));
+    }
+    let Some(RedisValue::Set(set)) = db.get_mut(args[0]) else {
+        return String::from(WRONGTYPE);
+    };
+    let added = args[1..]
+        .iter()
+        .filter(|member| set.insert(member.as_bytes().to_vec()))
+        .count();
+    format!(":{}\r\n", added)
+}
+
+pub fn srem(db: &mut InMemoryDb, args: &[&str]) -> String {
+    if args.len() < 2 {
+        return wrong_number_of_arguments("srem");
+    }
+
+    let set = match db.get_mut(args[0]) {
+        Some(RedisValue::Set(set)) => set,
+        Some(_) => return String::from(WRONGTYPE),
+        None => return String::from(":0\r\n"),
+    };
+    let removed = args[1..]
+        .iter()
+        .filter(|member| set.remove(member.as_bytes()))
+        .count();
+    if set.is_empty() {
+        db.remove(args[0]);
+    }
+    format!(":{}\r\n", removed)
+}
+
+pub fn smembers(db: &mut InMemoryDb, args: &[&str]) -> String {
+    if args.len() != 1 {
+        return wrong_number_of_arguments("smembers");
+    }
+
+    match db.get_mut(args[0]) {
+        Some(RedisValue::Set(set)) => encode_array(set.iter().map(Vec::as_slice).collect()),
+        Some(_) => String::from(WRONGTYPE),
+        None => String::from("*0\r\n"),
+    }
+}
+
+pub fn scard(db: &mut InMemoryDb, args: &[&str]) -> String {
+    if args.len() != 1 {
+        return wrong_number_of_arguments("scard");
+    }
+
+    match db.get_mut(args[0]) {
+        Some(RedisValue::Set(set)) => format!(":{}\r\n", set.len()),
+        Some(_) => String::from(WRONGTYPE),
+        None => String::from(":0\r\n"),
+    }
+}
+
+pub fn sismember(db: &mut InMemoryDb, args: &[&str]) -> String {
+    if args.len() != 2 {
+        return wrong_number_of_arguments("sismember");
+    }
+
+    match db.get_mut(args[0]) {
+        Some(RedisValue::Set(set)) => format!(":{}\r\n", set.contains(args[1].as_bytes()) as u8),
+        Some(_) => String::from(WRONGTYPE),
+        None => String::from(":0\r\n"),
+    }
+}
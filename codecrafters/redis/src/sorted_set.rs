@@ -0,0 +1,238 @@
+use std::collections::{BTreeSet, HashMap};
+use std::ops::{Bound, RangeBounds};
+use std::str::FromStr;
+
+use ordered_float::OrderedFloat;
+
+use crate::{
+    bulk_string, encode_array, wrong_number_of_arguments, InMemoryDb, RedisValue, NOT_AN_INTEGER,
+    NOT_A_FLOAT, WRONGTYPE,
+};
+
+// members are indexed both by name (for score lookups) and by (score, member),
+// which keeps them ordered by score and then lexicographically like Redis does
+#[derive(Default)]
+pub struct SortedSet {
+    scores: HashMap<Vec<u8>, f64>,
+    ordered: BTreeSet<(OrderedFloat<f64>, Vec<u8>)>,
+}
+
+impl SortedSet {
+    fn insert(&mut self, member: Vec<u8>, score: f64) {
+        if let Some(old_score) = self.scores.insert(member.clone(), score) {
+            self.ordered
+                .remove(&(OrderedFloat(old_score), member.clone()));
+        }
+        self.ordered.insert((OrderedFloat(score), member));
+    }
+
+    // members in score order
+    pub fn iter(&self) -> impl Iterator<Item = (f64, &[u8])> {
+        self.ordered
+            .iter()
+            .map(|(score, member)| (score.0, member.as_slice()))
+    }
+
+    fn rank(&self, member: &[u8]) -> Option<usize> {
+        let score = *self.scores.get(member)?;
+        Some(
+            self.ordered
+                .range(..(OrderedFloat(score), member.to_vec()))
+                .count(),
+        )
+    }
+}
+
+pub fn zadd(db: &mut InMemoryDb, args: &[&str]) -> String {
+    if args.is_empty() {
+        return wrong_number_of_arguments("zadd");
+    }
+
+    let (mut nx, mut xx, mut gt, mut lt) = (false, false, false, false);
+    let mut i = 1;
+    while let Some(arg) = args.get(i) {
+        match arg.to_uppercase().as_str() {
+            "NX" => nx = true,
+            "XX" => xx = true,
+            "GT" => gt = true,
+            "LT" => lt = true,
+            _ => break,
+        }
+        i += 1;
+    }
+    let pairs = &args[i..];
+    if pairs.is_empty() || !pairs.len().is_multiple_of(2) {
+        return String::from("-ERR syntax error\r\n");
+    }
+    if nx && xx {
+        return String::from("-ERR XX and NX options at the same time are not compatible\r\n");
+    }
+    if (nx && (gt || lt)) || (gt && lt) {
+        return String::from(
+            "-ERR GT, LT, and/or NX options at the same time are not compatible\r\n",
+        );
+    }
+    let mut members = Vec::with_capacity(pairs.len() / 2);
+    for pair in pairs.chunks(2) {
+        match f64::from_str(pair[0]) {
+            Ok(score) if !score.is_nan() => members.push((score, pair[1])),
+            _ => return String::from(NOT_A_FLOAT),
+        }
+    }
+
+    if db.get_mut(args[0]).is_none() {
+        db.insert(
+            args[0].to_string(),
+            (RedisValue::SortedSet(SortedSet::default()), None),
+        );
+    }
+    let Some(RedisValue::SortedSet(sorted_set)) = db.get_mut(args[0]) else {
+        return String::from(WRONGTYPE);
+    };
+
+    let mut added = 0;
+    for (score, member) in members {
+        match sorted_set.scores.get(member.as_bytes()) {
+            Some(_) if nx => continue,
+            Some(&old_score) if (gt && score <= old_score) || (lt && score >= old_score) => {
+                continue
+            }
+            None if xx => continue,
+            None => added += 1,
+            Some(_) => {}
+        }
+        sorted_set.insert(member.as_bytes().to_vec(), score);
+    }
+    // XX on a missing key must not leave an empty sorted set behind
+    if sorted_set.scores.is_empty() {
+        db.remove(args[0]);
+    }
+    format!(":{}\r\n", added)
+}
+
+pub fn zrange(db: &mut InMemoryDb, args: &[&str]) -> String {
+    if args.len() < 3 {
+        return wrong_number_of_arguments("zrange");
+    }
+
+    let (mut by_score, mut rev, mut limit) = (false, false, None);
+    let mut options = args[3..].iter();
+    while let Some(option) = options.next() {
+        match option.to_uppercase().as_str() {
+            "BYSCORE" => by_score = true,
+            "REV" => rev = true,
+            "LIMIT" => {
+                let (Some(offset), Some(count)) = (options.next(), options.next()) else {
+                    return String::from("-ERR syntax error\r\n");
+                };
+                let (Ok(offset), Ok(count)) = (offset.parse::<usize>(), count.parse::<i64>())
+                else {
+                    return String::from(NOT_AN_INTEGER);
+                };
+                limit = Some((offset, count));
+            }
+            _ => return String::from("-ERR syntax error\r\n"),
+        }
+    }
+    if limit.is_some() && !by_score {
+        return String::from(
+            "-ERR syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX\r\n",
+        );
+    }
+
+    let sorted_set = match db.get_mut(args[0]) {
+        Some(RedisValue::SortedSet(sorted_set)) => sorted_set,
+        Some(_) => return String::from(WRONGTYPE),
+        None => return String::from("*0\r\n"),
+    };
+    let ordered: Box<dyn Iterator<Item = &(OrderedFloat<f64>, Vec<u8>)>> = if rev {
+        Box::new(sorted_set.ordered.iter().rev())
+    } else {
+        Box::new(sorted_set.ordered.iter())
+    };
+
+    let members = if by_score {
+        // with REV the range is given as max then min
+        let (min, max) = if rev {
+            (args[2], args[1])
+        } else {
+            (args[1], args[2])
+        };
+        let (Some(min), Some(max)) = (parse_score_bound(min), parse_score_bound(max)) else {
+            return String::from("-ERR min or max is not a float\r\n");
+        };
+        let (offset, count) = limit.unwrap_or((0, -1));
+        let count = usize::try_from(count).unwrap_or(usize::MAX);
+        ordered
+            .filter(|(score, _)| (min, max).contains(&score.0))
+            .skip(offset)
+            .take(count)
+            .map(|(_, member)| member.as_slice())
+            .collect()
+    } else {
+        let (Ok(start), Ok(stop)) = (args[1].parse::<i64>(), args[2].parse::<i64>()) else {
+            return String::from(NOT_AN_INTEGER);
+        };
+        // negative indices count from the end, like LRANGE
+        let len = sorted_set.ordered.len() as i64;
+        let start = if start < 0 {
+            (len + start).max(0)
+        } else {
+            start
+        };
+        let stop = if stop < 0 {
+            len + stop
+        } else {
+            stop.min(len - 1)
+        };
+        if start > stop {
+            return String::from("*0\r\n");
+        }
+        ordered
+            .skip(start as usize)
+            .take((stop - start + 1) as usize)
+            .map(|(_, member)| member.as_slice())
+            .collect()
+    };
+    encode_array(members)
+}
+
+pub fn zrank(db: &mut InMemoryDb, args: &[&str]) -> String {
+    if args.len() != 2 {
+        return wrong_number_of_arguments("zrank");
+    }
+
+    match db.get_mut(args[0]) {
+        Some(RedisValue::SortedSet(sorted_set)) => match sorted_set.rank(args[1].as_bytes()) {
+            Some(rank) => format!(":{}\r\n", rank),
+            None => String::from("$-1\r\n"),
+        },
+        Some(_) => String::from(WRONGTYPE),
+        None => String::from("$-1\r\n"),
+    }
+}
+
+pub fn zscore(db: &mut InMemoryDb, args: &[&str]) -> String {
+    if args.len() != 2 {
+        return wrong_number_of_arguments("zscore");
+    }
+
+    match db.get_mut(args[0]) {
+        Some(RedisValue::SortedSet(sorted_set)) => {
+            match sorted_set.scores.get(args[1].as_bytes()) {
+                Some(score) => bulk_string(score.to_string().as_bytes()),
+                None => String::from("$-1\r\n"),
+            }
+        }
+        Some(_) => String::from(WRONGTYPE),
+        None => String::from("$-1\r\n"),
+    }
+}
+
+// "(1.5" is an exclusive bound, "-inf" and "+inf" are parsed by f64::from_str
+fn parse_score_bound(arg: &str) -> Option<Bound<f64>> {
+    match arg.strip_prefix('(') {
+        Some(score) => f64::from_str(score).ok().map(Bound::Excluded),
+        None => f64::from_str(arg).ok().map(Bound::Included),
+    }
+}
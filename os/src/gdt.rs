@@ -5,17 +5,36 @@ use x86_64::VirtAddr;
 
 pub const DOUBLE_FAULT_IST_INDEX: u16 = 0;
 
+pub(crate) const STACK_SIZE: usize = 4096 * 5;
+
+// スタックのトップは System V ABI に従って 16 バイト境界に揃える必要があるので、
+// u8 の配列をそのまま置かずにアラインメントを指定した構造体で包む
+#[repr(align(16))]
+pub(crate) struct Stack([u8; STACK_SIZE]);
+
+impl Stack {
+    pub(crate) const fn new() -> Self {
+        Stack([0; STACK_SIZE])
+    }
+}
+
 lazy_static! {
     static ref TSS: TaskStateSegment = {
         let mut tss = TaskStateSegment::new();
         tss.interrupt_stack_table[DOUBLE_FAULT_IST_INDEX as usize] = {
-            const STACK_SIZE: usize = 4096 * 5;
-            static mut STACK: [u8; STACK_SIZE] = [0; STACK_SIZE];
+            static mut STACK: Stack = Stack::new();
 
             let stack_start = VirtAddr::from_ptr(unsafe { &STACK });
             let stack_end = stack_start + STACK_SIZE;
             stack_end
         };
+        // ユーザーモードで割り込みが起きた時に切り替えるカーネルのスタック
+        tss.privilege_stack_table[0] = {
+            static mut STACK: Stack = Stack::new();
+
+            let stack_start = VirtAddr::from_ptr(unsafe { &STACK });
+            stack_start + STACK_SIZE
+        };
         tss
     };
 }
@@ -23,21 +42,36 @@ lazy_static! {
 lazy_static! {
     static ref GDT: (GlobalDescriptorTable, Selectors) = {
         let mut gdt = GlobalDescriptorTable::new();
+        // syscall/sysret は STAR レジスタの値からの相対位置でセグメントを決めるので、
+        // カーネルのコード・データ、ユーザーのデータ・コードの順に並べる
         let code_selector = gdt.add_entry(Descriptor::kernel_code_segment());
+        let data_selector = gdt.add_entry(Descriptor::kernel_data_segment());
         let tss_selector = gdt.add_entry(Descriptor::tss_segment(&TSS));
+        let user_data_selector = gdt.add_entry(Descriptor::user_data_segment());
+        let user_code_selector = gdt.add_entry(Descriptor::user_code_segment());
         (
             gdt,
             Selectors {
                 code_selector,
+                data_selector,
                 tss_selector,
+                user_data_selector,
+                user_code_selector,
             },
         )
     };
 }
 
-struct Selectors {
-    code_selector: SegmentSelector,
+pub(crate) struct Selectors {
+    pub(crate) code_selector: SegmentSelector,
+    pub(crate) data_selector: SegmentSelector,
     tss_selector: SegmentSelector,
+    pub(crate) user_data_selector: SegmentSelector,
+    pub(crate) user_code_selector: SegmentSelector,
+}
+
+pub(crate) fn selectors() -> &'static Selectors {
+    &GDT.1
 }
 
 pub fn init() {
@@ -1,4 +1,4 @@
-use crate::{gdt, hlt_loop, print, println};
+use crate::{gdt, hlt_loop, print, println, process};
 use lazy_static::lazy_static;
 use pic8259::ChainedPics;
 use spin;
@@ -65,6 +65,10 @@ extern "x86-interrupt" fn timer_interrupt_handler(_stack_frame: InterruptStackFr
         PICS.lock()
             .notify_end_of_interrupt(InterruptIndex::Timer.as_u8());
     }
+    // スケジューラーはローカル APIC タイマーではなく、この PIC のタイマー割り込みで駆動する。
+    // 切り替え先のプロセスはこのハンドラから戻らないことがあるので、EOI を送ってから切り替える。
+    // 切り替え前のプロセスに戻ってきた時に、このハンドラの iretq で割り込まれた場所に戻る
+    process::schedule();
 }
 
 extern "x86-interrupt" fn keyboard_interrupt_handler(_stack_frame: InterruptStackFrame) {
@@ -83,6 +87,7 @@ extern "x86-interrupt" fn keyboard_interrupt_handler(_stack_frame: InterruptStac
 
     let scancode: u8 = unsafe { port.read() };
     crate::task::keyboard::add_scancode(scancode); // new
+    crate::keyboard::add_scancode(scancode);
 
     // if let Ok(Some(key_event)) = keyboard.add_byte(scancode) {
     //     if let Some(key) = keyboard.process_keyevent(key_event) {
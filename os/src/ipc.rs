@@ -0,0 +1,145 @@
+use alloc::{vec, vec::Vec};
+use core::{
+    mem::size_of,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+const PAGE_SIZE: usize = 4096;
+// ページの先頭に head と tail を置き、その後ろをデータ領域として使う
+const HEADER_SIZE: usize = 2 * size_of::<AtomicUsize>();
+// 各メッセージの先頭に付ける長さ (u32)
+const LENGTH_PREFIX_SIZE: usize = size_of::<u32>();
+
+/// 共有メモリページ上に置かれる、単一の送信者と単一の受信者の間のリングバッファ
+///
+/// head と tail は書き込み・読み出したバイト数の累計で、
+/// 実際の位置は `size` で割った余りで求める。
+pub struct RingBuffer {
+    buf: *mut u8,
+    size: usize,
+    head: *mut AtomicUsize,
+    tail: *mut AtomicUsize,
+}
+
+// head と tail はアトミックに更新されるので、送信側と受信側を別のコンテキストに渡してよい
+unsafe impl Send for RingBuffer {}
+unsafe impl Sync for RingBuffer {}
+
+impl RingBuffer {
+    /// 共有メモリページの先頭に head と tail を初期化してリングバッファを作る。
+    ///
+    /// この関数はunsafeである：呼び出し元は `page_ptr` が
+    /// 有効な（少なくとも 1 ページ分の）書き込み可能なメモリを指し、
+    /// `usize` のアラインメントを満たしていることを保証しなければならない。
+    pub unsafe fn new(page_ptr: *mut u8, capacity: usize) -> RingBuffer {
+        let ring_buffer = RingBuffer::attach(page_ptr, capacity);
+        ring_buffer.head.write(AtomicUsize::new(0));
+        ring_buffer.tail.write(AtomicUsize::new(0));
+        ring_buffer
+    }
+
+    /// 既に `new` で初期化されたページを、もう一方の側から参照する。
+    ///
+    /// この関数はunsafeである：`new` と同じ条件に加えて、
+    /// ページが同じ `capacity` で初期化済みであることを保証しなければならない。
+    pub unsafe fn attach(page_ptr: *mut u8, capacity: usize) -> RingBuffer {
+        assert!(
+            capacity > LENGTH_PREFIX_SIZE && HEADER_SIZE + capacity <= PAGE_SIZE,
+            "ring buffer capacity must fit in a single page"
+        );
+        let head = page_ptr as *mut AtomicUsize;
+        RingBuffer {
+            buf: page_ptr.add(HEADER_SIZE),
+            size: capacity,
+            head,
+            tail: head.add(1),
+        }
+    }
+
+    /// メッセージを書き込む。空きが足りない場合は何もせず false を返す。
+    pub fn push(&self, data: &[u8]) -> bool {
+        let (head, tail) = unsafe {
+            (
+                (*self.head).load(Ordering::Relaxed),
+                (*self.tail).load(Ordering::Acquire),
+            )
+        };
+        let required = LENGTH_PREFIX_SIZE + data.len();
+        if data.len() > u32::MAX as usize || self.size - head.wrapping_sub(tail) < required {
+            return false;
+        }
+
+        self.write_bytes(head, &(data.len() as u32).to_le_bytes());
+        self.write_bytes(head.wrapping_add(LENGTH_PREFIX_SIZE), data);
+        // データを書き終えてから head を進めることで、受信側に書きかけのデータが見えないようにする
+        unsafe { (*self.head).fetch_add(required, Ordering::Release) };
+        true
+    }
+
+    /// メッセージを 1 つ読み出す。バッファが空の場合は None を返す。
+    pub fn pop(&self) -> Option<Vec<u8>> {
+        let (head, tail) = unsafe {
+            (
+                (*self.head).load(Ordering::Acquire),
+                (*self.tail).load(Ordering::Relaxed),
+            )
+        };
+        if head == tail {
+            return None;
+        }
+
+        let mut length = [0; LENGTH_PREFIX_SIZE];
+        self.read_bytes(tail, &mut length);
+        let mut data = vec![0; u32::from_le_bytes(length) as usize];
+        self.read_bytes(tail.wrapping_add(LENGTH_PREFIX_SIZE), &mut data);
+        // 読み終えてから tail を進め、送信側がその領域を再利用できるようにする
+        unsafe { (*self.tail).fetch_add(LENGTH_PREFIX_SIZE + data.len(), Ordering::Release) };
+        Some(data)
+    }
+
+    fn write_bytes(&self, position: usize, bytes: &[u8]) {
+        for (i, byte) in bytes.iter().enumerate() {
+            let offset = position.wrapping_add(i) % self.size;
+            unsafe { self.buf.add(offset).write_volatile(*byte) };
+        }
+    }
+
+    fn read_bytes(&self, position: usize, bytes: &mut [u8]) {
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            let offset = position.wrapping_add(i) % self.size;
+            *byte = unsafe { self.buf.add(offset).read_volatile() };
+        }
+    }
+}
+
+#[cfg(test)]
+#[repr(align(4096))]
+struct Page([u8; PAGE_SIZE]);
+
+#[test_case]
+fn test_ring_buffer_push_pop() {
+    let mut page = Page([0; PAGE_SIZE]);
+    let sender = unsafe { RingBuffer::new(page.0.as_mut_ptr(), 64) };
+    let receiver = unsafe { RingBuffer::attach(page.0.as_mut_ptr(), 64) };
+
+    assert_eq!(receiver.pop(), None);
+    assert!(sender.push(b"hello"));
+    assert!(sender.push(b"world"));
+    assert_eq!(receiver.pop(), Some(b"hello".to_vec()));
+    assert_eq!(receiver.pop(), Some(b"world".to_vec()));
+    assert_eq!(receiver.pop(), None);
+}
+
+#[test_case]
+fn test_ring_buffer_wraps_around() {
+    let mut page = Page([0; PAGE_SIZE]);
+    let ring_buffer = unsafe { RingBuffer::new(page.0.as_mut_ptr(), 16) };
+
+    // 4 + 10 バイトのメッセージは 16 バイトのバッファに 1 つしか入らない
+    assert!(ring_buffer.push(&[1; 10]));
+    assert!(!ring_buffer.push(&[2; 10]));
+    for i in 1..=10 {
+        assert_eq!(ring_buffer.pop(), Some(vec![i; 10]));
+        assert!(ring_buffer.push(&[i + 1; 10]));
+    }
+}
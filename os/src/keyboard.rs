@@ -0,0 +1,130 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+use spin::Mutex;
+use x86_64::instructions::interrupts;
+
+// 読み出されていない文字を溜めておける数
+const BUFFER_SIZE: usize = 64;
+
+// スキャンコードセット 1 のシフトキー (押下時と離した時)
+const LEFT_SHIFT_PRESSED: u8 = 0x2a;
+const RIGHT_SHIFT_PRESSED: u8 = 0x36;
+const LEFT_SHIFT_RELEASED: u8 = 0xaa;
+const RIGHT_SHIFT_RELEASED: u8 = 0xb6;
+// キーを離した時のスキャンコードは、押下時のものに最上位ビットを立てたもの
+const RELEASED_BIT: u8 = 0x80;
+
+// US QWERTY 配列のスキャンコードセット 1 から ASCII への変換表 (0 は対応する文字なし)
+// cf: https://wiki.osdev.org/PS/2_Keyboard#Scan_Code_Set_1
+#[rustfmt::skip]
+const SCANCODE_TO_ASCII: [u8; 0x3a] = [
+    0, 0x1b, b'1', b'2', b'3', b'4', b'5', b'6', b'7', b'8', b'9', b'0', b'-', b'=', 0x08, b'\t',
+    b'q', b'w', b'e', b'r', b't', b'y', b'u', b'i', b'o', b'p', b'[', b']', b'\n', 0, b'a', b's',
+    b'd', b'f', b'g', b'h', b'j', b'k', b'l', b';', b'\'', b'`', 0, b'\\', b'z', b'x', b'c', b'v',
+    b'b', b'n', b'm', b',', b'.', b'/', 0, b'*', 0, b' ',
+];
+
+// シフトキーを押している間の変換表
+#[rustfmt::skip]
+const SHIFTED_SCANCODE_TO_ASCII: [u8; 0x3a] = [
+    0, 0x1b, b'!', b'@', b'#', b'$', b'%', b'^', b'&', b'*', b'(', b')', b'_', b'+', 0x08, b'\t',
+    b'Q', b'W', b'E', b'R', b'T', b'Y', b'U', b'I', b'O', b'P', b'{', b'}', b'\n', 0, b'A', b'S',
+    b'D', b'F', b'G', b'H', b'J', b'K', b'L', b':', b'"', b'~', 0, b'|', b'Z', b'X', b'C', b'V',
+    b'B', b'N', b'M', b'<', b'>', b'?', 0, b'*', 0, b' ',
+];
+
+static SHIFT: AtomicBool = AtomicBool::new(false);
+static BUFFER: Mutex<CharBuffer> = Mutex::new(CharBuffer::new());
+
+/// 固定長のリングバッファ
+///
+/// 割り込みハンドラからも使うので、アロケートはしない。
+struct CharBuffer {
+    chars: [u8; BUFFER_SIZE],
+    head: usize,
+    len: usize,
+}
+
+impl CharBuffer {
+    const fn new() -> CharBuffer {
+        CharBuffer {
+            chars: [0; BUFFER_SIZE],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// いっぱいの場合は何もしない
+    fn push(&mut self, c: u8) {
+        if self.len == BUFFER_SIZE {
+            return;
+        }
+        self.chars[(self.head + self.len) % BUFFER_SIZE] = c;
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<u8> {
+        if self.len == 0 {
+            return None;
+        }
+        let c = self.chars[self.head];
+        self.head = (self.head + 1) % BUFFER_SIZE;
+        self.len -= 1;
+        Some(c)
+    }
+}
+
+/// キーボード割り込みハンドラから呼び出される
+///
+/// スキャンコードを ASCII に変換してバッファに積む。
+/// 処理をブロックしたり、アロケートをしてはいけない。
+pub(crate) fn add_scancode(scancode: u8) {
+    match scancode {
+        LEFT_SHIFT_PRESSED | RIGHT_SHIFT_PRESSED => SHIFT.store(true, Ordering::Relaxed),
+        LEFT_SHIFT_RELEASED | RIGHT_SHIFT_RELEASED => SHIFT.store(false, Ordering::Relaxed),
+        _ if scancode & RELEASED_BIT != 0 => {}
+        _ => {
+            let table = if SHIFT.load(Ordering::Relaxed) {
+                &SHIFTED_SCANCODE_TO_ASCII
+            } else {
+                &SCANCODE_TO_ASCII
+            };
+            match table.get(usize::from(scancode)) {
+                Some(&c) if c != 0 => {
+                    // 割り込みハンドラ内では割り込みが無効なので、ロックを待ち続けることはない。
+                    // read_char を使わない間もバッファは埋まるので、溢れた分は黙って捨てる
+                    BUFFER.lock().push(c);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// 入力された文字をバッファから 1 文字取り出す。何も入力されていなければ None を返す。
+pub fn read_char() -> Option<char> {
+    // ロック中にキーボード割り込みが入るとデッドロックするので、割り込みを無効にしておく
+    interrupts::without_interrupts(|| BUFFER.lock().pop().map(char::from))
+}
+
+#[test_case]
+fn test_translate_scancodes() {
+    while read_char().is_some() {}
+
+    // "aB1" を入力する (B はシフトを押しながら)
+    for scancode in [
+        0x1e,
+        0x9e,
+        LEFT_SHIFT_PRESSED,
+        0x30,
+        0xb0,
+        LEFT_SHIFT_RELEASED,
+        0x02,
+        0x82,
+    ] {
+        interrupts::without_interrupts(|| add_scancode(scancode));
+    }
+    assert_eq!(read_char(), Some('a'));
+    assert_eq!(read_char(), Some('B'));
+    assert_eq!(read_char(), Some('1'));
+    assert_eq!(read_char(), None);
+}
@@ -15,14 +15,20 @@ use core::panic::PanicInfo;
 pub mod allocator;
 pub mod gdt;
 pub mod interrupts;
+pub mod ipc;
+pub mod keyboard;
+pub mod libuser;
 pub mod memory;
+pub mod process;
 pub mod serial;
+pub mod syscall;
 pub mod task;
 pub mod vga_buffer;
 
 pub fn init() {
     gdt::init();
     interrupts::init_idt();
+    syscall::init();
     unsafe { interrupts::PICS.lock().initialize() };
     x86_64::instructions::interrupts::enable();
 }
@@ -0,0 +1,34 @@
+//! ユーザープログラムからカーネルを呼び出すための関数
+use crate::syscall::{STDIN, STDOUT, SYS_EXIT, SYS_READ, SYS_WRITE};
+use core::arch::asm;
+
+// syscall 命令は rcx と r11 を書き換えるので、壊れてもよいレジスタとして指定する
+unsafe fn syscall3(number: u64, arg0: u64, arg1: u64, arg2: u64) -> u64 {
+    let ret;
+    asm!(
+        "syscall",
+        inlateout("rax") number => ret,
+        in("rdi") arg0,
+        in("rsi") arg1,
+        in("rdx") arg2,
+        out("rcx") _,
+        out("r11") _,
+        options(nostack),
+    );
+    ret
+}
+
+/// 標準出力 (VGA バッファ) に書き込み、書き込んだバイト数を返す
+pub fn sys_write(s: &str) -> u64 {
+    unsafe { syscall3(SYS_WRITE, STDOUT, s.as_ptr() as u64, s.len() as u64) }
+}
+
+/// 標準入力 (キーボード) から読めるだけ読み、読んだバイト数を返す
+pub fn sys_read(buf: &mut [u8]) -> u64 {
+    unsafe { syscall3(SYS_READ, STDIN, buf.as_mut_ptr() as u64, buf.len() as u64) }
+}
+
+pub fn sys_exit(code: u64) -> ! {
+    unsafe { syscall3(SYS_EXIT, code, 0, 0) };
+    unreachable!("exit syscall returned");
+}
@@ -0,0 +1,181 @@
+use alloc::{boxed::Box, collections::VecDeque, vec, vec::Vec};
+use core::arch::global_asm;
+use core::sync::atomic::{AtomicU64, Ordering};
+use lazy_static::lazy_static;
+use spin::Mutex;
+use x86_64::instructions::interrupts;
+
+// 各プロセスのスタックサイズ
+const STACK_SIZE: usize = 16 * 1024;
+
+static NEXT_PID: AtomicU64 = AtomicU64::new(1);
+
+lazy_static! {
+    static ref SCHEDULER: Mutex<Scheduler> = Mutex::new(Scheduler {
+        current: None,
+        ready: VecDeque::new(),
+    });
+}
+
+/// コンテキストスイッチで保存するレジスタ
+///
+/// 呼び出し先保存レジスタ以外は `switch_context` の呼び出し側 (割り込みハンドラなど) が
+/// スタックに退避しているので、ここでは保存しない。
+/// `switch_context` がオフセットで読み書きするので、フィールドの順番を変えてはいけない。
+#[derive(Debug, Default)]
+#[repr(C)]
+pub struct Context {
+    rsp: u64,
+    rbp: u64,
+    rbx: u64,
+    r12: u64,
+    r13: u64,
+    r14: u64,
+    r15: u64,
+}
+
+pub struct Process {
+    pub pid: u64,
+    // プロセスが生きている間スタックを解放しないように持っておく。
+    // カーネルの起動時から動いているプロセスは、ブートローダーが用意したスタックを使うので空
+    #[allow(dead_code)]
+    stack: Vec<u8>,
+    // プロセスがキューの間を移動してもアドレスが変わらないように Box に入れる
+    context: Box<Context>,
+}
+
+impl Process {
+    fn new(f: fn() -> !) -> Process {
+        let mut stack = vec![0; STACK_SIZE];
+        let mut context = Box::new(Context::default());
+
+        // 最初に切り替えられた時に switch_context の ret で process_start に飛ぶように、
+        // スタックの末尾に戻りアドレスを積んでおく。
+        // process_start から関数を call する時に rsp が 16 バイト境界になるように揃える
+        let stack_end = stack.as_mut_ptr() as u64 + STACK_SIZE as u64;
+        let rsp = (stack_end & !0xf) - 8;
+        unsafe { (rsp as *mut u64).write(process_start as usize as u64) };
+        context.rsp = rsp;
+        // process_start は r12 に入っている関数を実行する
+        context.r12 = f as usize as u64;
+
+        Process {
+            pid: NEXT_PID.fetch_add(1, Ordering::Relaxed),
+            stack,
+            context,
+        }
+    }
+
+    // 今動いているカーネル自身を pid 0 のプロセスとして扱う
+    fn kernel() -> Process {
+        Process {
+            pid: 0,
+            stack: Vec::new(),
+            context: Box::new(Context::default()),
+        }
+    }
+}
+
+struct Scheduler {
+    current: Option<Process>,
+    ready: VecDeque<Process>,
+}
+
+/// `f` を実行するプロセスを作り、実行待ちのキューに追加する
+pub fn spawn(f: fn() -> !) -> u64 {
+    let process = Process::new(f);
+    let pid = process.pid;
+    interrupts::without_interrupts(|| {
+        let mut scheduler = SCHEDULER.lock();
+        // 割り込みハンドラ内で Box をアロケートしなくて済むように、ここで作っておく
+        if scheduler.current.is_none() {
+            scheduler.current = Some(Process::kernel());
+        }
+        scheduler.ready.push_back(process);
+    });
+    pid
+}
+
+/// 実行中のプロセスをキューの末尾に戻し、先頭のプロセスに切り替える (ラウンドロビン)
+///
+/// 割り込みはローカル APIC タイマーではなく、8259 PIC 経由の PIT (IRQ 0) のタイマー割り込みを使っている。
+/// タイマー割り込みハンドラから呼び出されるので、ロックを待ったりアロケートしたりしてはいけない。
+/// 割り込みを無効にした状態で呼び出す必要がある。
+pub fn schedule() {
+    let (current, next) = {
+        let mut scheduler = SCHEDULER.lock();
+        let next = match scheduler.ready.pop_front() {
+            Some(next) => next,
+            None => return,
+        };
+        let next_context = &*next.context as *const Context;
+        let mut current = scheduler
+            .current
+            .replace(next)
+            .expect("scheduler has no current process");
+        let current_context = &mut *current.context as *mut Context;
+        // pop_front した直後なので、push_back でアロケートは起きない
+        scheduler.ready.push_back(current);
+        (current_context, next_context)
+    };
+    // 切り替え先のプロセスがロックを取れるように、ロックを外してから切り替える
+    unsafe { switch_context(current, next) };
+}
+
+/// 実行中のプロセスの残りの時間を他のプロセスに譲る
+pub fn yield_now() {
+    interrupts::without_interrupts(schedule);
+}
+
+/// 実行中のプロセスの pid を返す
+pub fn current_pid() -> u64 {
+    interrupts::without_interrupts(|| {
+        SCHEDULER
+            .lock()
+            .current
+            .as_ref()
+            .map_or(0, |process| process.pid)
+    })
+}
+
+extern "C" {
+    // 現在のレジスタを `current` に保存し、`next` のレジスタを復元する。
+    // 復元したスタックの戻りアドレスに戻るので、`next` が前回 switch_context を呼んだ場所から再開する
+    fn switch_context(current: *mut Context, next: *const Context);
+    fn process_start() -> !;
+}
+
+global_asm!(
+    r#"
+.global switch_context
+switch_context:
+    mov [rdi + 0x00], rsp
+    mov [rdi + 0x08], rbp
+    mov [rdi + 0x10], rbx
+    mov [rdi + 0x18], r12
+    mov [rdi + 0x20], r13
+    mov [rdi + 0x28], r14
+    mov [rdi + 0x30], r15
+    mov rsp, [rsi + 0x00]
+    mov rbp, [rsi + 0x08]
+    mov rbx, [rsi + 0x10]
+    mov r12, [rsi + 0x18]
+    mov r13, [rsi + 0x20]
+    mov r14, [rsi + 0x28]
+    mov r15, [rsi + 0x30]
+    ret
+
+.global process_start
+process_start:
+    mov rdi, r12
+    call run_process
+    ud2
+"#
+);
+
+// 新しいプロセスは割り込みを無効にした状態で切り替えられてくるので、有効にしてから実行する
+#[no_mangle]
+extern "C" fn run_process(f: fn() -> !) -> ! {
+    interrupts::enable();
+    f()
+}
@@ -0,0 +1,158 @@
+use crate::gdt::{self, Stack, STACK_SIZE};
+use crate::{hlt_loop, keyboard, print, println, serial_print};
+use core::arch::global_asm;
+use x86_64::registers::model_specific::{Efer, EferFlags, LStar, SFMask, Star};
+use x86_64::registers::rflags::RFlags;
+use x86_64::VirtAddr;
+
+// システムコール番号 (rax に入れて syscall する)
+pub const SYS_WRITE: u64 = 0;
+pub const SYS_READ: u64 = 1;
+pub const SYS_EXIT: u64 = 2;
+
+pub const STDIN: u64 = 0;
+pub const STDOUT: u64 = 1;
+pub const STDERR: u64 = 2;
+
+// 失敗した時の戻り値 (-1)
+const ERROR: u64 = u64::MAX;
+
+static mut SYSCALL_STACK: Stack = Stack::new();
+
+// syscall 命令はスタックを切り替えないので、エントリポイントで自分で切り替える。
+// システムコール中は割り込みを無効にしているので (SFMask)、スタックは 1 つで足りる
+#[no_mangle]
+static mut SYSCALL_STACK_TOP: u64 = 0;
+#[no_mangle]
+static mut USER_RSP: u64 = 0;
+
+/// syscall 命令で syscall_entry に飛ぶように MSR を設定する
+pub fn init() {
+    let selectors = gdt::selectors();
+    unsafe {
+        SYSCALL_STACK_TOP = VirtAddr::from_ptr(&SYSCALL_STACK).as_u64() + STACK_SIZE as u64;
+
+        Efer::update(|flags| *flags |= EferFlags::SYSTEM_CALL_EXTENSIONS);
+        LStar::write(VirtAddr::new(syscall_entry as usize as u64));
+        Star::write(
+            selectors.user_code_selector,
+            selectors.user_data_selector,
+            selectors.code_selector,
+            selectors.data_selector,
+        )
+        .expect("invalid segment layout for syscall/sysret");
+        // syscall でカーネルに入る時にクリアするフラグ
+        SFMask::write(RFlags::INTERRUPT_FLAG | RFlags::DIRECTION_FLAG);
+    }
+}
+
+extern "C" {
+    fn syscall_entry();
+}
+
+// syscall 命令は rcx に戻り先のアドレス、r11 に rflags を入れてここに飛んでくる。
+// 引数は rdi, rsi, rdx で受け取り、戻り値を rax に入れて sysret で戻る。
+// rcx と r11 以外のレジスタは呼び出し元に戻る時に元の値に戻す。
+// 9 個 push した後、call の前に rsp を 16 バイト境界に揃えるために 8 バイトずらす
+global_asm!(
+    r#"
+.global syscall_entry
+syscall_entry:
+    mov [rip + USER_RSP], rsp
+    mov rsp, [rip + SYSCALL_STACK_TOP]
+    push qword ptr [rip + USER_RSP]
+    push rcx
+    push r11
+    push rdi
+    push rsi
+    push rdx
+    push r8
+    push r9
+    push r10
+    sub rsp, 8
+
+    mov rcx, rdx
+    mov rdx, rsi
+    mov rsi, rdi
+    mov rdi, rax
+    call syscall_handler
+
+    add rsp, 8
+    pop r10
+    pop r9
+    pop r8
+    pop rdx
+    pop rsi
+    pop rdi
+    pop r11
+    pop rcx
+    pop rsp
+    sysretq
+"#
+);
+
+/// rax のシステムコール番号に対応するカーネルの関数を呼び出す
+#[no_mangle]
+extern "C" fn syscall_handler(number: u64, arg0: u64, arg1: u64, arg2: u64) -> u64 {
+    match number {
+        SYS_WRITE => write(arg0, arg1 as *const u8, arg2 as usize),
+        SYS_READ => read(arg0, arg1 as *mut u8, arg2 as usize),
+        SYS_EXIT => exit(arg0),
+        _ => ERROR,
+    }
+}
+
+// fd 1 は VGA バッファ、fd 2 はシリアルポートに書き込む
+fn write(fd: u64, buf: *const u8, len: usize) -> u64 {
+    // まだユーザー空間のアドレスかどうかは確かめていない
+    let bytes = unsafe { core::slice::from_raw_parts(buf, len) };
+    let s = match core::str::from_utf8(bytes) {
+        Ok(s) => s,
+        Err(_) => return ERROR,
+    };
+    match fd {
+        STDOUT => print!("{}", s),
+        STDERR => serial_print!("{}", s),
+        _ => return ERROR,
+    }
+    len as u64
+}
+
+// fd 0 からキーボードに入力された文字を読む。入力を待たずに、読めた分だけを返す
+fn read(fd: u64, buf: *mut u8, len: usize) -> u64 {
+    if fd != STDIN {
+        return ERROR;
+    }
+    let buf = unsafe { core::slice::from_raw_parts_mut(buf, len) };
+    let mut count = 0;
+    while count < len {
+        match keyboard::read_char() {
+            Some(c) => {
+                buf[count] = c as u8;
+                count += 1;
+            }
+            None => break,
+        }
+    }
+    count as u64
+}
+
+// ユーザープロセスの終了処理はまだないので、終了コードを表示して止める
+fn exit(code: u64) -> u64 {
+    println!("process exited with code {}", code);
+    hlt_loop();
+}
+
+#[test_case]
+fn test_syscall_handler() {
+    let s = "test_syscall_handler output\n";
+    assert_eq!(
+        syscall_handler(SYS_WRITE, STDERR, s.as_ptr() as u64, s.len() as u64),
+        s.len() as u64
+    );
+    assert_eq!(
+        syscall_handler(SYS_WRITE, 3, s.as_ptr() as u64, s.len() as u64),
+        ERROR
+    );
+    assert_eq!(syscall_handler(42, 0, 0, 0), ERROR);
+}
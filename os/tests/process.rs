@@ -0,0 +1,83 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(blog_os::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use blog_os::{process, serial_print, serial_println};
+use bootloader::{entry_point, BootInfo};
+use core::panic::PanicInfo;
+use spin::Mutex;
+use x86_64::instructions::interrupts;
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    use blog_os::allocator;
+    use blog_os::memory::{self, BootInfoFrameAllocator};
+    use x86_64::VirtAddr;
+
+    blog_os::init();
+
+    let phys_mem_offset = VirtAddr::new(boot_info.physical_memory_offset);
+    let mut mapper = unsafe { memory::init(phys_mem_offset) };
+    let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
+
+    // ヒープ領域の作成
+    allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
+
+    test_main();
+    loop {}
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    blog_os::test_panic_handler(info)
+}
+
+const COUNT: usize = 5;
+
+static OUTPUT: Mutex<Vec<char>> = Mutex::new(Vec::new());
+
+// 1 文字書くたびに他のプロセスに切り替える。
+// 書いてから切り替えるまでの間にタイマーで切り替えられないように、割り込みを無効にしておく
+fn print_repeatedly(c: char) -> ! {
+    for _ in 0..COUNT {
+        interrupts::without_interrupts(|| {
+            serial_print!("{}", c);
+            OUTPUT.lock().push(c);
+            process::schedule();
+        });
+    }
+    loop {
+        process::yield_now();
+    }
+}
+
+fn print_a() -> ! {
+    print_repeatedly('a')
+}
+
+fn print_b() -> ! {
+    print_repeatedly('b')
+}
+
+#[test_case]
+fn alternate_between_processes() {
+    let a = process::spawn(print_a);
+    let b = process::spawn(print_b);
+    assert_ne!(a, b);
+    assert_eq!(process::current_pid(), 0);
+
+    while interrupts::without_interrupts(|| OUTPUT.lock().len()) < 2 * COUNT {
+        process::yield_now();
+    }
+    serial_println!();
+
+    let output = interrupts::without_interrupts(|| OUTPUT.lock().clone());
+    let expected = ['a', 'b'].repeat(COUNT);
+    assert_eq!(output, expected);
+}
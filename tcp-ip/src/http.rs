@@ -1,7 +1,8 @@
 use crate::{
     ip::IpPacketManager,
     nic::NetDevice,
-    tcp::{HeaderFlags, SharedConnection, TcpPacketManager},
+    pcap::PcapWriter,
+    tcp::{SharedConnection, TcpPacketManager},
 };
 use std::{collections::HashMap, str, sync::Arc};
 use tracing::info;
@@ -13,9 +14,9 @@ pub struct Server {
 }
 
 impl Server {
-    pub fn new() -> Server {
+    pub fn new(pcap: Option<PcapWriter>) -> Server {
         Server {
-            net_device: Arc::new(NetDevice::new()),
+            net_device: Arc::new(NetDevice::new(pcap)),
             ip_manager: Arc::new(IpPacketManager::new()),
             tcp_manager: Arc::new(TcpPacketManager::new()),
         }
@@ -33,9 +34,26 @@ impl Server {
         self.tcp_manager.accept()
     }
 
-    pub fn write(&self, conn: &mut SharedConnection, data: &[u8]) {
+    pub fn read(&self, conn: &SharedConnection) -> Vec<u8> {
+        self.tcp_manager.read(conn.0.src_port, conn.0.dst_port)
+    }
+
+    pub fn write(&self, conn: &SharedConnection, data: &[u8]) {
         self.tcp_manager
-            .write(conn, HeaderFlags::PSH | HeaderFlags::ACK, data)
+            .write(conn.0.src_port, conn.0.dst_port, data)
+    }
+
+    pub fn flush(&self, conn: &SharedConnection) {
+        self.tcp_manager.flush(conn.0.src_port, conn.0.dst_port)
+    }
+
+    pub fn set_no_delay(&self, conn: &SharedConnection, no_delay: bool) {
+        self.tcp_manager
+            .set_no_delay(conn.0.src_port, conn.0.dst_port, no_delay)
+    }
+
+    pub fn close(&self, conn: &SharedConnection) {
+        self.tcp_manager.close(conn.0.src_port, conn.0.dst_port)
     }
 }
 
@@ -88,10 +88,12 @@ impl IpHeader {
         }
     }
 
+    #[allow(clippy::wrong_self_convention)]
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut bytes = Vec::new();
         let version_and_ihl = (self.version << 4) | self.ihl;
-        let flags_and_fragment_offset = ((self.flags as u16) << 13) | (self.fragment_offset & 0x1FFF);
+        let flags_and_fragment_offset =
+            ((self.flags as u16) << 13) | (self.fragment_offset & 0x1FFF);
         bytes.push(version_and_ihl);
         bytes.push(self.tos);
         bytes.extend(&self.total_length.to_be_bytes());
@@ -1,19 +1,29 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use tracing::info;
 
 mod http;
 mod ip;
 mod nic;
+mod pcap;
 mod tcp;
 
 fn main() {
     tracing_subscriber::fmt::init();
-    let args: Vec<String> = std::env::args().collect();
+    let mut args: Vec<String> = std::env::args().collect();
+    // --pcap <file> を指定すると、読み書きしたパケットを pcap 形式で保存する
+    let pcap = args.iter().position(|arg| arg == "--pcap").map(|i| {
+        let path = args
+            .get(i + 1)
+            .expect("--pcap requires a file path")
+            .clone();
+        args.drain(i..=i + 1);
+        pcap::PcapWriter::new(&path).expect("failed to create pcap file")
+    });
 
     match args[1].as_str() {
         "nic" => {
-            let nic = nic::NetDevice::new();
+            let nic = nic::NetDevice::new(pcap);
             nic.bind();
             loop {
                 let packet = nic.read();
@@ -21,7 +31,7 @@ fn main() {
             }
         }
         "ip" => {
-            let nic = Arc::new(nic::NetDevice::new());
+            let nic = Arc::new(nic::NetDevice::new(pcap));
             nic.bind();
             let ip = ip::IpPacketManager::new();
             ip.manage_queue(nic);
@@ -31,7 +41,7 @@ fn main() {
             }
         }
         "tcp" => {
-            let nic = Arc::new(nic::NetDevice::new());
+            let nic = Arc::new(nic::NetDevice::new(pcap));
             nic.bind();
             let ip = Arc::new(ip::IpPacketManager::new());
             ip.manage_queue(nic);
@@ -41,23 +51,65 @@ fn main() {
             loop {
                 let conn = tcp.accept();
                 info!("TCP connection: {:?}", conn);
+                // こちらから close して、CLOSED に遷移するまで待つ
+                tcp.close(conn.0.src_port, conn.0.dst_port);
+                loop {
+                    let event = tcp.recv_event();
+                    if (event.src_port, event.dst_port) == (conn.0.src_port, conn.0.dst_port) {
+                        info!(
+                            "TCP connection closed: {} ({:?})",
+                            event.src_port, event.kind
+                        );
+                        break;
+                    }
+                }
+            }
+        }
+        "keepalive" => {
+            let nic = Arc::new(nic::NetDevice::new(pcap));
+            nic.bind();
+            let ip = Arc::new(ip::IpPacketManager::new());
+            ip.manage_queue(nic);
+            let tcp = tcp::TcpPacketManager::new();
+            tcp.manage_queue(ip);
+            tcp.listen();
+            loop {
+                let conn = tcp.accept();
+                info!("TCP connection: {:?}", conn);
+                // 動作確認しやすいように、短い間隔でキープアライブプローブを送る
+                tcp.set_keepalive(
+                    conn.0.src_port,
+                    conn.0.dst_port,
+                    Duration::from_secs(10),
+                    Duration::from_secs(10),
+                    3,
+                );
             }
         }
         "http" => {
-            let server = http::Server::new();
+            // --no-delay を指定すると Nagle アルゴリズムを無効にする
+            let no_delay = args.get(2).is_some_and(|arg| arg == "--no-delay");
+            let server = http::Server::new(pcap);
             server.listen();
             loop {
-                let conn = &mut server.accept();
-                let req = http::HttpRequest::parse(&conn.1.payload());
+                let conn = &server.accept();
+                server.set_no_delay(conn, no_delay);
+                let data = server.read(conn);
+                // リクエストを送らずに閉じられた場合
+                if data.is_empty() {
+                    server.close(conn);
+                    continue;
+                }
+                let req = http::HttpRequest::parse(&data);
                 info!("request: {:?}", req);
-                match (req.method.as_str(), req.uri.as_str()) {
-                    ("GET", "/") => {
-                        let res_body = "Hello, World!\r\n";
-                        let res = http::HttpResponse::new(http::StatusCode::OK, res_body);
-                        server.write(conn, res.to_bytes().as_slice());
-                    }
-                    _ => (),
+                if let ("GET", "/") = (req.method.as_str(), req.uri.as_str()) {
+                    let res_body = "Hello, World!\r\n";
+                    let res = http::HttpResponse::new(http::StatusCode::OK, res_body);
+                    server.write(conn, res.to_bytes().as_slice());
+                    // レスポンスはこれで全部なので、ACK を待たずに送る
+                    server.flush(conn);
                 }
+                server.close(conn);
             }
         }
         _ => (),
@@ -1,8 +1,9 @@
+use crate::pcap::PcapWriter;
 use crossbeam_channel::{bounded, Receiver, Sender};
 use nix::unistd::{read, write};
 use std::fs::OpenOptions;
 use std::os::fd::AsFd;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::{fs::File, os::fd::AsRawFd};
 use tracing::info;
@@ -33,10 +34,12 @@ pub struct NetDevice {
     file: Arc<File>,
     incoming_queue: Arc<Channel>,
     outgoing_queue: Arc<Channel>,
+    // 読み書きしたパケットを保存する、None の場合は保存しない
+    pcap: Arc<Mutex<Option<PcapWriter>>>,
 }
 
 impl NetDevice {
-    pub fn new() -> NetDevice {
+    pub fn new(pcap: Option<PcapWriter>) -> NetDevice {
         let file = OpenOptions::new()
             .read(true)
             .write(true)
@@ -63,12 +66,14 @@ impl NetDevice {
             file: Arc::new(file),
             incoming_queue: Arc::new(bounded::<Packet>(10)),
             outgoing_queue: Arc::new(bounded::<Packet>(10)),
+            pcap: Arc::new(Mutex::new(pcap)),
         }
     }
 
     pub fn bind(&self) {
         let read_file = self.file.clone();
         let incoming_queue = self.incoming_queue.clone();
+        let read_pcap = self.pcap.clone();
         thread::spawn(move || loop {
             info!("read packet from TUN device...");
             let mut buffer = [0; 2048];
@@ -77,6 +82,7 @@ impl NetDevice {
             let packet = Packet {
                 data: buffer[..length].to_vec(),
             };
+            capture(&read_pcap, &packet);
             let (in_sender, _) = incoming_queue.as_ref();
             in_sender
                 .send(packet)
@@ -85,12 +91,14 @@ impl NetDevice {
 
         let write_file = self.file.clone();
         let outgoing_queue = self.outgoing_queue.clone();
+        let write_pcap = self.pcap.clone();
         thread::spawn(move || loop {
             let (_, out_receiver) = outgoing_queue.as_ref();
             let packet = out_receiver
                 .recv()
                 .expect("failed to receive packet in bind");
             info!("write packet to TUN device");
+            capture(&write_pcap, &packet);
             write(write_file.as_fd(), &packet.data).expect("failed to write to TUN device");
         });
     }
@@ -105,3 +113,11 @@ impl NetDevice {
         sender.send(packet).expect("failed to send packet");
     }
 }
+
+fn capture(pcap: &Mutex<Option<PcapWriter>>, packet: &Packet) {
+    if let Some(writer) = pcap.lock().unwrap().as_mut() {
+        writer
+            .write_packet(&packet.data)
+            .expect("failed to write packet to pcap file");
+    }
+}
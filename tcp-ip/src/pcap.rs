@@ -0,0 +1,60 @@
+use std::{
+    fs::File,
+    io::{self, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// pcap のファイルフォーマット
+// cf: https://wiki.wireshark.org/Development/LibpcapFileFormat
+const MAGIC_NUMBER: u32 = 0xa1b2c3d4;
+const VERSION_MAJOR: u16 = 2;
+const VERSION_MINOR: u16 = 4;
+// 1 パケットあたりに保存する最大のバイト数
+const SNAPLEN: u32 = 65535;
+// TUN デバイスでは Ethernet ヘッダーがなく IP パケットをそのまま読み書きするので、
+// Ethernet (1) ではなく Raw IP (101) を指定する
+// cf: https://www.tcpdump.org/linktypes.html
+const LINKTYPE_RAW: u32 = 101;
+
+// 読み書きしたパケットを Wireshark で開ける pcap 形式で保存する
+#[derive(Debug)]
+pub struct PcapWriter {
+    file: File,
+}
+
+impl PcapWriter {
+    pub fn new(path: &str) -> io::Result<PcapWriter> {
+        let mut file = File::create(path)?;
+        // グローバルヘッダー
+        let header = [
+            MAGIC_NUMBER.to_ne_bytes().as_slice(),
+            &VERSION_MAJOR.to_ne_bytes(),
+            &VERSION_MINOR.to_ne_bytes(),
+            &0i32.to_ne_bytes(), // thiszone: タイムスタンプは UTC なので 0
+            &0u32.to_ne_bytes(), // sigfigs: 実際には使われていないので 0
+            &SNAPLEN.to_ne_bytes(),
+            &LINKTYPE_RAW.to_ne_bytes(),
+        ]
+        .concat();
+        file.write_all(&header)?;
+        Ok(PcapWriter { file })
+    }
+
+    pub fn write_packet(&mut self, data: &[u8]) -> io::Result<()> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let incl_len = data.len().min(SNAPLEN as usize);
+        // パケットごとのヘッダー
+        let record = [
+            (timestamp.as_secs() as u32).to_ne_bytes().as_slice(),
+            &timestamp.subsec_micros().to_ne_bytes(),
+            &(incl_len as u32).to_ne_bytes(),
+            &(data.len() as u32).to_ne_bytes(),
+            &data[..incl_len],
+        ]
+        .concat();
+        // 途中で終了してもそれまでのパケットを読めるように、パケットごとにまとめて書き込む
+        self.file.write_all(&record)
+    }
+}
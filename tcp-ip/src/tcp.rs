@@ -3,15 +3,38 @@ use crate::{
     nic::Packet,
 };
 use bitflags::bitflags;
-use crossbeam_channel::{bounded, Receiver, Sender};
+use crossbeam_channel::{bounded, unbounded, Receiver, Sender};
 use std::{
+    collections::VecDeque,
     sync::{Arc, Mutex},
     thread,
+    time::{Duration, Instant},
 };
 use tracing::info;
 
 // 基本は 20 byte だが、オプションフィールドがある場合はそれが追加される
 const TCP_HEADER_LENGTH: usize = 20;
+// Maximum Segment Size
+// MTU (1500) から IP ヘッダーと TCP ヘッダーの分を引いた値
+const MSS: usize = 1460;
+// Maximum Segment Lifetime
+// RFC では 2 分とされているが、動作確認しやすいように短くしている
+const MSL: Duration = Duration::from_secs(1);
+// ACK が返ってこないセグメントを再送するまでの時間
+const RETRANSMISSION_TIMEOUT: Duration = Duration::from_secs(3);
+// 再送が必要なセグメントを探す間隔
+const RETRANSMISSION_INTERVAL: Duration = Duration::from_millis(100);
+// これを超えて再送しても ACK が返ってこない場合は RST を送ってコネクションを破棄する
+const MAX_RETRANSMISSION: usize = 5;
+// キープアライブの設定値の初期値、いずれも Linux のデフォルト値と同じ
+// 最後にパケットを受け取ってからこの時間が経つと、キープアライブプローブを送り始める
+const KEEPALIVE_IDLE: Duration = Duration::from_secs(2 * 60 * 60);
+// プローブに応答がない場合に、次のプローブを送るまでの時間
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(75);
+// この回数プローブを送っても応答がない場合は、コネクションを閉じる
+const KEEPALIVE_COUNT: u8 = 9;
+// キープアライブプローブを送る必要があるコネクションを探す間隔
+const KEEPALIVE_SCAN_INTERVAL: Duration = Duration::from_secs(60);
 
 // TCP のヘッダーフォーマット
 // cf: https://datatracker.ietf.org/doc/html/rfc9293#name-header-format
@@ -108,7 +131,7 @@ impl TcpHeader {
         }
     }
 
-    fn to_bytes(&self, ip_header: &IpHeader, data: &[u8]) -> Vec<u8> {
+    fn to_bytes(self, ip_header: &IpHeader, data: &[u8]) -> Vec<u8> {
         let mut bytes = Vec::new();
         bytes.extend(&self.src_port.to_be_bytes());
         bytes.extend(&self.dst_port.to_be_bytes());
@@ -216,12 +239,61 @@ impl TcpPacket {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+// 送信したが ACK をまだ受け取っていないセグメント
+#[derive(Debug, Clone)]
+struct SentSegment {
+    seq_num: u32,
+    flag: HeaderFlags,
+    data: Vec<u8>,
+    sent_at: Instant,
+    retransmissions: usize,
+}
+
+impl SentSegment {
+    // このセグメントが ACK されたときに相手から返ってくる確認応答番号
+    fn end_seq_num(&self) -> u32 {
+        self.seq_num
+            .wrapping_add(segment_length(self.flag, &self.data))
+    }
+}
+
+// シーケンス番号をいくつ消費するか、SYN と FIN は 1 つ分として数える
+fn segment_length(flag: HeaderFlags, data: &[u8]) -> u32 {
+    (if flag.contains(HeaderFlags::SYN) || flag.contains(HeaderFlags::FIN) {
+        1
+    } else {
+        data.len()
+    }) as u32
+}
+
+#[derive(Debug, Clone)]
 pub struct Connection {
-    src_port: u16,
-    dst_port: u16,
+    pub src_port: u16,
+    pub dst_port: u16,
     state: ConnectionState,
     next_seq_num: u32,
+    // 受信したデータを read に渡すためのキュー、相手が FIN を送ってきたら空のデータを送る
+    // read を呼ばない間もパケットの処理が止まらないように上限は設けない
+    received_data: (Sender<Vec<u8>>, Receiver<Vec<u8>>),
+    // 再送用のバッファ、connections の Mutex 越しにだけ触る
+    send_buffer: VecDeque<SentSegment>,
+    // Nagle アルゴリズムによって送信を遅らせているデータ
+    pending_data: Vec<u8>,
+    // true の場合は Nagle アルゴリズムを無効にして、write されたデータをすぐに送る (TCP_NODELAY 相当)
+    pub no_delay: bool,
+    // 最後にデータもしくは ACK を受け取った時刻
+    last_activity: Instant,
+    keepalive: KeepAlive,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct KeepAlive {
+    idle: Duration,
+    interval: Duration,
+    count: u8,
+    // 応答がないまま送ったプローブの数と、最後にプローブを送った時刻
+    probes: u8,
+    probed_at: Instant,
 }
 
 impl Connection {
@@ -231,8 +303,38 @@ impl Connection {
             dst_port,
             state: ConnectionState::Listen,
             next_seq_num: 0,
+            received_data: unbounded::<Vec<u8>>(),
+            send_buffer: VecDeque::new(),
+            pending_data: Vec::new(),
+            no_delay: false,
+            last_activity: Instant::now(),
+            keepalive: KeepAlive {
+                idle: KEEPALIVE_IDLE,
+                interval: KEEPALIVE_INTERVAL,
+                count: KEEPALIVE_COUNT,
+                probes: 0,
+                probed_at: Instant::now(),
+            },
         }
     }
+
+    // 確認応答番号までのセグメントは相手に届いたので、再送用のバッファから取り除く
+    fn acknowledge(&mut self, ack_num: u32) {
+        self.send_buffer
+            .retain(|segment| segment.end_seq_num().wrapping_sub(ack_num) as i32 > 0);
+    }
+
+    // 送信済みのデータに対する ACK がすべて返ってきているか
+    fn all_acknowledged(&self) -> bool {
+        self.send_buffer.is_empty()
+    }
+
+    fn push_received_data(&self, data: &[u8]) {
+        let (sender, _) = &self.received_data;
+        sender
+            .send(data.to_vec())
+            .expect("failed to send received data in push_received_data");
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -240,6 +342,10 @@ enum ConnectionState {
     Listen,
     SynReceived,
     Established,
+    FinWait1,
+    FinWait2,
+    Closing,
+    TimeWait,
     CloseWait,
     LastAck,
     Closed,
@@ -247,16 +353,50 @@ enum ConnectionState {
 
 pub type SharedConnection = Box<(Connection, TcpPacket)>;
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TcpError {
+    MaxRetransmissionExceeded,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TcpEventKind {
+    // TIME-WAIT もしくは LAST-ACK を経て CLOSED に遷移した
+    ConnectionClosed,
+    // エラーによって RST を送り、コネクションを破棄した
+    ConnectionAborted(TcpError),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TcpEvent {
+    pub src_port: u16,
+    pub dst_port: u16,
+    pub kind: TcpEventKind,
+}
+
+// イベントを読む側がいなくてもパケットの処理が止まらないように、キューが一杯のときは捨てる
+fn publish_event(events: &Sender<TcpEvent>, connection: &Connection, kind: TcpEventKind) {
+    let event = TcpEvent {
+        src_port: connection.src_port,
+        dst_port: connection.dst_port,
+        kind,
+    };
+    if events.try_send(event).is_err() {
+        info!("event queue is full, dropped: {:?}", event);
+    }
+}
+
 struct ConnectionManager {
-    connections: Mutex<Vec<SharedConnection>>,
+    connections: Arc<Mutex<Vec<SharedConnection>>>,
     accpted_connections: (Sender<SharedConnection>, Receiver<SharedConnection>),
+    events: (Sender<TcpEvent>, Receiver<TcpEvent>),
 }
 
 impl ConnectionManager {
     pub fn new() -> ConnectionManager {
         ConnectionManager {
-            connections: Mutex::new(Vec::new()),
+            connections: Arc::new(Mutex::new(Vec::new())),
             accpted_connections: bounded::<SharedConnection>(10),
+            events: bounded::<TcpEvent>(10),
         }
     }
 
@@ -285,7 +425,7 @@ impl ConnectionManager {
             )));
         }
         // コネクションを取得
-        let mut shared_connection = connections
+        let shared_connection = connections
             .iter_mut()
             .find(|e| {
                 e.0.src_port == incoming_packet.tcp_header.src_port
@@ -293,31 +433,41 @@ impl ConnectionManager {
             })
             .expect("failed to find connection");
         // 受信したパケットをコネクションに紐づける
-        (*shared_connection).1 = incoming_packet.clone();
+        shared_connection.1 = incoming_packet.clone();
 
         let flag = shared_connection.1.tcp_header.flag;
         let state = shared_connection.0.state;
         info!("connection state: {:?}, flag: {:?}", state, flag);
         let (sender, _) = outgoing_queue;
+        if flag.contains(HeaderFlags::ACK) || !incoming_packet.payload().is_empty() {
+            // 相手からの応答があったので、キープアライブのプローブ数をリセットする
+            shared_connection.0.last_activity = Instant::now();
+            shared_connection.0.keepalive.probes = 0;
+        }
+        if flag.contains(HeaderFlags::ACK) {
+            shared_connection
+                .0
+                .acknowledge(incoming_packet.tcp_header.ack_num);
+            // ACK が返ってきたので、遅らせていたデータを送れるかもしれない
+            if state == ConnectionState::Established || state == ConnectionState::CloseWait {
+                self.send_pending_data(sender, shared_connection, false);
+            }
+        }
         match flag {
             _ if flag.contains(HeaderFlags::SYN) && state == ConnectionState::Listen => {
                 info!("received SYN packet...");
                 self.send_packet(
                     sender,
-                    &mut shared_connection,
+                    shared_connection,
                     HeaderFlags::SYN | HeaderFlags::ACK,
                     &[],
                 );
                 // update connection
-                (*shared_connection).0.state = ConnectionState::SynReceived;
+                shared_connection.0.state = ConnectionState::SynReceived;
             }
             _ if flag.contains(HeaderFlags::ACK) && state == ConnectionState::SynReceived => {
                 info!("received ACK packet...");
-                (*shared_connection).0.state = ConnectionState::Established;
-            }
-            _ if flag.contains(HeaderFlags::PSH) && state == ConnectionState::Established => {
-                info!("received PSH packet...");
-                self.send_packet(sender, &mut shared_connection, HeaderFlags::ACK, &[]);
+                shared_connection.0.state = ConnectionState::Established;
 
                 // コネクションを send する
                 let (sender, _) = &self.accpted_connections;
@@ -325,25 +475,82 @@ impl ConnectionManager {
                     .send(shared_connection.to_owned())
                     .expect("failed to send connection in passive_handler");
             }
+            _ if !flag.contains(HeaderFlags::FIN)
+                && !incoming_packet.payload().is_empty()
+                && state == ConnectionState::Established =>
+            {
+                info!("received PSH packet...");
+                self.send_packet(sender, shared_connection, HeaderFlags::ACK, &[]);
+                shared_connection
+                    .0
+                    .push_received_data(incoming_packet.payload());
+            }
             _ if flag.contains(HeaderFlags::FIN) && state == ConnectionState::Established => {
                 info!("received FIN packet...");
-                self.send_packet(sender, &mut shared_connection, HeaderFlags::ACK, &[]);
-                (*shared_connection).0.state = ConnectionState::CloseWait;
+                // FIN と一緒に届いたデータを渡してから、空のデータで終端を知らせる
+                if !incoming_packet.payload().is_empty() {
+                    shared_connection
+                        .0
+                        .push_received_data(incoming_packet.payload());
+                }
+                shared_connection.0.push_received_data(&[]);
+                self.send_packet(sender, shared_connection, HeaderFlags::ACK, &[]);
+                shared_connection.0.state = ConnectionState::CloseWait;
+
+                // FIN を送る前に、遅らせていたデータを送り切る
+                self.send_pending_data(sender, shared_connection, true);
 
                 // RFC を読むと FIN パケットを送るように書いてあるが、FIN/ACK を送ることが想定されているらしい
                 // cf: https://kawasin73.hatenablog.com/entry/2019/08/31/153809
                 self.send_packet(
                     sender,
-                    &mut shared_connection,
+                    shared_connection,
                     HeaderFlags::FIN | HeaderFlags::ACK,
                     &[],
                 );
-                (*shared_connection).0.state = ConnectionState::LastAck;
+                shared_connection.0.state = ConnectionState::LastAck;
             }
             _ if flag.contains(HeaderFlags::ACK) && state == ConnectionState::LastAck => {
                 info!("received ACK packet...");
-                (*shared_connection).0.state = ConnectionState::Closed;
+                shared_connection.0.state = ConnectionState::Closed;
                 info!("connection closed");
+                publish_event(
+                    &self.events.0,
+                    &shared_connection.0,
+                    TcpEventKind::ConnectionClosed,
+                );
+            }
+            _ if flag.contains(HeaderFlags::FIN) && state == ConnectionState::FinWait1 => {
+                info!("received FIN packet...");
+                // 自分が送った FIN に対する ACK も一緒に届いた場合は、FIN-WAIT-2 を経由せずに TIME-WAIT に遷移する
+                let fin_acked = flag.contains(HeaderFlags::ACK)
+                    && shared_connection.1.tcp_header.ack_num == shared_connection.0.next_seq_num;
+                self.send_packet(sender, shared_connection, HeaderFlags::ACK, &[]);
+                if fin_acked {
+                    shared_connection.0.state = ConnectionState::TimeWait;
+                    self.start_time_wait_timer(&shared_connection.0);
+                } else {
+                    // 同時クローズ: 相手の FIN を先に受け取ったので、自分の FIN に対する ACK を CLOSING で待つ
+                    shared_connection.0.state = ConnectionState::Closing;
+                }
+            }
+            _ if flag.contains(HeaderFlags::ACK)
+                && state == ConnectionState::FinWait1
+                && shared_connection.1.tcp_header.ack_num == shared_connection.0.next_seq_num =>
+            {
+                info!("received ACK packet...");
+                shared_connection.0.state = ConnectionState::FinWait2;
+            }
+            _ if flag.contains(HeaderFlags::FIN) && state == ConnectionState::FinWait2 => {
+                info!("received FIN packet...");
+                self.send_packet(sender, shared_connection, HeaderFlags::ACK, &[]);
+                shared_connection.0.state = ConnectionState::TimeWait;
+                self.start_time_wait_timer(&shared_connection.0);
+            }
+            _ if flag.contains(HeaderFlags::ACK) && state == ConnectionState::Closing => {
+                info!("received ACK packet...");
+                shared_connection.0.state = ConnectionState::TimeWait;
+                self.start_time_wait_timer(&shared_connection.0);
             }
             _ => {
                 // fall through
@@ -351,12 +558,234 @@ impl ConnectionManager {
         }
     }
 
+    // TCP active close の流れを実装
+    pub fn active_close(&self, outgoing_queue: &Channel, src_port: u16, dst_port: u16) {
+        let mut connections = self.connections.lock().unwrap();
+        let Some(shared_connection) = connections
+            .iter_mut()
+            .find(|e| e.0.src_port == src_port && e.0.dst_port == dst_port)
+        else {
+            info!("connection not found: {}:{}", src_port, dst_port);
+            return;
+        };
+        if shared_connection.0.state != ConnectionState::Established {
+            info!(
+                "cannot close connection in state: {:?}",
+                shared_connection.0.state
+            );
+            return;
+        }
+
+        let (sender, _) = outgoing_queue;
+        // FIN を送る前に、遅らせていたデータを送り切る
+        self.send_pending_data(sender, shared_connection, true);
+        self.send_packet(
+            sender,
+            shared_connection,
+            HeaderFlags::FIN | HeaderFlags::ACK,
+            &[],
+        );
+        shared_connection.0.state = ConnectionState::FinWait1;
+    }
+
+    // Nagle アルゴリズム
+    // MSS に達したデータはすぐに送り、MSS に満たないデータは送信済みのデータがすべて ACK されるまで送らない
+    // force が true の場合や no_delay が設定されている場合は、MSS に満たないデータもすぐに送る
+    fn send_pending_data(
+        &self,
+        packet_sender: &Sender<TcpPacket>,
+        shared_connection: &mut SharedConnection,
+        force: bool,
+    ) {
+        while !shared_connection.0.pending_data.is_empty() {
+            let connection = &shared_connection.0;
+            if connection.pending_data.len() < MSS
+                && !(force || connection.no_delay || connection.all_acknowledged())
+            {
+                break;
+            }
+            let length = connection.pending_data.len().min(MSS);
+            let data = shared_connection
+                .0
+                .pending_data
+                .drain(..length)
+                .collect::<Vec<_>>();
+            self.send_packet(
+                packet_sender,
+                shared_connection,
+                HeaderFlags::PSH | HeaderFlags::ACK,
+                &data,
+            );
+        }
+    }
+
+    // TIME-WAIT で 2MSL 待ってから CLOSED に遷移し、コネクションを削除する
+    fn start_time_wait_timer(&self, connection: &Connection) {
+        let connections = self.connections.clone();
+        let events = self.events.0.clone();
+        let (src_port, dst_port) = (connection.src_port, connection.dst_port);
+        thread::spawn(move || {
+            thread::sleep(MSL * 2);
+            let mut connections = connections.lock().unwrap();
+            for shared_connection in connections.iter_mut() {
+                if shared_connection.0.src_port == src_port
+                    && shared_connection.0.dst_port == dst_port
+                    && shared_connection.0.state == ConnectionState::TimeWait
+                {
+                    shared_connection.0.state = ConnectionState::Closed;
+                    info!("connection closed");
+                    publish_event(
+                        &events,
+                        &shared_connection.0,
+                        TcpEventKind::ConnectionClosed,
+                    );
+                }
+            }
+            connections.retain(|e| e.0.state != ConnectionState::Closed);
+        });
+    }
+
+    // RETRANSMISSION_TIMEOUT を過ぎても ACK が返ってこないセグメントを再送する
+    pub fn retransmission_handler(&self, outgoing_queue: &Channel) {
+        thread::sleep(RETRANSMISSION_INTERVAL);
+
+        let mut connections = self.connections.lock().unwrap();
+        let (sender, _) = outgoing_queue;
+        for shared_connection in connections.iter_mut() {
+            if let Err(error) = self.retransmit(sender, shared_connection) {
+                info!(
+                    "abort connection {}:{}: {:?}",
+                    shared_connection.0.src_port, shared_connection.0.dst_port, error
+                );
+                let seq_num = shared_connection.0.next_seq_num;
+                self.send_segment(sender, shared_connection, seq_num, HeaderFlags::RST, &[]);
+                shared_connection.0.state = ConnectionState::Closed;
+                shared_connection.0.send_buffer.clear();
+                shared_connection.0.pending_data.clear();
+                // read で待っている場合に備えて、終端を知らせる
+                shared_connection.0.push_received_data(&[]);
+                publish_event(
+                    &self.events.0,
+                    &shared_connection.0,
+                    TcpEventKind::ConnectionAborted(error),
+                );
+            }
+        }
+        connections.retain(|e| e.0.state != ConnectionState::Closed);
+    }
+
+    fn retransmit(
+        &self,
+        packet_sender: &Sender<TcpPacket>,
+        shared_connection: &mut SharedConnection,
+    ) -> Result<(), TcpError> {
+        for i in 0..shared_connection.0.send_buffer.len() {
+            let segment = &shared_connection.0.send_buffer[i];
+            if segment.sent_at.elapsed() < RETRANSMISSION_TIMEOUT {
+                continue;
+            }
+            if segment.retransmissions >= MAX_RETRANSMISSION {
+                return Err(TcpError::MaxRetransmissionExceeded);
+            }
+
+            info!("retransmit segment: {}", segment.seq_num);
+            let (seq_num, flag, data) = (segment.seq_num, segment.flag, segment.data.clone());
+            self.send_segment(packet_sender, shared_connection, seq_num, flag, &data);
+            let segment = &mut shared_connection.0.send_buffer[i];
+            segment.sent_at = Instant::now();
+            segment.retransmissions += 1;
+        }
+        Ok(())
+    }
+
+    // 一定時間パケットを受け取っていないコネクションにキープアライブプローブを送る
+    // 決められた回数プローブを送っても応答がない場合は、コネクションを閉じる
+    pub fn keepalive_handler(&self, outgoing_queue: &Channel) {
+        thread::sleep(KEEPALIVE_SCAN_INTERVAL);
+
+        let mut connections = self.connections.lock().unwrap();
+        let (sender, _) = outgoing_queue;
+        for shared_connection in connections.iter_mut() {
+            let connection = &shared_connection.0;
+            if connection.state != ConnectionState::Established {
+                continue;
+            }
+            let keepalive = connection.keepalive;
+            if keepalive.probes == 0 {
+                if connection.last_activity.elapsed() < keepalive.idle {
+                    continue;
+                }
+            } else if keepalive.probed_at.elapsed() < keepalive.interval {
+                continue;
+            }
+
+            if keepalive.probes >= keepalive.count {
+                info!(
+                    "keep-alive timeout {}:{}",
+                    connection.src_port, connection.dst_port
+                );
+                shared_connection.0.state = ConnectionState::Closed;
+                shared_connection.0.send_buffer.clear();
+                shared_connection.0.pending_data.clear();
+                // read で待っている場合に備えて、終端を知らせる
+                shared_connection.0.push_received_data(&[]);
+                publish_event(
+                    &self.events.0,
+                    &shared_connection.0,
+                    TcpEventKind::ConnectionClosed,
+                );
+                continue;
+            }
+
+            // キープアライブプローブは、相手が既に受け取ったシーケンス番号を持つデータなしの ACK
+            // 相手はこれを受け取ると、確認応答番号を訂正するために ACK を返してくる
+            info!("send keep-alive probe: {}", keepalive.probes + 1);
+            let seq_num = connection.next_seq_num.wrapping_sub(1);
+            self.send_segment(sender, shared_connection, seq_num, HeaderFlags::ACK, &[]);
+            shared_connection.0.keepalive.probes += 1;
+            shared_connection.0.keepalive.probed_at = Instant::now();
+        }
+        connections.retain(|e| e.0.state != ConnectionState::Closed);
+    }
+
     pub fn send_packet(
         &self,
         packet_sender: &Sender<TcpPacket>,
         shared_connection: &mut SharedConnection,
         outgoing_packet_flag: HeaderFlags,
         outgoing_packet_data: &[u8],
+    ) {
+        let seq_num = shared_connection.0.next_seq_num;
+        self.send_segment(
+            packet_sender,
+            shared_connection,
+            seq_num,
+            outgoing_packet_flag,
+            outgoing_packet_data,
+        );
+
+        // 次のシーケンス番号を計算する
+        // シーケンス番号を消費するセグメントは、ACK が返ってくるまで再送できるように残しておく
+        let increment_seq_num = segment_length(outgoing_packet_flag, outgoing_packet_data);
+        if increment_seq_num > 0 {
+            shared_connection.0.send_buffer.push_back(SentSegment {
+                seq_num,
+                flag: outgoing_packet_flag,
+                data: outgoing_packet_data.to_vec(),
+                sent_at: Instant::now(),
+                retransmissions: 0,
+            });
+        }
+        shared_connection.0.next_seq_num += increment_seq_num;
+    }
+
+    fn send_segment(
+        &self,
+        packet_sender: &Sender<TcpPacket>,
+        shared_connection: &SharedConnection,
+        seq_num: u32,
+        outgoing_packet_flag: HeaderFlags,
+        outgoing_packet_data: &[u8],
     ) {
         let tcp_packet = &shared_connection.1;
         // IP ヘッダーの生成
@@ -368,20 +797,16 @@ impl ConnectionManager {
         );
 
         let incoming_tcp_header = tcp_packet.tcp_header;
-        let increment_ack_num = (if incoming_tcp_header.flag.contains(HeaderFlags::SYN)
-            || incoming_tcp_header.flag.contains(HeaderFlags::FIN)
-        {
-            1
-        } else {
-            tcp_packet.packet.data.len()
-                - (incoming_ip_header.ihl * 4) as usize
-                - (incoming_tcp_header.data_offset * 4) as usize
-        }) as u32;
+        // SYN と FIN はデータと別に 1 つ分のシーケンス番号を使うので、
+        // データ付きの FIN にはデータ長 + 1 を ACK する
+        let syn_or_fin = incoming_tcp_header.flag.contains(HeaderFlags::SYN)
+            || incoming_tcp_header.flag.contains(HeaderFlags::FIN);
+        let increment_ack_num = (tcp_packet.payload().len() + syn_or_fin as usize) as u32;
         // TCP ヘッダーの生成
         let tcp_header = TcpHeader::new(
             incoming_tcp_header.dst_port,
             incoming_tcp_header.src_port,
-            shared_connection.0.next_seq_num,
+            seq_num,
             incoming_tcp_header.seq_num + increment_ack_num,
             outgoing_packet_flag,
         );
@@ -407,17 +832,7 @@ impl ConnectionManager {
         info!("send packet: {:?}", tcp_packet);
         packet_sender
             .send(tcp_packet)
-            .expect("failed to send tcp packet in send_packet");
-
-        // 次のシーケンス番号を計算する
-        let increment_seq_num = (if outgoing_packet_flag.contains(HeaderFlags::SYN)
-            || outgoing_packet_flag.contains(HeaderFlags::FIN)
-        {
-            1
-        } else {
-            outgoing_packet_data.len()
-        }) as u32;
-        (*shared_connection).0.next_seq_num += increment_seq_num;
+            .expect("failed to send tcp packet in send_segment");
     }
 }
 
@@ -475,9 +890,19 @@ impl TcpPacketManager {
         let connection_manager = self.connection_manager.clone();
         let incoming_queue = self.incoming_queue.clone();
         let outgoing_queue = self.outgoing_queue.clone();
+        let retransmission_manager = connection_manager.clone();
+        let retransmission_queue = outgoing_queue.clone();
+        let keepalive_manager = connection_manager.clone();
+        let keepalive_queue = outgoing_queue.clone();
         thread::spawn(move || loop {
             connection_manager.passive_handler(incoming_queue.as_ref(), outgoing_queue.as_ref());
         });
+        thread::spawn(move || loop {
+            retransmission_manager.retransmission_handler(retransmission_queue.as_ref());
+        });
+        thread::spawn(move || loop {
+            keepalive_manager.keepalive_handler(keepalive_queue.as_ref());
+        });
     }
 
     pub fn accept(&self) -> SharedConnection {
@@ -487,9 +912,112 @@ impl TcpPacketManager {
             .expect("failed to receive connection in accept")
     }
 
-    pub fn write(&self, connection: &mut SharedConnection, flag: HeaderFlags, data: &[u8]) {
+    // データを MSS ごとに分割して送る
+    // Nagle アルゴリズムにより、MSS に満たない分は送信済みのデータが ACK されるまでバッファに貯めておく
+    pub fn write(&self, src_port: u16, dst_port: u16, data: &[u8]) {
+        self.with_writable_connection(src_port, dst_port, |shared_connection| {
+            shared_connection.0.pending_data.extend_from_slice(data);
+            false
+        });
+    }
+
+    // バッファに貯めているデータを、ACK を待たずにすぐに送る
+    pub fn flush(&self, src_port: u16, dst_port: u16) {
+        self.with_writable_connection(src_port, dst_port, |_| true);
+    }
+
+    // Nagle アルゴリズムを無効にするかどうかを設定する
+    pub fn set_no_delay(&self, src_port: u16, dst_port: u16, no_delay: bool) {
+        self.with_writable_connection(src_port, dst_port, |shared_connection| {
+            shared_connection.0.no_delay = no_delay;
+            false
+        });
+    }
+
+    // 最後にパケットを受け取ってから idle 経つとキープアライブプローブを送り始め、
+    // interval ごとに count 回送っても応答がない場合はコネクションを閉じる
+    pub fn set_keepalive(
+        &self,
+        src_port: u16,
+        dst_port: u16,
+        idle: Duration,
+        interval: Duration,
+        count: u8,
+    ) {
+        let mut connections = self.connection_manager.connections.lock().unwrap();
+        let Some(shared_connection) = connections
+            .iter_mut()
+            .find(|e| e.0.src_port == src_port && e.0.dst_port == dst_port)
+        else {
+            info!("connection not found: {}:{}", src_port, dst_port);
+            return;
+        };
+        let keepalive = &mut shared_connection.0.keepalive;
+        keepalive.idle = idle;
+        keepalive.interval = interval;
+        keepalive.count = count;
+    }
+
+    // データを送れる状態のコネクションに対して f を呼び、その後バッファに貯めているデータを送る
+    // f が true を返した場合は Nagle アルゴリズムによらずすべて送る
+    fn with_writable_connection(
+        &self,
+        src_port: u16,
+        dst_port: u16,
+        f: impl FnOnce(&mut SharedConnection) -> bool,
+    ) {
         let connection_manager = self.connection_manager.as_ref();
+        let mut connections = connection_manager.connections.lock().unwrap();
+        let Some(shared_connection) = connections
+            .iter_mut()
+            .find(|e| e.0.src_port == src_port && e.0.dst_port == dst_port)
+        else {
+            info!("connection not found: {}:{}", src_port, dst_port);
+            return;
+        };
+        if shared_connection.0.state != ConnectionState::Established
+            && shared_connection.0.state != ConnectionState::CloseWait
+        {
+            info!(
+                "cannot write to connection in state: {:?}",
+                shared_connection.0.state
+            );
+            return;
+        }
+
+        let force = f(shared_connection);
         let (sender, _) = self.outgoing_queue.as_ref();
-        connection_manager.send_packet(sender, connection, flag, data);
+        connection_manager.send_pending_data(sender, shared_connection, force);
+    }
+
+    // 相手からデータが届くまでブロックする
+    // 相手が FIN を送ってきた後やコネクションが存在しない場合は空のデータを返す
+    pub fn read(&self, src_port: u16, dst_port: u16) -> Vec<u8> {
+        let receiver = {
+            let connections = self.connection_manager.connections.lock().unwrap();
+            let Some(shared_connection) = connections
+                .iter()
+                .find(|e| e.0.src_port == src_port && e.0.dst_port == dst_port)
+            else {
+                info!("connection not found: {}:{}", src_port, dst_port);
+                return Vec::new();
+            };
+            shared_connection.0.received_data.1.clone()
+        };
+        // 送信側はアプリケーションが持っている SharedConnection にも残っているので、
+        // コネクションを削除しただけでは recv は返らない。閉じる時は必ず空のデータで終端を知らせる
+        receiver.recv().unwrap_or_default()
+    }
+
+    pub fn close(&self, src_port: u16, dst_port: u16) {
+        self.connection_manager
+            .active_close(self.outgoing_queue.as_ref(), src_port, dst_port);
+    }
+
+    pub fn recv_event(&self) -> TcpEvent {
+        let (_, receiver) = &self.connection_manager.events;
+        receiver
+            .recv()
+            .expect("failed to receive event in recv_event")
     }
 }
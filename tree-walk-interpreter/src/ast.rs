@@ -6,6 +6,8 @@ pub enum Expr {
     Unary(Box<UnaryExpr>),
     Binary(Box<BinaryExpr>),
     Grouping(Box<GroupingExpr>),
+    Array(Box<ArrayExpr>),
+    Index(Box<IndexExpr>),
 }
 
 #[derive(Debug)]
@@ -58,3 +60,31 @@ impl GroupingExpr {
         GroupingExpr { expression }
     }
 }
+
+#[derive(Debug)]
+pub struct ArrayExpr {
+    pub elements: Vec<Expr>,
+}
+
+impl ArrayExpr {
+    pub fn new(elements: Vec<Expr>) -> Self {
+        ArrayExpr { elements }
+    }
+}
+
+#[derive(Debug)]
+pub struct IndexExpr {
+    pub object: Expr,
+    pub bracket: Token,
+    pub index: Expr,
+}
+
+impl IndexExpr {
+    pub fn new(object: Expr, bracket: Token, index: Expr) -> Self {
+        IndexExpr {
+            object,
+            bracket,
+            index,
+        }
+    }
+}
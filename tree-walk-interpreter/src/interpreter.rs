@@ -1,5 +1,7 @@
+use std::{cell::RefCell, rc::Rc};
+
 use crate::{
-    ast::{BinaryExpr, Expr, GroupingExpr, LiteralExpr, UnaryExpr},
+    ast::{ArrayExpr, BinaryExpr, Expr, GroupingExpr, IndexExpr, LiteralExpr, UnaryExpr},
     lexer::TokenType,
 };
 
@@ -9,12 +11,14 @@ pub enum RuntimeError {
     UnexpectedOperator,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Value {
     String(String),
     Number(f64),
     Null,
     Boolean(bool),
+    // arrays are shared by reference, like instances in Lox
+    Array(Rc<RefCell<Vec<Value>>>),
 }
 
 pub struct Interpreter {}
@@ -40,7 +44,7 @@ impl Interpreter {
     }
 
     fn visit_grouping(&self, grouping: GroupingExpr) -> Result<Value, RuntimeError> {
-        return self.evaluate(grouping.expression);
+        self.evaluate(grouping.expression)
     }
 
     fn visit_unary(&self, unary: UnaryExpr) -> Result<Value, RuntimeError> {
@@ -72,6 +76,11 @@ impl Interpreter {
                 TokenType::MINUS => Ok(Value::Number(left - right)),
                 TokenType::STAR => Ok(Value::Number(left * right)),
                 TokenType::SLASH => Ok(Value::Number(left / right)),
+                TokenType::PERCENT if right == 0.0 => {
+                    tracing::error!("[line {}] Modulo by zero", binary.operator.line);
+                    Err(RuntimeError::UnexpectedValue)
+                }
+                TokenType::PERCENT => Ok(Value::Number(left % right)),
                 TokenType::GREATER => Ok(Value::Boolean(left > right)),
                 TokenType::GREATER_EQUAL => Ok(Value::Boolean(left >= right)),
                 TokenType::LESS => Ok(Value::Boolean(left < right)),
@@ -93,12 +102,51 @@ impl Interpreter {
         }
     }
 
+    fn visit_array(&self, array: ArrayExpr) -> Result<Value, RuntimeError> {
+        let elements = array
+            .elements
+            .into_iter()
+            .map(|element| self.evaluate(element))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Value::Array(Rc::new(RefCell::new(elements))))
+    }
+
+    fn visit_index(&self, index: IndexExpr) -> Result<Value, RuntimeError> {
+        let object = self.evaluate(index.object)?;
+        let position = self.evaluate(index.index)?;
+        let Value::Array(elements) = object else {
+            tracing::error!("[line {}] Only arrays can be indexed", index.bracket.line);
+            return Err(RuntimeError::UnexpectedValue);
+        };
+        let elements = elements.borrow();
+        match position {
+            Value::Number(i) if i >= 0.0 && i.fract() == 0.0 && (i as usize) < elements.len() => {
+                Ok(elements[i as usize].clone())
+            }
+            Value::Number(i) => {
+                tracing::error!(
+                    "[line {}] Index {} out of bounds for array of length {}",
+                    index.bracket.line,
+                    i,
+                    elements.len()
+                );
+                Err(RuntimeError::UnexpectedValue)
+            }
+            _ => {
+                tracing::error!("[line {}] Array index must be a number", index.bracket.line);
+                Err(RuntimeError::UnexpectedValue)
+            }
+        }
+    }
+
     fn evaluate(&self, expression: Expr) -> Result<Value, RuntimeError> {
         match expression {
             Expr::Literal(literal) => self.visit_literal(*literal),
             Expr::Grouping(grouping) => self.visit_grouping(*grouping),
             Expr::Unary(unary) => self.visit_unary(*unary),
             Expr::Binary(binary) => self.visit_binary(*binary),
+            Expr::Array(array) => self.visit_array(*array),
+            Expr::Index(index) => self.visit_index(*index),
         }
     }
 }
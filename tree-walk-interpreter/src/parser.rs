@@ -1,5 +1,5 @@
 use crate::{
-    ast::{BinaryExpr, Expr, GroupingExpr, LiteralExpr, UnaryExpr},
+    ast::{ArrayExpr, BinaryExpr, Expr, GroupingExpr, IndexExpr, LiteralExpr, UnaryExpr},
     lexer::{Token, TokenType},
 };
 
@@ -71,7 +71,10 @@ impl Parser {
     fn factor(&mut self) -> Result<Expr, ParseError> {
         let mut expr = self.unary()?;
 
-        while matches!(self.peek().r#type, TokenType::SLASH | TokenType::STAR) {
+        while matches!(
+            self.peek().r#type,
+            TokenType::SLASH | TokenType::STAR | TokenType::PERCENT
+        ) {
             let operator = self.advance();
             let right = self.unary()?;
             expr = Expr::Binary(Box::new(BinaryExpr::new(expr, operator.clone(), right)));
@@ -87,7 +90,20 @@ impl Parser {
             return Ok(Expr::Unary(Box::new(UnaryExpr::new(operator, right))));
         }
 
-        self.primary()
+        self.index()
+    }
+
+    fn index(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.primary()?;
+
+        while matches!(self.peek().r#type, TokenType::LEFT_BRACKET) {
+            let bracket = self.advance();
+            let index = self.expression()?;
+            self.consume(TokenType::RIGHT_BRACKET)?;
+            expr = Expr::Index(Box::new(IndexExpr::new(expr, bracket, index)));
+        }
+
+        Ok(expr)
     }
 
     fn primary(&mut self) -> Result<Expr, ParseError> {
@@ -104,11 +120,28 @@ impl Parser {
         }
 
         if matches!(self.peek().r#type, TokenType::LEFT_PAREN) {
+            self.advance();
             let expr = self.expression()?;
             self.consume(TokenType::RIGHT_PAREN)?;
             return Ok(Expr::Grouping(Box::new(GroupingExpr::new(expr))));
         }
 
+        if matches!(self.peek().r#type, TokenType::LEFT_BRACKET) {
+            self.advance();
+            let mut elements = Vec::new();
+            if !matches!(self.peek().r#type, TokenType::RIGHT_BRACKET) {
+                loop {
+                    elements.push(self.expression()?);
+                    if !matches!(self.peek().r#type, TokenType::COMMA) {
+                        break;
+                    }
+                    self.advance();
+                }
+            }
+            self.consume(TokenType::RIGHT_BRACKET)?;
+            return Ok(Expr::Array(Box::new(ArrayExpr::new(elements))));
+        }
+
         Err(self.error(&self.peek(), "expect expression, but not found."))
     }
 
@@ -142,6 +175,8 @@ impl Parser {
         }
     }
 
+    // not used yet: this is for recovering from errors once statements are parsed
+    #[allow(dead_code)]
     fn synchronize(&mut self) {
         self.advance();
 
@@ -177,6 +212,6 @@ impl Parser {
                 token.lexeme
             );
         }
-        return ParseError::SyntaxError;
+        ParseError::SyntaxError
     }
 }
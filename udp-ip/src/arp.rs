@@ -4,12 +4,59 @@ use crate::{
     net::NetworkInterface,
     socket,
 };
-use std::net::Ipv4Addr;
+use std::{
+    collections::HashMap,
+    net::Ipv4Addr,
+    sync::{Arc, LazyLock, Mutex},
+    time::{Duration, Instant},
+};
 use tracing::info;
 
+// 解決済みのアドレスを再利用する期間
+const ARP_CACHE_TTL: Duration = Duration::from_secs(20);
+
+const OPCODE_REQUEST: u16 = 0x0001;
+const OPCODE_REPLY: u16 = 0x0002;
+
+static ARP_CACHE: LazyLock<Arc<Mutex<ArpCache>>> =
+    LazyLock::new(|| Arc::new(Mutex::new(ArpCache::default())));
+
+#[derive(Default)]
+pub struct ArpCache {
+    entries: HashMap<Ipv4Addr, (MacAddr, Instant)>,
+}
+
+impl ArpCache {
+    pub fn shared() -> Arc<Mutex<ArpCache>> {
+        ARP_CACHE.clone()
+    }
+
+    pub fn get(&mut self, ip_addr: Ipv4Addr) -> Option<MacAddr> {
+        let (mac_addr, resolved_at) = self.entries.get(&ip_addr)?;
+        if resolved_at.elapsed() >= ARP_CACHE_TTL {
+            self.entries.remove(&ip_addr);
+            return None;
+        }
+        Some(*mac_addr)
+    }
+
+    pub fn insert(&mut self, ip_addr: Ipv4Addr, mac_addr: MacAddr) {
+        self.entries.insert(ip_addr, (mac_addr, Instant::now()));
+    }
+}
+
 pub struct Arp {}
 
 impl Arp {
+    // キャッシュに有効なエントリがあれば ARP リクエストを送らずにそれを返す
+    pub fn resolve(dst_ip_addr: Ipv4Addr, src_net_interface: &NetworkInterface) -> Option<MacAddr> {
+        if let Some(mac_addr) = ArpCache::shared().lock().unwrap().get(dst_ip_addr) {
+            info!("found {} in the arp cache...", dst_ip_addr);
+            return Some(mac_addr);
+        }
+        Arp::send(dst_ip_addr, src_net_interface).map(|reply| reply.sender_mac_addr)
+    }
+
     pub fn send(dst_ip_addr: Ipv4Addr, src_net_interface: &NetworkInterface) -> Option<ArpFrame> {
         // 送信するパケットの準備
         let ethernet_frame = EthernetFrame::new(
@@ -26,24 +73,45 @@ impl Arp {
         send_packet.extend(ethernet_frame.to_bytes());
         send_packet.extend(arp_req_frame.to_bytes());
 
-        let (sender, mut reciever) = socket::channel(&src_net_interface);
+        let (sender, mut reciever) = socket::channel(src_net_interface);
         info!("send the arp packet...");
         sender.sendto(send_packet);
 
         // パケットの送受信
         info!("receive the arp packet...");
         while let Ok((_ret, _addr)) = reciever.recvfrom() {
-            if !reciever.buf.is_empty() && Arp::is_arp_reply_packet(&reciever.buf) {
-                info!("found an arp reply packet...");
-                let offset = ETHERNET_FRAME_LENGTH;
-                return Some(ArpFrame::from_bytes(&reciever.buf[offset..]));
+            if reciever.buf.is_empty() || !Arp::is_arp_packet(&reciever.buf) {
+                continue;
+            }
+            let frame = ArpFrame::from_bytes(&reciever.buf[ETHERNET_FRAME_LENGTH..]);
+            match frame.opcode {
+                OPCODE_REPLY => {
+                    info!("found an arp reply packet...");
+                    ArpCache::shared()
+                        .lock()
+                        .unwrap()
+                        .insert(frame.sender_ip_addr, frame.sender_mac_addr);
+                    return Some(frame);
+                }
+                // 自分宛ての ARP リクエストを送ってきた相手のアドレスも覚えておく
+                OPCODE_REQUEST if frame.target_ip_addr == src_net_interface.ip_addr => {
+                    info!(
+                        "found an arp request packet from {}...",
+                        frame.sender_ip_addr
+                    );
+                    ArpCache::shared()
+                        .lock()
+                        .unwrap()
+                        .insert(frame.sender_ip_addr, frame.sender_mac_addr);
+                }
+                _ => {}
             }
         }
         None
     }
 
-    fn is_arp_reply_packet(packet: &[u8]) -> bool {
-        packet[12] == 0x08 && packet[13] == 0x06 && packet[20] == 0x00 && packet[21] == 0x02
+    fn is_arp_packet(packet: &[u8]) -> bool {
+        packet[12] == 0x08 && packet[13] == 0x06
     }
 }
 
@@ -73,7 +141,7 @@ impl ArpFrame {
             protocol_type: 0x0800, // IPv4
             hardware_size: 0x06,   // mac address は 6 bytes
             protocol_size: 0x04,   // IP address は 4 bytes
-            opcode: 0x0001,        // ARP request
+            opcode: OPCODE_REQUEST,
             sender_mac_addr,
             sender_ip_addr,
             target_mac_addr: BROADCAST_MAC_ADDR,
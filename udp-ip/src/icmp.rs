@@ -2,7 +2,7 @@ use crate::{
     address::MacAddr,
     checksum,
     ethernet::{EthernetFrame, EthernetType, ETHERNET_FRAME_LENGTH},
-    ip::{IpHeader, Protocol, IP_HEADER_LENGTH},
+    ip::{IpHeader, Protocol, ICMP_PROTOCOL, IP_HEADER_LENGTH},
     net::NetworkInterface,
     socket,
 };
@@ -11,6 +11,9 @@ use tracing::info;
 
 pub const ICMP_HEADER_LENGTH: usize = 8;
 
+const ECHO_REPLY: u8 = 0;
+const ECHO_REQUEST: u8 = 8;
+
 pub struct Icmp {}
 
 impl Icmp {
@@ -36,22 +39,80 @@ impl Icmp {
         ]
         .concat();
 
-        let (sender, mut reciever) = socket::channel(&src_net_interface);
+        let (sender, mut reciever) = socket::channel(src_net_interface);
         info!("send the icmp packet...");
         sender.sendto(send_packet);
 
         // パケットの送受信
         info!("receive the icmp packet...");
-        while let Ok((_ret, _addr)) = reciever.recvfrom() {
+        while let Ok((ret, _addr)) = reciever.recvfrom() {
             if !reciever.buf.is_empty() && Icmp::is_icmp_reply_packet(&reciever.buf) {
                 info!("found an icmp reply packet...");
                 let offset = ETHERNET_FRAME_LENGTH + IP_HEADER_LENGTH;
-                return Some(IcmpFrame::from_bytes(&reciever.buf[offset..]));
+                return Some(IcmpFrame::from_bytes(&reciever.buf[offset..ret]));
             }
         }
         None
     }
 
+    // 自分宛ての Echo Request に Echo Reply を返し続ける
+    pub fn serve(src_net_interface: &NetworkInterface) {
+        let (sender, mut reciever) = socket::channel(src_net_interface);
+        info!("wait for icmp echo requests...");
+        while let Ok((ret, _addr)) = reciever.recvfrom() {
+            if let Some(reply) = Icmp::echo_reply(
+                &reciever.buf[..ret],
+                src_net_interface.mac_addr,
+                src_net_interface.ip_addr,
+            ) {
+                info!("send an icmp echo reply...");
+                sender.sendto(reply);
+            }
+        }
+    }
+
+    // 受信した Ethernet フレームが自分宛ての Echo Request なら、それに対する Echo Reply のフレームを作る
+    // identification, seq_num, データはそのまま返し、送信元と宛先を入れ替える
+    pub fn echo_reply(frame: &[u8], mac_addr: MacAddr, ip_addr: Ipv4Addr) -> Option<Vec<u8>> {
+        if frame.len() < ETHERNET_FRAME_LENGTH + IP_HEADER_LENGTH || frame[12..14] != [0x08, 0x00] {
+            return None;
+        }
+        let ip_header = IpHeader::from_bytes(&frame[ETHERNET_FRAME_LENGTH..]);
+        if ip_header.protocol != ICMP_PROTOCOL
+            || ip_header.dst_ip != ip_addr
+            || ip_header.is_fragment()
+        {
+            return None;
+        }
+        let offset = ETHERNET_FRAME_LENGTH + ip_header.ihl as usize * 4;
+        let end = ETHERNET_FRAME_LENGTH + ip_header.total_length as usize;
+        let request = frame.get(offset..end)?;
+        if request.len() < ICMP_HEADER_LENGTH || request[0] != ECHO_REQUEST {
+            return None;
+        }
+        let request = IcmpFrame::from_bytes(request);
+
+        let reply = IcmpFrame {
+            frame_type: ECHO_REPLY,
+            code: 0,
+            checksum: 0, // 後でセットする
+            identification: request.identification,
+            seq_num: request.seq_num,
+            data: request.data,
+        }
+        .to_bytes();
+        let requester_mac_addr =
+            MacAddr::new(frame[6], frame[7], frame[8], frame[9], frame[10], frame[11]);
+        let ethernet_frame = EthernetFrame::new(EthernetType::Ipv4, requester_mac_addr, mac_addr);
+        let ip_header = IpHeader::new(
+            ip_addr,
+            ip_header.src_ip,
+            (IP_HEADER_LENGTH + reply.len()) as u16,
+            Protocol::IP,
+        );
+        Some([ethernet_frame.to_bytes(), ip_header.to_bytes(), reply].concat())
+    }
+
     fn is_icmp_reply_packet(packet: &[u8]) -> bool {
         packet[23] == 0x01
     }
@@ -76,6 +137,8 @@ pub struct IcmpFrame {
     // ICMP ping を受け取った側は、そのまま同じ値で返す
     identification: u16,
     seq_num: u16,
+    // Echo Request に付けられたデータ、Echo Reply ではそのまま返す
+    data: Vec<u8>,
 }
 
 impl IcmpFrame {
@@ -89,6 +152,7 @@ impl IcmpFrame {
             checksum: 0,       // 後でセットする
             identification: 0, // なんでも良いので、今回は 0
             seq_num: 0,        // 初期値はなんでも良いので、今回は 0
+            data: Vec::new(),
             frame_type,
         }
     }
@@ -100,6 +164,7 @@ impl IcmpFrame {
             checksum: u16::from_be_bytes([bytes[2], bytes[3]]),
             identification: u16::from_be_bytes([bytes[4], bytes[5]]),
             seq_num: u16::from_be_bytes([bytes[6], bytes[7]]),
+            data: bytes[ICMP_HEADER_LENGTH..].to_vec(),
         }
     }
 
@@ -110,6 +175,7 @@ impl IcmpFrame {
         bytes.extend(&self.checksum.to_be_bytes());
         bytes.extend(&self.identification.to_be_bytes());
         bytes.extend(&self.seq_num.to_be_bytes());
+        bytes.extend(&self.data);
         // checksum を計算して、再度セットする
         self.set_checksum(&mut bytes);
         bytes
@@ -120,3 +186,41 @@ impl IcmpFrame {
         bytes[2..4].copy_from_slice(&checksum.to_be_bytes());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reply_to_echo_request() {
+        let mac_addr = MacAddr::new(0x02, 0x00, 0x00, 0x00, 0x00, 0x01);
+        let ip_addr = Ipv4Addr::new(192, 168, 0, 1);
+        // 192.168.0.2 から 192.168.0.1 への Echo Request (identification=0x1234, seq_num=7, データ="ping!")
+        let request = [
+            0x02, 0x00, 0x00, 0x00, 0x00, 0x01, 0x02, 0x00, 0x00, 0x00, 0x00, 0x02, //
+            0x08, 0x00, 0x45, 0x00, 0x00, 0x21, 0x00, 0x00, 0x40, 0x00, 0x40, 0x01, //
+            0xb9, 0x88, 0xc0, 0xa8, 0x00, 0x02, 0xc0, 0xa8, 0x00, 0x01, 0x08, 0x00, //
+            0xe5, 0xf3, 0x12, 0x34, 0x00, 0x07, 0x70, 0x69, 0x6e, 0x67, 0x21,
+        ];
+        let reply = [
+            0x02, 0x00, 0x00, 0x00, 0x00, 0x02, 0x02, 0x00, 0x00, 0x00, 0x00, 0x01, //
+            0x08, 0x00, 0x45, 0x00, 0x00, 0x21, 0x00, 0x00, 0x40, 0x00, 0x40, 0x01, //
+            0xb9, 0x88, 0xc0, 0xa8, 0x00, 0x01, 0xc0, 0xa8, 0x00, 0x02, 0x00, 0x00, //
+            0xed, 0xf3, 0x12, 0x34, 0x00, 0x07, 0x70, 0x69, 0x6e, 0x67, 0x21,
+        ];
+        assert_eq!(
+            Icmp::echo_reply(&request, mac_addr, ip_addr),
+            Some(reply.to_vec())
+        );
+
+        // 自分宛てでない Echo Request や、Echo Reply には応答しない
+        assert_eq!(
+            Icmp::echo_reply(&request, mac_addr, Ipv4Addr::new(192, 168, 0, 3)),
+            None
+        );
+        assert_eq!(
+            Icmp::echo_reply(&reply, mac_addr, Ipv4Addr::new(192, 168, 0, 2)),
+            None
+        );
+    }
+}
@@ -1,23 +1,44 @@
 use crate::checksum;
-use std::net::Ipv4Addr;
+use std::{
+    collections::HashMap,
+    net::Ipv4Addr,
+    sync::atomic::{AtomicU16, Ordering},
+    time::{Duration, Instant},
+};
 
 // 基本は 20 byte だが、オプションフィールドがある場合はそれが追加される
 pub const IP_HEADER_LENGTH: usize = 20;
+// Ethernet で一度に送れる IP パケットの最大長
+pub const MTU: usize = 1500;
+pub const ICMP_PROTOCOL: u8 = 0x01;
+pub const UDP_PROTOCOL: u8 = 0x11;
+
+// flags の各ビット (先頭のビットは予約されていて常に 0)
+const DONT_FRAGMENT: u8 = 0b010;
+const MORE_FRAGMENTS: u8 = 0b001;
+
+// 最初のフラグメントが届いてからこの時間内に揃わなかったパケットは捨てる (Linux と同じ 30 秒)
+const REASSEMBLY_TIMEOUT: Duration = Duration::from_secs(30);
+// total_length は 16 ビットなので、組み立てたデータはこの長さまでしか表せない
+const MAX_PAYLOAD_LENGTH: usize = u16::MAX as usize - IP_HEADER_LENGTH;
+
+// フラグメント化するパケットごとに異なる identification を割り当てる
+static NEXT_IDENTIFICATION: AtomicU16 = AtomicU16::new(1);
 
 pub enum Protocol {
     IP,
-    UDP,
+    Udp,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct IpHeader {
     version: u8,
     // Internet Header Length (IHL)：ヘッダの長さ
-    ihl: u8,
+    pub ihl: u8,
     // Type of Service：トラフィックの優先順位を 0-5 の値で指定
     tos: u8,
     // パケット全体の長さ
-    total_length: u16,
+    pub total_length: u16,
     // パケットをフラグメント化する際に使用される識別子
     identification: u16,
     // フラグメント化の制御フラグ
@@ -26,7 +47,7 @@ pub struct IpHeader {
     fragment_offset: u16,
     // Time to Live：パケットがネットワーク上に存在できる時間
     ttl: u8,
-    protocol: u8,
+    pub protocol: u8,
     checksum: u16,
     pub src_ip: Ipv4Addr,
     pub dst_ip: Ipv4Addr,
@@ -40,16 +61,16 @@ impl IpHeader {
         protocol: Protocol,
     ) -> IpHeader {
         let protocol = match protocol {
-            Protocol::IP => 0x01,
-            Protocol::UDP => 0x11,
+            Protocol::IP => ICMP_PROTOCOL,
+            Protocol::Udp => UDP_PROTOCOL,
         };
         IpHeader {
             version: 4,                        // 常に 4
             ihl: (IP_HEADER_LENGTH / 4) as u8, // 32 ビット単位で表現するため 4 で割る
             tos: 0,                            // 優先度が一番低い 0 を指定
-            identification: 0,                 // フラグメント化しないので 0
-            flags: 2,                          // フラグメント化を許可しない (010)
-            fragment_offset: 0,                // フラグメント化しないので 0
+            identification: 0,                 // フラグメント化するときに fragment でセットする
+            flags: DONT_FRAGMENT,              // フラグメント化を許可しない (010)
+            fragment_offset: 0,                // フラグメント化するときに fragment でセットする
             ttl: 64,                           // 64, 128, 255 などを指定、今回は 64
             checksum: 0,                       // 後でセットする
             src_ip,
@@ -59,7 +80,30 @@ impl IpHeader {
         }
     }
 
-    pub fn to_bytes(&self) -> Vec<u8> {
+    pub fn from_bytes(bytes: &[u8]) -> IpHeader {
+        IpHeader {
+            version: bytes[0] >> 4,
+            // 0x0F=00001111 で下位 4 ビットを取得
+            ihl: bytes[0] & 0x0F,
+            tos: bytes[1],
+            total_length: u16::from_be_bytes([bytes[2], bytes[3]]),
+            identification: u16::from_be_bytes([bytes[4], bytes[5]]),
+            flags: bytes[6] >> 5,
+            // 0x1FFF=0001111111111111 で下位 13 ビットを取得
+            fragment_offset: u16::from_be_bytes([bytes[6], bytes[7]]) & 0x1FFF,
+            ttl: bytes[8],
+            protocol: bytes[9],
+            checksum: u16::from_be_bytes([bytes[10], bytes[11]]),
+            src_ip: Ipv4Addr::new(bytes[12], bytes[13], bytes[14], bytes[15]),
+            dst_ip: Ipv4Addr::new(bytes[16], bytes[17], bytes[18], bytes[19]),
+        }
+    }
+
+    pub fn is_fragment(&self) -> bool {
+        self.flags & MORE_FRAGMENTS != 0 || self.fragment_offset != 0
+    }
+
+    pub fn to_bytes(self) -> Vec<u8> {
         let mut bytes = Vec::new();
         let version_and_ihl = (self.version << 4) | self.ihl;
         let flags_and_fragment_offset =
@@ -84,3 +128,237 @@ impl IpHeader {
         bytes[10..12].copy_from_slice(&checksum.to_be_bytes());
     }
 }
+
+// ヘッダーとペイロードから IP パケットを作り、MTU を超える場合はフラグメントに分割する
+pub fn fragment(mut header: IpHeader, payload: &[u8]) -> Vec<Vec<u8>> {
+    if IP_HEADER_LENGTH + payload.len() <= MTU {
+        return vec![[header.to_bytes(), payload.to_vec()].concat()];
+    }
+
+    // オフセットは 8 バイト単位なので、最後以外のフラグメントのデータ長は 8 の倍数にする
+    let max_data_length = (MTU - IP_HEADER_LENGTH) / 8 * 8;
+    header.identification = NEXT_IDENTIFICATION.fetch_add(1, Ordering::Relaxed);
+    let chunks = payload.chunks(max_data_length);
+    let count = chunks.len();
+    chunks
+        .enumerate()
+        .map(|(i, chunk)| {
+            let mut fragment_header = header;
+            fragment_header.total_length = (IP_HEADER_LENGTH + chunk.len()) as u16;
+            // 最後のフラグメント以外には MF (More Fragments) を立てる
+            fragment_header.flags = if i + 1 < count { MORE_FRAGMENTS } else { 0 };
+            fragment_header.fragment_offset = (i * max_data_length / 8) as u16;
+            [fragment_header.to_bytes(), chunk.to_vec()].concat()
+        })
+        .collect()
+}
+
+type FragmentKey = (Ipv4Addr, Ipv4Addr, u16);
+
+// 組み立て途中のパケット
+struct PartialPacket {
+    // 8 バイト単位のオフセットをインデックスとして、届いたフラグメントのデータを置く
+    slots: Vec<Option<Vec<u8>>>,
+    // 最後のフラグメントが届くと分かる、元のパケットのデータ長
+    total_length: Option<usize>,
+    first_received_at: Instant,
+}
+
+impl PartialPacket {
+    // 届いたフラグメントそれぞれが占めるデータの範囲 (開始, 終了)
+    fn ranges(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            slot.as_ref()
+                .map(|data| (index * 8, index * 8 + data.len()))
+        })
+    }
+}
+
+// 送信元、宛先、identification ごとにフラグメントを集めて元のパケットに戻す
+#[derive(Default)]
+pub struct Reassembler {
+    packets: HashMap<FragmentKey, PartialPacket>,
+}
+
+impl Reassembler {
+    // フラグメント化されていないパケットはそのまま返し、
+    // フラグメントの場合は全て揃ったときに結合したヘッダーとデータを返す
+    pub fn push(&mut self, packet: &[u8]) -> Option<(IpHeader, Vec<u8>)> {
+        // フラグメントが失われたパケットがいつまでも残らないようにする
+        self.evict(Instant::now());
+
+        let header = IpHeader::from_bytes(packet);
+        let data = packet.get(header.ihl as usize * 4..header.total_length as usize)?;
+        let more_fragments = header.flags & MORE_FRAGMENTS != 0;
+        if !more_fragments && header.fragment_offset == 0 {
+            return Some((header, data.to_vec()));
+        }
+
+        let key = (header.src_ip, header.dst_ip, header.identification);
+        let index = header.fragment_offset as usize;
+        let (start, end) = (index * 8, index * 8 + data.len());
+        let partial = self.packets.entry(key).or_insert_with(|| PartialPacket {
+            slots: Vec::new(),
+            total_length: None,
+            first_received_at: Instant::now(),
+        });
+
+        // 最大長を超えるものや、他のフラグメントと範囲が重なるものは不正なパケットとして丸ごと捨てる
+        let overlaps = partial.slots.get(index).is_some_and(Option::is_some)
+            || partial.ranges().any(|(s, e)| start < e && s < end);
+        let beyond_end = partial.total_length.is_some_and(|total| end > total)
+            || (!more_fragments && partial.ranges().any(|(_, e)| e > end));
+        if end > MAX_PAYLOAD_LENGTH || overlaps || beyond_end {
+            self.packets.remove(&key);
+            return None;
+        }
+        if partial.slots.len() <= index {
+            partial.slots.resize(index + 1, None);
+        }
+        partial.slots[index] = Some(data.to_vec());
+        if !more_fragments {
+            partial.total_length = Some(index * 8 + data.len());
+        }
+
+        // 先頭からフラグメントを辿り、最後まで隙間なく揃っているか確認する
+        let total_length = partial.total_length?;
+        let mut payload = Vec::with_capacity(total_length);
+        while payload.len() < total_length {
+            if payload.len() % 8 != 0 {
+                return None;
+            }
+            payload.extend(partial.slots.get(payload.len() / 8)?.as_ref()?);
+        }
+        self.packets.remove(&key);
+
+        let mut header = header;
+        header.total_length = (IP_HEADER_LENGTH + total_length) as u16;
+        header.flags = 0;
+        header.fragment_offset = 0;
+        Some((header, payload))
+    }
+
+    fn evict(&mut self, now: Instant) {
+        self.packets.retain(|_, partial| {
+            now.duration_since(partial.first_received_at) < REASSEMBLY_TIMEOUT
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn udp_header(payload_length: usize) -> IpHeader {
+        IpHeader::new(
+            Ipv4Addr::new(192, 168, 0, 2),
+            Ipv4Addr::new(192, 168, 0, 1),
+            (IP_HEADER_LENGTH + payload_length) as u16,
+            Protocol::Udp,
+        )
+    }
+
+    #[test]
+    fn reassemble_fragments_out_of_order() {
+        let payload = (0..4000).map(|i| i as u8).collect::<Vec<_>>();
+        let fragments = fragment(udp_header(payload.len()), &payload);
+
+        // 1480 バイトずつに分かれ、オフセットは 8 バイト単位になる
+        let headers = fragments
+            .iter()
+            .map(|fragment| IpHeader::from_bytes(fragment))
+            .collect::<Vec<_>>();
+        let offsets = headers
+            .iter()
+            .map(|h| h.fragment_offset)
+            .collect::<Vec<_>>();
+        assert_eq!(offsets, vec![0, 185, 370]);
+        let flags = headers.iter().map(|h| h.flags).collect::<Vec<_>>();
+        assert_eq!(flags, vec![MORE_FRAGMENTS, MORE_FRAGMENTS, 0]);
+        let lengths = headers.iter().map(|h| h.total_length).collect::<Vec<_>>();
+        assert_eq!(lengths, vec![1500, 1500, 1060]);
+        assert!(headers
+            .iter()
+            .all(|h| h.identification == headers[0].identification));
+
+        let mut reassembler = Reassembler::default();
+        assert!(reassembler.push(&fragments[2]).is_none());
+        assert!(reassembler.push(&fragments[0]).is_none());
+        let (header, reassembled) = reassembler.push(&fragments[1]).unwrap();
+        assert_eq!(reassembled, payload);
+        assert_eq!(
+            header.total_length as usize,
+            IP_HEADER_LENGTH + payload.len()
+        );
+        assert!(!header.is_fragment());
+        assert!(reassembler.packets.is_empty());
+    }
+
+    #[test]
+    fn do_not_fragment_small_packet() {
+        let payload = b"hello".to_vec();
+        let packets = fragment(udp_header(payload.len()), &payload);
+        assert_eq!(packets.len(), 1);
+
+        let (header, data) = Reassembler::default().push(&packets[0]).unwrap();
+        assert!(!header.is_fragment());
+        assert_eq!(data, payload);
+    }
+
+    #[test]
+    fn evict_incomplete_packet() {
+        let payload = vec![0; 4000];
+        let fragments = fragment(udp_header(payload.len()), &payload);
+
+        let mut reassembler = Reassembler::default();
+        assert!(reassembler.push(&fragments[0]).is_none());
+        reassembler.evict(Instant::now());
+        assert_eq!(reassembler.packets.len(), 1);
+        reassembler.evict(Instant::now() + REASSEMBLY_TIMEOUT);
+        assert!(reassembler.packets.is_empty());
+    }
+
+    fn fragment_packet(offset: usize, more_fragments: bool, data: &[u8]) -> Vec<u8> {
+        let mut header = udp_header(data.len());
+        header.identification = 42;
+        header.fragment_offset = (offset / 8) as u16;
+        header.flags = if more_fragments { MORE_FRAGMENTS } else { 0 };
+        [header.to_bytes(), data.to_vec()].concat()
+    }
+
+    #[test]
+    fn drop_oversized_packet() {
+        let mut reassembler = Reassembler::default();
+        assert!(reassembler
+            .push(&fragment_packet(0, true, &[0; 1480]))
+            .is_none());
+        assert_eq!(reassembler.packets.len(), 1);
+
+        // 組み立てると 65515 バイトを超えるフラグメント
+        assert!(reassembler
+            .push(&fragment_packet(65504, false, &[0; 16]))
+            .is_none());
+        assert!(reassembler.packets.is_empty());
+    }
+
+    #[test]
+    fn drop_overlapping_fragments() {
+        let mut reassembler = Reassembler::default();
+        assert!(reassembler
+            .push(&fragment_packet(0, true, &[0; 16]))
+            .is_none());
+        assert!(reassembler
+            .push(&fragment_packet(8, true, &[0; 16]))
+            .is_none());
+        assert!(reassembler.packets.is_empty());
+
+        // 重なりを検出した後は、残りのフラグメントが届いても組み立てない
+        assert!(reassembler
+            .push(&fragment_packet(16, false, &[0; 8]))
+            .is_none());
+        assert!(reassembler
+            .push(&fragment_packet(8, true, &[0; 8]))
+            .is_none());
+        assert_eq!(reassembler.packets.len(), 1);
+    }
+}
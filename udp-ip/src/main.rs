@@ -29,6 +29,9 @@ fn main() {
             let reply =
                 arp::Arp::send(dst_ip_addr, &src_net_interface).expect("missing reply arp frame");
             info!("reply arp frame: {:?}", &reply);
+            // 直後の解決はキャッシュから返る
+            let cached = arp::Arp::resolve(dst_ip_addr, &src_net_interface);
+            info!("cached mac address: {:?}", cached);
             info!("arp test is successful!!")
         }
         "icmp" => {
@@ -40,13 +43,20 @@ fn main() {
             let src_net_interface =
                 net::NetworkInterface::new(&args[2]).expect("failed to find the network interface");
             let dst_ip_addr = args[3].parse::<Ipv4Addr>().expect("invalid ip address");
-            let reply =
-                arp::Arp::send(dst_ip_addr, &src_net_interface).expect("missing reply arp frame");
-            let reply = icmp::Icmp::send(dst_ip_addr, reply.sender_mac_addr, &src_net_interface)
+            let dst_mac_addr = arp::Arp::resolve(dst_ip_addr, &src_net_interface)
+                .expect("missing reply arp frame");
+            let reply = icmp::Icmp::send(dst_ip_addr, dst_mac_addr, &src_net_interface)
                 .expect("missing reply icmp frame");
             info!("reply icmp frame: {:?}", &reply);
             info!("icmp ping test is successful!!")
         }
+        "icmp-reply" => {
+            info!("icmp ping responder...");
+            info!("src_network_interface_name='{}'", &args[2]);
+            let src_net_interface =
+                net::NetworkInterface::new(&args[2]).expect("failed to find the network interface");
+            icmp::Icmp::serve(&src_net_interface);
+        }
         "udp" => {
             info!("udp test...");
             info!(
@@ -57,16 +67,39 @@ fn main() {
                 net::NetworkInterface::new(&args[2]).expect("failed to find the network interface");
             let dst_ip_addr = args[3].parse::<Ipv4Addr>().expect("invalid ip address");
             let dst_port = args[4].parse::<u16>().expect("invalid port number");
-            let reply =
-                arp::Arp::send(dst_ip_addr, &src_net_interface).expect("missing reply arp frame");
+            // ペイロードのサイズを指定すると、MTU を超える場合はフラグメント化して送る
+            let payload = match args.get(5) {
+                Some(size) => vec![b'a'; size.parse::<usize>().expect("invalid payload size")],
+                None => b"Hello, UDP!".to_vec(),
+            };
+            let dst_mac_addr = arp::Arp::resolve(dst_ip_addr, &src_net_interface)
+                .expect("missing reply arp frame");
             udp::Udp::send(
                 dst_port,
                 dst_ip_addr,
-                reply.sender_mac_addr,
+                dst_mac_addr,
                 &src_net_interface,
+                &payload,
             );
             info!("udp test is successful!!")
         }
+        "udp-listen" => {
+            info!("udp listen test...");
+            info!(
+                "src_network_interface_name='{}', port='{}'",
+                &args[2], &args[3]
+            );
+            let src_net_interface =
+                net::NetworkInterface::new(&args[2]).expect("failed to find the network interface");
+            let port = args[3].parse::<u16>().expect("invalid port number");
+            let payload = udp::Udp::receive(port, &src_net_interface).expect("missing udp packet");
+            info!(
+                "received {} bytes: {:?}",
+                payload.len(),
+                String::from_utf8_lossy(&payload)
+            );
+            info!("udp listen test is successful!!")
+        }
         _ => {}
     }
 }
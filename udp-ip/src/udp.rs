@@ -3,8 +3,8 @@ use tracing::info;
 use crate::{
     address::MacAddr,
     checksum,
-    ethernet::{EthernetFrame, EthernetType},
-    ip::{IpHeader, Protocol, IP_HEADER_LENGTH},
+    ethernet::{EthernetFrame, EthernetType, ETHERNET_FRAME_LENGTH},
+    ip::{self, IpHeader, Protocol, Reassembler, IP_HEADER_LENGTH, UDP_PROTOCOL},
     net::NetworkInterface,
     socket,
 };
@@ -20,8 +20,8 @@ impl Udp {
         dst_ip_addr: Ipv4Addr,
         dst_mac_addr: MacAddr,
         src_net_interface: &NetworkInterface,
+        payload: &[u8],
     ) {
-        let payload = b"Hello, UDP!";
         let ip_packet_length = (IP_HEADER_LENGTH + UDP_HEADER_LENGTH + payload.len()) as u16;
         let ethernet_frame =
             EthernetFrame::new(EthernetType::Ipv4, dst_mac_addr, src_net_interface.mac_addr);
@@ -29,21 +29,53 @@ impl Udp {
             src_net_interface.ip_addr,
             dst_ip_addr,
             ip_packet_length,
-            Protocol::UDP,
+            Protocol::Udp,
         );
         let udp_packet_length = (UDP_HEADER_LENGTH + payload.len()) as u16;
         // src_port は適当に設定
         let udp_header = UdpHeader::new(23456, dst_port, udp_packet_length);
-        let send_packet = [
-            ethernet_frame.to_bytes(),
-            ip_header.to_bytes(),
-            udp_header.to_bytes(&ip_header, payload),
-            payload.to_vec(),
-        ]
-        .concat();
-        let (sender, _) = socket::channel(&src_net_interface);
-        info!("send the UDP packet...");
-        sender.sendto(send_packet);
+        let udp_packet = [udp_header.to_bytes(&ip_header, payload), payload.to_vec()].concat();
+        let (sender, _) = socket::channel(src_net_interface);
+        // MTU を超える場合は複数のフラグメントに分けて送る
+        for ip_packet in ip::fragment(ip_header, &udp_packet) {
+            info!("send the UDP packet...");
+            sender.sendto([ethernet_frame.to_bytes(), ip_packet].concat());
+        }
+    }
+
+    // 自分宛ての UDP パケットを受け取るまで待ち、ペイロードを返す
+    pub fn receive(dst_port: u16, src_net_interface: &NetworkInterface) -> Option<Vec<u8>> {
+        let (_, mut reciever) = socket::channel(src_net_interface);
+        let mut reassembler = Reassembler::default();
+
+        info!("receive the UDP packet...");
+        while let Ok((length, _addr)) = reciever.recvfrom() {
+            let frame = &reciever.buf[..length];
+            if !Udp::is_ipv4_packet(frame) {
+                continue;
+            }
+            let Some((ip_header, ip_payload)) = reassembler.push(&frame[ETHERNET_FRAME_LENGTH..])
+            else {
+                continue;
+            };
+            if ip_header.protocol != UDP_PROTOCOL
+                || ip_header.dst_ip != src_net_interface.ip_addr
+                || ip_payload.len() < UDP_HEADER_LENGTH
+            {
+                continue;
+            }
+            if u16::from_be_bytes([ip_payload[2], ip_payload[3]]) == dst_port {
+                info!("found a UDP packet...");
+                return Some(ip_payload[UDP_HEADER_LENGTH..].to_vec());
+            }
+        }
+        None
+    }
+
+    fn is_ipv4_packet(frame: &[u8]) -> bool {
+        frame.len() >= ETHERNET_FRAME_LENGTH + IP_HEADER_LENGTH
+            && frame[12] == 0x08
+            && frame[13] == 0x00
     }
 }
 
@@ -0,0 +1,68 @@
+use crate::eval::{Instructions, Program};
+use std::collections::HashSet;
+
+// コンパイルした命令列を、オフセット・命令名・オペランドの表にして表示する
+// ジャンプ先はラベル名ではなく、実際に次に実行する命令のオフセットで表示する
+pub fn disassemble(program: &Program, name: &str) {
+    println!("== {} ==", name);
+    println!("{:<6} | {:<13} | operands", "offset", "instruction");
+
+    // ジャンプ用のラベル以外のシンボルは関数の先頭
+    let jump_labels: HashSet<&String> = program
+        .instructions
+        .iter()
+        .filter_map(|instruction| match instruction {
+            Instructions::Jump(label) | Instructions::JumpIfNotZero(label) => Some(label),
+            _ => None,
+        })
+        .collect();
+    let mut functions: Vec<(&String, i32)> = program
+        .syms
+        .iter()
+        .filter(|(label, _)| !jump_labels.contains(label))
+        .map(|(label, symbol)| (label, symbol.location))
+        .collect();
+    functions.sort();
+
+    for (offset, instruction) in program.instructions.iter().enumerate() {
+        for (function, _) in functions
+            .iter()
+            .filter(|(_, location)| *location == offset as i32)
+        {
+            println!("{:<6} | {}:", "", function);
+        }
+        let (name, operands) = describe(program, instruction);
+        let line = format!("{:04}   | {:<13} | {}", offset, name, operands);
+        println!("{}", line.trim_end());
+    }
+}
+
+fn describe(program: &Program, instruction: &Instructions) -> (&'static str, String) {
+    match instruction {
+        Instructions::DupPlusFP(offset) => ("DupPlusFP", format!("slot {}", offset)),
+        Instructions::MoveMinusFP(local_offset, fp_offset) => (
+            "MoveMinusFP",
+            format!("slot {} <- fp-{}", local_offset, fp_offset + 4),
+        ),
+        Instructions::MovePlusFP(offset) => ("MovePlusFP", format!("slot {}", offset)),
+        Instructions::Store(value) => ("Store", value.to_string()),
+        Instructions::Return => ("Return", String::new()),
+        // eval では JumpIfNotZero はラベルの次の命令に飛ぶ
+        Instructions::JumpIfNotZero(label) => (
+            "JumpIfNotZero",
+            format!("-> {:04}", program.syms[label].location + 1),
+        ),
+        Instructions::Jump(label) => ("Jump", format!("-> {:04}", program.syms[label].location)),
+        Instructions::Call(label, num_arguments) => {
+            let target = match program.syms.get(label) {
+                Some(symbol) => format!(" -> {:04}", symbol.location),
+                None => String::from(" (builtin)"),
+            };
+            ("Call", format!("{}({}){}", label, num_arguments, target))
+        }
+        Instructions::Add => ("Add", String::new()),
+        Instructions::Subtract => ("Subtract", String::new()),
+        Instructions::Modulo => ("Modulo", String::new()),
+        Instructions::LessThan => ("LessThan", String::new()),
+    }
+}
@@ -2,7 +2,7 @@ use crate::parse::*;
 use std::collections::HashMap;
 
 #[derive(Debug, Clone)]
-enum Instructions {
+pub enum Instructions {
     DupPlusFP(i32),          // どういう命令...?
     MoveMinusFP(usize, i32), // どういう命令...?
     MovePlusFP(usize),       // どういう命令...?
@@ -13,21 +13,22 @@ enum Instructions {
     Call(String, usize),
     Add,
     Subtract,
+    Modulo,
     LessThan,
 }
 
 // 関数やif文の範囲を表すために利用する
 #[derive(Debug)]
-struct Symbol {
-    location: i32,
-    num_arguments: usize,
-    num_locals: usize,
+pub struct Symbol {
+    pub location: i32,
+    pub num_arguments: usize,
+    pub num_locals: usize,
 }
 
 #[derive(Debug)]
 pub struct Program {
-    syms: HashMap<String, Symbol>,
-    instructions: Vec<Instructions>, // 命令
+    pub syms: HashMap<String, Symbol>,
+    pub instructions: Vec<Instructions>, // 命令
 }
 
 // ASTをPCへの命令に変換している
@@ -94,8 +95,8 @@ fn compile_declaration(
     program.syms.insert(
         function_declaration.name.value,
         Symbol {
-            location: function_index as i32,
-            num_arguments: num_arguments,
+            location: function_index,
+            num_arguments,
             num_locals: new_locals.keys().len(),
         },
     );
@@ -164,18 +165,14 @@ fn compile_binary_operation(
     locals: &mut HashMap<String, i32>,
     binary_operation: BinaryOperation,
 ) {
+    let start = program.instructions.len();
     compile_expression(program, raw, locals, *binary_operation.left);
     compile_expression(program, raw, locals, *binary_operation.right);
-    match binary_operation.operator.value.as_str() {
-        "+" => {
-            program.instructions.push(Instructions::Add);
-        }
-        "-" => {
-            program.instructions.push(Instructions::Subtract);
-        }
-        "<" => {
-            program.instructions.push(Instructions::LessThan);
-        }
+    let instruction = match binary_operation.operator.value.as_str() {
+        "+" => Instructions::Add,
+        "-" => Instructions::Subtract,
+        "%" => Instructions::Modulo,
+        "<" => Instructions::LessThan,
         _ => panic!(
             "{}",
             binary_operation
@@ -183,6 +180,27 @@ fn compile_binary_operation(
                 .location
                 .debug(raw, "Unable to compile binary operation")
         ),
+    };
+
+    // 定数畳み込み: 両辺がそれぞれ Store 1 命令だけなら、コンパイル時に計算して 1 つの Store にする
+    if let [Instructions::Store(left), Instructions::Store(right)] = program.instructions[start..] {
+        if let Some(value) = fold_constants(&instruction, left, right) {
+            program.instructions.truncate(start);
+            program.instructions.push(Instructions::Store(value));
+            return;
+        }
+    }
+    program.instructions.push(instruction);
+}
+
+// オーバーフローやゼロ除算になる場合は畳み込まずに、実行時と同じ挙動にする
+fn fold_constants(instruction: &Instructions, left: i32, right: i32) -> Option<i32> {
+    match instruction {
+        Instructions::Add => left.checked_add(right),
+        Instructions::Subtract => left.checked_sub(right),
+        Instructions::Modulo => left.checked_rem(right),
+        Instructions::LessThan => Some(if left < right { 1 } else { 0 }),
+        _ => None,
     }
 }
 
@@ -256,6 +274,15 @@ pub fn eval(program: Program) {
                 data.push(left - right);
                 pc += 1;
             }
+            Instructions::Modulo => {
+                let right = data.pop().unwrap();
+                let left = data.pop().unwrap();
+                if right == 0 {
+                    panic!("Attempt to calculate the remainder with a divisor of zero");
+                }
+                data.push(left % right);
+                pc += 1;
+            }
             Instructions::LessThan => {
                 let right = data.pop().unwrap();
                 let left = data.pop().unwrap();
@@ -358,3 +385,54 @@ pub fn eval(program: Program) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::lex;
+
+    fn compile_source(source: &str) -> Program {
+        let raw: Vec<char> = source.chars().collect();
+        let tokens = lex(&raw).unwrap();
+        let ast = parse(&raw, tokens).unwrap();
+        compile(&raw, ast)
+    }
+
+    #[test]
+    fn fold_constant_binary_operations() {
+        let program = compile_source("local a = 3 + 4;\nlocal b = 2 - 5;\nlocal c = 1 < a;");
+        assert!(matches!(
+            program.instructions[..],
+            [
+                Instructions::Store(7),
+                Instructions::MovePlusFP(0),
+                Instructions::Store(-3),
+                Instructions::MovePlusFP(1),
+                Instructions::Store(1),
+                Instructions::DupPlusFP(0),
+                Instructions::LessThan,
+                Instructions::MovePlusFP(2),
+            ]
+        ));
+    }
+
+    #[test]
+    fn compile_modulo_operation() {
+        let program = compile_source("local a = 7 % 3;\nlocal b = a % 2;\nlocal c = 1 % 0;");
+        assert!(matches!(
+            program.instructions[..],
+            [
+                Instructions::Store(1),
+                Instructions::MovePlusFP(0),
+                Instructions::DupPlusFP(0),
+                Instructions::Store(2),
+                Instructions::Modulo,
+                Instructions::MovePlusFP(1),
+                Instructions::Store(1),
+                Instructions::Store(0),
+                Instructions::Modulo,
+                Instructions::MovePlusFP(2),
+            ]
+        ));
+    }
+}
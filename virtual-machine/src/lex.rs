@@ -39,7 +39,7 @@ impl Location {
                 continue;
             }
             if self.line == line {
-                line_str.push_str(&c.to_string());
+                line_str.push(*c);
             }
         }
         let space = " ".repeat(self.column as usize);
@@ -128,7 +128,7 @@ fn lex_identifier(raw: &[char], loc: Location) -> Option<(Token, Location)> {
     }
 
     // First character must not be a digit
-    if value.len() > 0 && !value.chars().next().unwrap().is_digit(10) {
+    if !value.is_empty() && !value.chars().next().unwrap().is_ascii_digit() {
         Some((
             Token {
                 value,
@@ -193,7 +193,7 @@ fn lex_number(raw: &[char], loc: Location) -> Option<(Token, Location)> {
     let mut next_loc = loc;
     let mut value = String::new();
 
-    while c.is_digit(10) {
+    while c.is_ascii_digit() {
         value.push(c);
         next_loc = next_loc.increment(false);
         c = raw[next_loc.index];
@@ -214,7 +214,7 @@ fn lex_number(raw: &[char], loc: Location) -> Option<(Token, Location)> {
 }
 
 fn lex_operator(raw: &[char], loc: Location) -> Option<(Token, Location)> {
-    let operator = ["+", "-", "<"];
+    let operator = ["+", "-", "%", "<"];
     for possible_operator in operator {
         let c = raw[loc.index];
         let next_loc = loc.increment(false);
@@ -275,7 +275,7 @@ mod tests {
             tokens[2],
             Token {
                 value: "=".to_string(),
-                kind: TokenKind::Operator,
+                kind: TokenKind::Separator,
                 location: Location {
                     index: 9,
                     line: 0,
@@ -1,3 +1,4 @@
+mod disassembler;
 mod eval;
 mod lex;
 mod parse;
@@ -8,7 +9,14 @@ use std::fs;
 // entrypoint
 fn main() {
     let args: Vec<String> = env::args().collect();
-    let contents = fs::read_to_string(&args[1]).expect("Could not read file");
+    // --disassemble を付けると、実行する前にコンパイルした命令列を表示する
+    let disassemble = args.iter().any(|arg| arg == "--disassemble");
+    let path = args
+        .iter()
+        .skip(1)
+        .find(|arg| !arg.starts_with("--"))
+        .expect("Usage: vm-from-scratch-in-rust [--disassemble] <file>");
+    let contents = fs::read_to_string(path).expect("Could not read file");
 
     let raw: Vec<char> = contents.chars().collect();
 
@@ -30,6 +38,10 @@ fn main() {
     // compile the tree to a linear set of virtual machine instructions
     let pgm = eval::compile(&raw, ast);
 
+    if disassemble {
+        disassembler::disassemble(&pgm, path);
+    }
+
     // evaluator: 評価
     // interpret the virtual machine instructions
     eval::eval(pgm);
@@ -171,16 +171,14 @@ fn parse_expression(raw: &[char], tokens: &[Token], index: usize) -> Option<(Exp
         // Function Call
         let mut arguments: Vec<Expression> = vec![];
         while !expect_separator(tokens, next_index, ")") {
-            if !arguments.is_empty() {
-                if !expect_separator(tokens, next_index, ",") {
-                    println!(
-                        "{}",
-                        tokens[next_index]
-                            .location
-                            .debug(raw, "Expected comma between function call arguments:")
-                    );
-                    return None;
-                }
+            if !arguments.is_empty() && !expect_separator(tokens, next_index, ",") {
+                println!(
+                    "{}",
+                    tokens[next_index]
+                        .location
+                        .debug(raw, "Expected comma between function call arguments:")
+                );
+                return None;
             }
 
             let res = parse_expression(raw, tokens, next_index);
@@ -202,7 +200,7 @@ fn parse_expression(raw: &[char], tokens: &[Token], index: usize) -> Option<(Exp
         return Some((
             Expression::FunctionCall(FunctionCall {
                 name: tokens[index].clone(),
-                arguments: arguments,
+                arguments,
             }),
             next_index,
         ));
@@ -246,7 +244,7 @@ fn parse_expression(raw: &[char], tokens: &[Token], index: usize) -> Option<(Exp
     Some((
         Expression::BinaryOperation(BinaryOperation {
             left: Box::new(left),
-            operator: operator,
+            operator,
             right: Box::new(right),
         }),
         next_index,
@@ -323,8 +321,8 @@ fn parse_function(raw: &[char], tokens: &[Token], index: usize) -> Option<(State
     next_index += 1; // Skip past end
     Some((
         Statement::FunctionDeclaration(FunctionDeclaration {
-            name: name,
-            parameters: parameters,
+            name,
+            parameters,
             body: statements,
         }),
         next_index,
@@ -360,12 +358,7 @@ fn parse_return(raw: &[char], tokens: &[Token], index: usize) -> Option<(Stateme
         return None;
     }
     next_index += 1; // Skip past expression
-    Some((
-        Statement::Return(Return {
-            expression: expression,
-        }),
-        next_index,
-    ))
+    Some((Statement::Return(Return { expression }), next_index))
 }
 
 fn parse_local(raw: &[char], tokens: &[Token], index: usize) -> Option<(Statement, usize)> {
@@ -421,13 +414,7 @@ fn parse_local(raw: &[char], tokens: &[Token], index: usize) -> Option<(Statemen
     }
     next_index += 1; // Skip past semicolon
 
-    Some((
-        Statement::Local(Local {
-            name: name,
-            expression: expression,
-        }),
-        next_index,
-    ))
+    Some((Statement::Local(Local { name, expression }), next_index))
 }
 
 fn parse_if(raw: &[char], tokens: &[Token], index: usize) -> Option<(Statement, usize)> {
@@ -476,7 +463,7 @@ fn parse_if(raw: &[char], tokens: &[Token], index: usize) -> Option<(Statement,
 
     Some((
         Statement::If(If {
-            test: test,
+            test,
             body: statements,
         }),
         next_index,
@@ -7,10 +7,30 @@ pub enum Instruction {
     Return,
     LocalGet(u32),
     LocalSet(u32),
+    GlobalGet(u32),
+    GlobalSet(u32),
     I32Store { align: u32, offset: u32 },
+    MemorySize,
+    MemoryGrow,
     I32Const(i32),
     I32Lts,
     I32Add,
     I32Sub,
+    I64Const(i64),
+    I64Eqz,
+    I64Eq,
+    I64Ne,
+    I64LtS,
+    I64GtS,
+    I64LeS,
+    I64GeS,
+    I64Add,
+    I64Sub,
+    I64Mul,
+    I64DivS,
+    I64DivU,
+    I64RemS,
+    I32WrapI64,
+    I64ExtendI32S,
     Call(u32),
 }
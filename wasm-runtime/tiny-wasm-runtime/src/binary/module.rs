@@ -6,7 +6,7 @@ use nom::{
     sequence::pair,
     IResult,
 };
-use nom_leb128::{leb128_i32, leb128_u32};
+use nom_leb128::{leb128_i32, leb128_i64, leb128_u32};
 use num_traits::FromPrimitive as _;
 
 use super::{
@@ -14,8 +14,8 @@ use super::{
     opcode::Opcode,
     section::{Function, SectionCode},
     types::{
-        Block, BlockType, Data, Export, ExportDesc, FuncType, FunctionLocal, Import, ImportDesc,
-        Limits, Memory, ValueType,
+        Block, BlockType, Data, Export, ExportDesc, FuncType, FunctionLocal, Global, Import,
+        ImportDesc, Limits, Memory, ValueType,
     },
 };
 
@@ -27,6 +27,8 @@ pub struct Module {
     pub memory_section: Option<Vec<Memory>>,
     // メモリの初期化データを保持する
     pub data_section: Option<Vec<Data>>,
+    // グローバル変数の型と初期値を保持する
+    pub global_section: Option<Vec<Global>>,
     // 関数の型を保持する
     pub type_section: Option<Vec<FuncType>>,
     // 関数の型と実装の対応を保持する
@@ -46,6 +48,7 @@ impl Default for Module {
             version: 1,
             memory_section: None,
             data_section: None,
+            global_section: None,
             type_section: None,
             function_section: None,
             code_section: None,
@@ -83,6 +86,10 @@ impl Module {
                             let (_, memory) = decode_memory_section(section_contents)?;
                             module.memory_section = Some(vec![memory]);
                         }
+                        SectionCode::Global => {
+                            let (_, globals) = decode_global_section(section_contents)?;
+                            module.global_section = Some(globals);
+                        }
                         SectionCode::Data => {
                             let (_, data) = decode_data_section(section_contents)?;
                             module.data_section = Some(data);
@@ -232,6 +239,14 @@ fn decode_instructions(input: &[u8]) -> IResult<&[u8], Instruction> {
             let (rest, local_idx) = leb128_u32(input)?;
             (rest, Instruction::LocalSet(local_idx))
         }
+        Opcode::GlobalGet => {
+            let (rest, global_idx) = leb128_u32(input)?;
+            (rest, Instruction::GlobalGet(global_idx))
+        }
+        Opcode::GlobalSet => {
+            let (rest, global_idx) = leb128_u32(input)?;
+            (rest, Instruction::GlobalSet(global_idx))
+        }
         Opcode::I32Store => {
             // アライメントはメモリの境界値チェックのために使われるが、この本では扱わない
             let (rest, align) = leb128_u32(input)?;
@@ -239,6 +254,15 @@ fn decode_instructions(input: &[u8]) -> IResult<&[u8], Instruction> {
             let (rest, offset) = leb128_u32(rest)?;
             (rest, Instruction::I32Store { align, offset })
         }
+        // 操作するメモリのインデックスが続くが、version 1 では常に 0 なので読み飛ばす
+        Opcode::MemorySize => {
+            let (rest, _) = le_u8(input)?;
+            (rest, Instruction::MemorySize)
+        }
+        Opcode::MemoryGrow => {
+            let (rest, _) = le_u8(input)?;
+            (rest, Instruction::MemoryGrow)
+        }
         Opcode::I32Const => {
             let (rest, value) = leb128_i32(input)?;
             (rest, Instruction::I32Const(value))
@@ -246,6 +270,25 @@ fn decode_instructions(input: &[u8]) -> IResult<&[u8], Instruction> {
         Opcode::I32Lts => (input, Instruction::I32Lts),
         Opcode::I32Add => (input, Instruction::I32Add),
         Opcode::I32Sub => (input, Instruction::I32Sub),
+        Opcode::I64Const => {
+            let (rest, value) = leb128_i64(input)?;
+            (rest, Instruction::I64Const(value))
+        }
+        Opcode::I64Eqz => (input, Instruction::I64Eqz),
+        Opcode::I64Eq => (input, Instruction::I64Eq),
+        Opcode::I64Ne => (input, Instruction::I64Ne),
+        Opcode::I64LtS => (input, Instruction::I64LtS),
+        Opcode::I64GtS => (input, Instruction::I64GtS),
+        Opcode::I64LeS => (input, Instruction::I64LeS),
+        Opcode::I64GeS => (input, Instruction::I64GeS),
+        Opcode::I64Add => (input, Instruction::I64Add),
+        Opcode::I64Sub => (input, Instruction::I64Sub),
+        Opcode::I64Mul => (input, Instruction::I64Mul),
+        Opcode::I64DivS => (input, Instruction::I64DivS),
+        Opcode::I64DivU => (input, Instruction::I64DivU),
+        Opcode::I64RemS => (input, Instruction::I64RemS),
+        Opcode::I32WrapI64 => (input, Instruction::I32WrapI64),
+        Opcode::I64ExtendI32S => (input, Instruction::I64ExtendI32S),
         Opcode::End => (input, Instruction::End),
         Opcode::Call => {
             let (rest, func_idx) = leb128_u32(input)?;
@@ -339,6 +382,25 @@ fn decode_expr(input: &[u8]) -> IResult<&[u8], u32> {
     Ok((input, offset))
 }
 
+fn decode_global_section(input: &[u8]) -> IResult<&[u8], Vec<Global>> {
+    let (mut input, count) = leb128_u32(input)?;
+    let mut globals = vec![];
+    for _ in 0..count {
+        // 0x00 は変更できない (const)、0x01 は変更できる (var)
+        let (rest, (value_type, mutability)) = pair(le_u8, le_u8)(input)?;
+        // 初期値の命令と end 命令を読み取る
+        let (rest, init) = decode_instructions(rest)?;
+        let (rest, _) = decode_instructions(rest)?;
+        globals.push(Global {
+            value_type: value_type.into(),
+            mutable: mutability == 0x01,
+            init,
+        });
+        input = rest;
+    }
+    Ok((input, globals))
+}
+
 fn decode_data_section(input: &[u8]) -> IResult<&[u8], Vec<Data>> {
     let (mut input, count) = leb128_u32(input)?;
     let mut data = vec![];
@@ -627,6 +689,33 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn decode_global() -> Result<()> {
+        let wasm = wat::parse_str(
+            "(module (global i32 (i32.const 42)) (global (mut i64) (i64.const -1)))",
+        )?;
+        let module = Module::new(&wasm)?;
+        assert_eq!(
+            module,
+            Module {
+                global_section: Some(vec![
+                    Global {
+                        value_type: ValueType::I32,
+                        mutable: false,
+                        init: Instruction::I32Const(42),
+                    },
+                    Global {
+                        value_type: ValueType::I64,
+                        mutable: true,
+                        init: Instruction::I64Const(-1),
+                    },
+                ]),
+                ..Default::default()
+            }
+        );
+        Ok(())
+    }
+
     #[test]
     fn decode_data() -> Result<()> {
         let tests =
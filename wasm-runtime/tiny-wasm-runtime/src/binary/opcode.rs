@@ -7,10 +7,30 @@ pub enum Opcode {
     Return = 0x0F,
     LocalGet = 0x20,
     LocalSet = 0x21,
+    GlobalGet = 0x23,
+    GlobalSet = 0x24,
     I32Store = 0x36,
+    MemorySize = 0x3F,
+    MemoryGrow = 0x40,
     I32Const = 0x41,
     I32Lts = 0x48,
     I32Add = 0x6A,
     I32Sub = 0x6B,
+    I64Const = 0x42,
+    I64Eqz = 0x50,
+    I64Eq = 0x51,
+    I64Ne = 0x52,
+    I64LtS = 0x53,
+    I64GtS = 0x55,
+    I64LeS = 0x57,
+    I64GeS = 0x59,
+    I64Add = 0x7C,
+    I64Sub = 0x7D,
+    I64Mul = 0x7E,
+    I64DivS = 0x7F,
+    I64DivU = 0x80,
+    I64RemS = 0x81,
+    I32WrapI64 = 0xA7,
+    I64ExtendI32S = 0xAC,
     Call = 0x10,
 }
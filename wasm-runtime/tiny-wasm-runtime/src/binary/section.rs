@@ -9,6 +9,7 @@ pub enum SectionCode {
     Import = 0x02,
     Function = 0x03,
     Memory = 0x05,
+    Global = 0x06,
     Export = 0x07,
     Code = 0x0a,
     Data = 0x0b,
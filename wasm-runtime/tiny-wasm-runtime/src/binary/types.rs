@@ -1,3 +1,5 @@
+use super::instruction::Instruction;
+
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct FuncType {
     pub params: Vec<ValueType>,
@@ -60,6 +62,15 @@ pub struct Memory {
     pub limits: Limits,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Global {
+    pub value_type: ValueType,
+    pub mutable: bool,
+    // 初期値を計算する命令
+    // 今回は i32.const か i64.const のどちらかであることを前提とする
+    pub init: Instruction,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Data {
     // データの配置先のメモリのインデックス
@@ -56,10 +56,7 @@ impl Runtime {
     }
 
     fn execute(&mut self) -> Result<()> {
-        loop {
-            let Some(frame) = self.call_stack.last_mut() else {
-                break;
-            };
+        while let Some(frame) = self.call_stack.last_mut() {
             frame.pc += 1;
             // ここ 1 から始まるけど、0 から始まるべきでは？
             // → invoke_internal で pc = -1 から始めているので問題なかった
@@ -123,6 +120,24 @@ impl Runtime {
                     };
                     frame.locals[*idx as usize] = value;
                 }
+                Instruction::GlobalGet(idx) => {
+                    let Some(global) = self.store.globals.get(*idx as usize) else {
+                        bail!("not found global variable.")
+                    };
+                    self.stack.push(global.value);
+                }
+                Instruction::GlobalSet(idx) => {
+                    let Some(value) = self.stack.pop() else {
+                        bail!("not found value in the stack.")
+                    };
+                    let Some(global) = self.store.globals.get_mut(*idx as usize) else {
+                        bail!("not found global variable.")
+                    };
+                    if !global.mutable {
+                        bail!("cannot set immutable global variable.")
+                    }
+                    global.value = value;
+                }
                 Instruction::I32Store { align: _, offset } => {
                     // メモリに書き込む値とアドレスを取得
                     // → i32.store を呼び出す前には i32.const などでスタックにこれらの値を積んでおく必要がある
@@ -142,6 +157,30 @@ impl Runtime {
                     let value: i32 = value.into();
                     memory.data[at..end].copy_from_slice(&value.to_le_bytes());
                 }
+                Instruction::MemorySize => {
+                    let memory = self
+                        .store
+                        .memories
+                        .first()
+                        .ok_or(anyhow!("not found memory."))?;
+                    self.stack.push(Value::I32(memory.size() as i32));
+                }
+                Instruction::MemoryGrow => {
+                    let Some(Value::I32(n)) = self.stack.pop() else {
+                        bail!("not found page count in the stack.")
+                    };
+                    let memory = self
+                        .store
+                        .memories
+                        .get_mut(0)
+                        .ok_or(anyhow!("not found memory."))?;
+                    // 増やせなかった場合は -1 を積む
+                    let result = match memory.grow(n as u32) {
+                        Some(size) => size as i32,
+                        None => -1,
+                    };
+                    self.stack.push(Value::I32(result));
+                }
                 Instruction::I32Const(value) => {
                     self.stack.push(Value::I32(*value));
                 }
@@ -166,6 +205,85 @@ impl Runtime {
                     let result = left < right;
                     self.stack.push(Value::I32(result.into()));
                 }
+                Instruction::I64Const(value) => {
+                    self.stack.push(Value::I64(*value));
+                }
+                Instruction::I64Eqz => {
+                    let value = pop_i64(&mut self.stack)?;
+                    self.stack.push((value == 0).into());
+                }
+                Instruction::I64Eq
+                | Instruction::I64Ne
+                | Instruction::I64LtS
+                | Instruction::I64GtS
+                | Instruction::I64LeS
+                | Instruction::I64GeS => {
+                    let (left, right) = pop_i64_operands(&mut self.stack)?;
+                    // 比較の結果は i64 ではなく i32 の 0 か 1 になる
+                    let result = match inst {
+                        Instruction::I64Eq => left == right,
+                        Instruction::I64Ne => left != right,
+                        Instruction::I64LtS => left < right,
+                        Instruction::I64GtS => left > right,
+                        Instruction::I64LeS => left <= right,
+                        _ => left >= right,
+                    };
+                    self.stack.push(result.into());
+                }
+                // wasm の整数演算はオーバーフローしたら折り返す
+                Instruction::I64Add => {
+                    let (left, right) = pop_i64_operands(&mut self.stack)?;
+                    self.stack.push(Value::I64(left.wrapping_add(right)));
+                }
+                Instruction::I64Sub => {
+                    let (left, right) = pop_i64_operands(&mut self.stack)?;
+                    self.stack.push(Value::I64(left.wrapping_sub(right)));
+                }
+                Instruction::I64Mul => {
+                    let (left, right) = pop_i64_operands(&mut self.stack)?;
+                    self.stack.push(Value::I64(left.wrapping_mul(right)));
+                }
+                Instruction::I64DivS => {
+                    let (left, right) = pop_i64_operands(&mut self.stack)?;
+                    if right == 0 {
+                        bail!("integer divide by zero.")
+                    }
+                    // i64::MIN / -1 は i64 で表せないので、折り返さずにエラーにする
+                    let Some(result) = left.checked_div(right) else {
+                        bail!("integer overflow.")
+                    };
+                    self.stack.push(Value::I64(result));
+                }
+                Instruction::I64DivU => {
+                    let (left, right) = pop_i64_operands(&mut self.stack)?;
+                    if right == 0 {
+                        bail!("integer divide by zero.")
+                    }
+                    let result = (left as u64) / (right as u64);
+                    self.stack.push(Value::I64(result as i64));
+                }
+                Instruction::I64RemS => {
+                    let (left, right) = pop_i64_operands(&mut self.stack)?;
+                    if right == 0 {
+                        bail!("integer divide by zero.")
+                    }
+                    // i64::MIN % -1 はエラーではなく 0 になる
+                    self.stack.push(Value::I64(left.wrapping_rem(right)));
+                }
+                Instruction::I32WrapI64 => {
+                    // 下位 32 bit だけを残す
+                    let value = pop_i64(&mut self.stack)?;
+                    self.stack.push(Value::I32(value as i32));
+                }
+                Instruction::I64ExtendI32S => {
+                    let Some(value) = self.stack.pop() else {
+                        bail!("not found value in the stack.")
+                    };
+                    let Value::I32(value) = value else {
+                        bail!("type mismatch: expected i32.")
+                    };
+                    self.stack.push(Value::I64(value.into()));
+                }
                 Instruction::Call(idx) => {
                     let Some(func) = self.store.funcs.get(*idx as usize) else {
                         bail!("not found function.")
@@ -310,6 +428,21 @@ pub fn stack_unwind(stack: &mut Vec<Value>, sp: usize, arity: usize) -> Result<(
     Ok(())
 }
 
+fn pop_i64(stack: &mut Vec<Value>) -> Result<i64> {
+    match stack.pop() {
+        Some(Value::I64(value)) => Ok(value),
+        Some(_) => bail!("type mismatch: expected i64."),
+        None => bail!("not found value in the stack."),
+    }
+}
+
+// 二項演算のオペランドを (左辺, 右辺) の順で取り出す
+fn pop_i64_operands(stack: &mut Vec<Value>) -> Result<(i64, i64)> {
+    let right = pop_i64(stack)?;
+    let left = pop_i64(stack)?;
+    Ok((left, right))
+}
+
 pub fn get_end_address(insts: &[Instruction], pc: usize) -> Result<usize> {
     let mut pc = pc;
     let mut depth = 0;
@@ -419,6 +552,43 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn global_get() -> Result<()> {
+        let wasm = wat::parse_file("src/fixtures/global.wat")?;
+        let mut runtime = Runtime::instantiate(&wasm)?;
+        let result = runtime.call("get_const", vec![])?;
+        assert_eq!(result, Some(Value::I32(42)));
+        let result = runtime.call("get_counter", vec![])?;
+        assert_eq!(result, Some(Value::I64(1 << 32)));
+        Ok(())
+    }
+
+    #[test]
+    fn global_set() -> Result<()> {
+        let wasm = wat::parse_file("src/fixtures/global.wat")?;
+        let mut runtime = Runtime::instantiate(&wasm)?;
+        runtime.call("set_counter", vec![Value::I64(7)])?;
+        let result = runtime.call("get_counter", vec![])?;
+        assert_eq!(result, Some(Value::I64(7)));
+        Ok(())
+    }
+
+    #[test]
+    fn set_immutable_global() -> Result<()> {
+        let wasm = wat::parse_file("src/fixtures/global.wat")?;
+        let mut runtime = Runtime::instantiate(&wasm)?;
+        // wat は変更できないグローバル変数への global.set をエラーにするので、インスタンス化した後に変更できなくする
+        runtime.store.globals[1].mutable = false;
+        let Err(err) = runtime.call("set_counter", vec![Value::I64(7)]) else {
+            panic!("global.set to an immutable global should fail");
+        };
+        assert!(err
+            .to_string()
+            .contains("cannot set immutable global variable."));
+        assert_eq!(runtime.store.globals[1].value, Value::I64(1 << 32));
+        Ok(())
+    }
+
     #[test]
     fn i32_store() -> Result<()> {
         let wasm = wat::parse_file("src/fixtures/i32_store.wat")?;
@@ -429,6 +599,37 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn memory_size() -> Result<()> {
+        let wasm = wat::parse_file("src/fixtures/memory_grow.wat")?;
+        let mut runtime = Runtime::instantiate(&wasm)?;
+        let result = runtime.call("memory_size", vec![])?;
+        assert_eq!(result, Some(Value::I32(1)));
+        Ok(())
+    }
+
+    #[test]
+    fn memory_grow() -> Result<()> {
+        let wasm = wat::parse_file("src/fixtures/memory_grow.wat")?;
+        let mut runtime = Runtime::instantiate(&wasm)?;
+        // 増やす前のページ数が返る
+        let result = runtime.call("grow_and_store", vec![])?;
+        assert_eq!(result, Some(Value::I32(1)));
+        let result = runtime.call("memory_size", vec![])?;
+        assert_eq!(result, Some(Value::I32(2)));
+
+        // 増やした領域に書き込めている
+        let memory = &runtime.store.memories[0].data;
+        assert_eq!(memory.len(), 2 * 65536);
+        assert_eq!(memory[65536], 42);
+
+        // 最大値の 2 ページを超えるので増やせない
+        let result = runtime.call("memory_grow", vec![Value::I32(1)])?;
+        assert_eq!(result, Some(Value::I32(-1)));
+        assert_eq!(runtime.store.memories[0].data.len(), 2 * 65536);
+        Ok(())
+    }
+
     #[test]
     fn i32_sub() -> Result<()> {
         let wasm = wat::parse_file("src/fixtures/func_sub.wat")?;
@@ -447,6 +648,178 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn i64_const() -> Result<()> {
+        let wasm = wat::parse_file("src/fixtures/i64_const.wat")?;
+        let mut runtime = Runtime::instantiate(&wasm)?;
+        let result = runtime.call("i64_const", vec![])?;
+        assert_eq!(result, Some(Value::I64(1 << 32)));
+        Ok(())
+    }
+
+    #[test]
+    fn i64_add() -> Result<()> {
+        let wasm = wat::parse_file("src/fixtures/i64_arith.wat")?;
+        let mut runtime = Runtime::instantiate(&wasm)?;
+        let tests = vec![
+            (2, 3, 5),
+            (1 << 32, 1 << 32, 1 << 33),
+            (i64::MAX, 1, i64::MIN),
+        ];
+        for (left, right, want) in tests {
+            let args = vec![Value::I64(left), Value::I64(right)];
+            let result = runtime.call("add", args)?;
+            assert_eq!(result, Some(Value::I64(want)));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn i64_sub() -> Result<()> {
+        let wasm = wat::parse_file("src/fixtures/i64_arith.wat")?;
+        let mut runtime = Runtime::instantiate(&wasm)?;
+        let tests = vec![(10, 5, 5), (5, 10, -5), (i64::MIN, 1, i64::MAX)];
+        for (left, right, want) in tests {
+            let args = vec![Value::I64(left), Value::I64(right)];
+            let result = runtime.call("sub", args)?;
+            assert_eq!(result, Some(Value::I64(want)));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn i64_mul() -> Result<()> {
+        let wasm = wat::parse_file("src/fixtures/i64_arith.wat")?;
+        let mut runtime = Runtime::instantiate(&wasm)?;
+        let tests = vec![(3, 4, 12), (-3, 4, -12), (1 << 32, 1 << 31, 1 << 63)];
+        for (left, right, want) in tests {
+            let args = vec![Value::I64(left), Value::I64(right)];
+            let result = runtime.call("mul", args)?;
+            assert_eq!(result, Some(Value::I64(want)));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn i64_div_s() -> Result<()> {
+        let wasm = wat::parse_file("src/fixtures/i64_arith.wat")?;
+        let mut runtime = Runtime::instantiate(&wasm)?;
+        let tests = vec![(10, 3, 3), (-10, 3, -3), (10, -3, -3)];
+        for (left, right, want) in tests {
+            let args = vec![Value::I64(left), Value::I64(right)];
+            let result = runtime.call("div_s", args)?;
+            assert_eq!(result, Some(Value::I64(want)));
+        }
+
+        let result = runtime.call("div_s", vec![Value::I64(1), Value::I64(0)]);
+        assert!(result.is_err());
+        let result = runtime.call("div_s", vec![Value::I64(i64::MIN), Value::I64(-1)]);
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn i64_div_u() -> Result<()> {
+        let wasm = wat::parse_file("src/fixtures/i64_arith.wat")?;
+        let mut runtime = Runtime::instantiate(&wasm)?;
+        // -2 は符号なしで見ると u64::MAX - 1 になる
+        let tests = vec![(10, 3, 3), (-2, 2, i64::MAX)];
+        for (left, right, want) in tests {
+            let args = vec![Value::I64(left), Value::I64(right)];
+            let result = runtime.call("div_u", args)?;
+            assert_eq!(result, Some(Value::I64(want)));
+        }
+
+        let result = runtime.call("div_u", vec![Value::I64(1), Value::I64(0)]);
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn i64_rem_s() -> Result<()> {
+        let wasm = wat::parse_file("src/fixtures/i64_arith.wat")?;
+        let mut runtime = Runtime::instantiate(&wasm)?;
+        let tests = vec![(10, 3, 1), (-10, 3, -1), (i64::MIN, -1, 0)];
+        for (left, right, want) in tests {
+            let args = vec![Value::I64(left), Value::I64(right)];
+            let result = runtime.call("rem_s", args)?;
+            assert_eq!(result, Some(Value::I64(want)));
+        }
+
+        let result = runtime.call("rem_s", vec![Value::I64(1), Value::I64(0)]);
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn i64_eqz() -> Result<()> {
+        let wasm = wat::parse_file("src/fixtures/i64_compare.wat")?;
+        let mut runtime = Runtime::instantiate(&wasm)?;
+        let tests = vec![(0, 1), (1, 0), (1 << 32, 0)];
+        for (arg, want) in tests {
+            let result = runtime.call("eqz", vec![Value::I64(arg)])?;
+            assert_eq!(result, Some(Value::I32(want)));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn i64_compare() -> Result<()> {
+        let wasm = wat::parse_file("src/fixtures/i64_compare.wat")?;
+        let mut runtime = Runtime::instantiate(&wasm)?;
+        let tests = vec![
+            ("eq", 5, 5, 1),
+            ("eq", 5, 6, 0),
+            ("ne", 5, 6, 1),
+            ("ne", 5, 5, 0),
+            ("lt_s", -1, 0, 1),
+            ("lt_s", 0, -1, 0),
+            ("gt_s", 0, -1, 1),
+            ("gt_s", -1, 0, 0),
+            ("le_s", 5, 5, 1),
+            ("le_s", 6, 5, 0),
+            ("ge_s", 5, 5, 1),
+            ("ge_s", 5, 6, 0),
+        ];
+        for (name, left, right, want) in tests {
+            let args = vec![Value::I64(left), Value::I64(right)];
+            let result = runtime.call(name, args)?;
+            assert_eq!(
+                result,
+                Some(Value::I32(want)),
+                "{} {} {}",
+                name,
+                left,
+                right
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn i32_wrap_i64() -> Result<()> {
+        let wasm = wat::parse_file("src/fixtures/i64_convert.wat")?;
+        let mut runtime = Runtime::instantiate(&wasm)?;
+        let tests = vec![(42, 42), ((1 << 32) + 7, 7), (-1, -1)];
+        for (arg, want) in tests {
+            let result = runtime.call("wrap", vec![Value::I64(arg)])?;
+            assert_eq!(result, Some(Value::I32(want)));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn i64_extend_i32_s() -> Result<()> {
+        let wasm = wat::parse_file("src/fixtures/i64_convert.wat")?;
+        let mut runtime = Runtime::instantiate(&wasm)?;
+        let tests = vec![(42, 42), (-1, -1), (i32::MIN, i32::MIN as i64)];
+        for (arg, want) in tests {
+            let result = runtime.call("extend", vec![Value::I32(arg)])?;
+            assert_eq!(result, Some(Value::I64(want)));
+        }
+        Ok(())
+    }
+
     #[test]
     fn fib() -> Result<()> {
         let wasm = wat::parse_file("src/fixtures/fib.wat")?;
@@ -7,8 +7,13 @@ use crate::binary::{
 };
 use anyhow::{anyhow, bail, Result};
 
+use super::value::Value;
+
 pub const PAGE_SIZE: u32 = 65536; // 64KiB
 
+// 最大値が指定されていない時の上限 (アドレスが i32 で表せる 4GiB まで)
+pub const MAX_PAGES: u32 = 65536;
+
 #[derive(Clone)]
 pub struct Func {
     pub locals: Vec<ValueType>,
@@ -50,11 +55,40 @@ pub struct MemoryInst {
     pub max: Option<u32>,
 }
 
+impl MemoryInst {
+    // 現在のページ数
+    pub fn size(&self) -> u32 {
+        (self.data.len() / PAGE_SIZE as usize) as u32
+    }
+
+    // メモリを n ページ増やして、増やす前のページ数を返す
+    // 最大値を超える場合やメモリを確保できない場合は何もせずに None を返す
+    pub fn grow(&mut self, n: u32) -> Option<u32> {
+        let size = self.size();
+        let new_size = size.checked_add(n)?;
+        if new_size > self.max.unwrap_or(MAX_PAGES) {
+            return None;
+        }
+        // 最大値を指定していないと 4GiB まで増やせるので、確保に失敗してもプロセスを止めない
+        let new_length = new_size as usize * PAGE_SIZE as usize;
+        self.data.try_reserve(new_length - self.data.len()).ok()?;
+        self.data.resize(new_length, 0);
+        Some(size)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GlobalInst {
+    pub value: Value,
+    pub mutable: bool,
+}
+
 #[derive(Default)]
 pub struct Store {
     pub funcs: Vec<FuncInst>,
     pub module: ModuleInst,
     pub memories: Vec<MemoryInst>,
+    pub globals: Vec<GlobalInst>,
 }
 
 impl Store {
@@ -66,6 +100,7 @@ impl Store {
 
         let mut funcs = vec![];
         let mut memories = vec![];
+        let mut globals = vec![];
 
         // 先に import された関数を funcs に追加
         if let Some(ref import_section) = module.import_section {
@@ -164,9 +199,25 @@ impl Store {
             }
         }
 
+        if let Some(ref global_section) = module.global_section {
+            for global in global_section {
+                // 初期値の命令を実行して、グローバル変数の値を決める
+                let value = match global.init {
+                    Instruction::I32Const(value) => Value::I32(value),
+                    Instruction::I64Const(value) => Value::I64(value),
+                    _ => bail!("unsupported init expression of global."),
+                };
+                globals.push(GlobalInst {
+                    value,
+                    mutable: global.mutable,
+                });
+            }
+        }
+
         Ok(Self {
             funcs,
             memories,
+            globals,
             module: module_inst,
         })
     }
@@ -176,7 +227,10 @@ impl Store {
 mod test {
     use anyhow::Result;
 
-    use crate::{binary::module::Module, execution::store::Store};
+    use crate::{
+        binary::module::Module,
+        execution::{store::Store, value::Value},
+    };
 
     #[test]
     fn init_memory() -> Result<()> {
@@ -189,4 +243,17 @@ mod test {
         assert_eq!(&store.memories[0].data[5..10], b"world");
         Ok(())
     }
+
+    #[test]
+    fn init_globals() -> Result<()> {
+        let wasm = wat::parse_file("src/fixtures/global.wat")?;
+        let module = Module::new(&wasm)?;
+        let store = Store::new(module)?;
+        assert_eq!(store.globals.len(), 2);
+        assert_eq!(store.globals[0].value, Value::I32(42));
+        assert!(!store.globals[0].mutable);
+        assert_eq!(store.globals[1].value, Value::I64(1 << 32));
+        assert!(store.globals[1].mutable);
+        Ok(())
+    }
 }
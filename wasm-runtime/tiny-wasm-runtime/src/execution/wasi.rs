@@ -75,7 +75,7 @@ fn memory_read(buf: &[u8], start: usize) -> Result<i32> {
     Ok(<i32>::from_le_bytes(buf[start..end].try_into()?))
 }
 
-fn memory_write(buf: &mut Vec<u8>, start: usize, data: &[u8]) -> Result<()> {
+fn memory_write(buf: &mut [u8], start: usize, data: &[u8]) -> Result<()> {
     let end = start + data.len();
     buf[start..end].copy_from_slice(data);
     Ok(())
@@ -0,0 +1,13 @@
+(module
+  (global $const i32 (i32.const 42))
+  (global $counter (mut i64) (i64.const 4294967296))
+  (func (export "get_const") (result i32)
+    (global.get $const)
+  )
+  (func (export "get_counter") (result i64)
+    (global.get $counter)
+  )
+  (func (export "set_counter") (param i64)
+    (global.set $counter (local.get 0))
+  )
+)
@@ -0,0 +1,6 @@
+(module
+  (func $i64_const (result i64)
+    (i64.const 4294967296)
+  )
+  (export "i64_const" (func $i64_const))
+)
@@ -0,0 +1,10 @@
+(module
+  (func (export "wrap") (param i64) (result i32)
+    (local.get 0)
+    i32.wrap_i64
+  )
+  (func (export "extend") (param i32) (result i64)
+    (local.get 0)
+    i64.extend_i32_s
+  )
+)
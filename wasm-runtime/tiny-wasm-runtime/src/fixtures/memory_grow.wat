@@ -0,0 +1,14 @@
+(module
+  (memory 1 2)
+  (func (export "memory_size") (result i32)
+    memory.size
+  )
+  (func (export "memory_grow") (param i32) (result i32)
+    (memory.grow (local.get 0))
+  )
+  (func (export "grow_and_store") (result i32) (local i32)
+    (local.set 0 (memory.grow (i32.const 1)))
+    (i32.store (i32.const 65536) (i32.const 42))
+    (local.get 0)
+  )
+)